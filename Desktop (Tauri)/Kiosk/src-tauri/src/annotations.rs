@@ -144,18 +144,14 @@ pub fn get_annotations(path: &str) -> Result<Vec<AnnotationData>, AnnotationErro
     
     // Iterate through pages
     for (page_num, page_id) in doc.get_pages() {
-        if let Ok(page) = doc.get_object(page_id) {
-            if let Object::Dictionary(page_dict) = page {
-                // Get the Annots array
-                if let Ok(annots) = page_dict.get(b"Annots") {
-                    if let Ok(annot_array) = doc.get_object(get_object_id(annots)?) {
-                        if let Object::Array(annot_refs) = annot_array {
-                            for annot_ref in annot_refs {
-                                if let Ok(annot_obj) = doc.get_object(get_object_id(&annot_ref)?) {
-                                    if let Some(annot) = parse_annotation(&doc, annot_obj, page_num - 1) {
-                                        annotations.push(annot);
-                                    }
-                                }
+        if let Ok(Object::Dictionary(page_dict)) = doc.get_object(page_id) {
+            // Get the Annots array
+            if let Ok(annots) = page_dict.get(b"Annots") {
+                if let Ok(Object::Array(annot_refs)) = doc.get_object(get_object_id(annots)?) {
+                    for annot_ref in annot_refs {
+                        if let Ok(annot_obj) = doc.get_object(get_object_id(annot_ref)?) {
+                            if let Some(annot) = parse_annotation(&doc, annot_obj, page_num - 1) {
+                                annotations.push(annot);
                             }
                         }
                     }
@@ -235,7 +231,7 @@ fn parse_annotation(_doc: &Document, obj: &Object, page_index: u32) -> Option<An
         // Get opacity
         let opacity = dict.get(b"CA")
             .ok()
-            .and_then(|o| get_number(o))
+            .and_then(get_number)
             .unwrap_or(1.0);
         
         // Map PDF subtype to our annotation type
@@ -301,7 +297,7 @@ fn parse_annotation(_doc: &Document, obj: &Object, page_index: u32) -> Option<An
             .ok()
             .and_then(|o| {
                 if let Object::Dictionary(bs) = o {
-                    bs.get(b"W").ok().and_then(|w| get_number(w))
+                    bs.get(b"W").ok().and_then(get_number)
                 } else {
                     None
                 }
@@ -357,7 +353,7 @@ pub fn save_annotations(
     // Add annotations to each page
     for (page_num, annots) in annotations_by_page {
         let page_id = pages.get(&(page_num + 1))
-            .ok_or_else(|| AnnotationError::InvalidPage(page_num))?;
+            .ok_or(AnnotationError::InvalidPage(page_num))?;
         
         // Create annotation objects
         let mut annot_refs: Vec<Object> = Vec::new();
@@ -368,27 +364,25 @@ pub fn save_annotations(
         }
         
         // Get existing annotations if any
-        if let Ok(page) = doc.get_object(*page_id).cloned() {
-            if let Object::Dictionary(mut page_dict) = page {
-                // Get existing Annots array or create new one
-                let mut existing_annots: Vec<Object> = Vec::new();
-                if let Ok(annots_obj) = page_dict.get(b"Annots") {
-                    if let Object::Reference(annots_ref) = annots_obj {
-                        if let Ok(Object::Array(arr)) = doc.get_object(*annots_ref) {
-                            existing_annots = arr.clone();
-                        }
-                    } else if let Object::Array(arr) = annots_obj {
+        if let Ok(Object::Dictionary(mut page_dict)) = doc.get_object(*page_id).cloned() {
+            // Get existing Annots array or create new one
+            let mut existing_annots: Vec<Object> = Vec::new();
+            if let Ok(annots_obj) = page_dict.get(b"Annots") {
+                if let Object::Reference(annots_ref) = annots_obj {
+                    if let Ok(Object::Array(arr)) = doc.get_object(*annots_ref) {
                         existing_annots = arr.clone();
                     }
+                } else if let Object::Array(arr) = annots_obj {
+                    existing_annots = arr.clone();
                 }
-                
-                // Combine existing and new annotations
-                existing_annots.extend(annot_refs);
-                
-                // Update page dictionary with new Annots array
-                page_dict.set(b"Annots", Object::Array(existing_annots));
-                doc.set_object(*page_id, Object::Dictionary(page_dict));
             }
+            
+            // Combine existing and new annotations
+            existing_annots.extend(annot_refs);
+            
+            // Update page dictionary with new Annots array
+            page_dict.set(b"Annots", Object::Array(existing_annots));
+            doc.set_object(*page_id, Object::Dictionary(page_dict));
         }
     }
     
@@ -526,58 +520,56 @@ pub fn remove_annotation(
     
     let pages = doc.get_pages();
     let page_id = pages.get(&(page_index + 1))
-        .ok_or_else(|| AnnotationError::InvalidPage(page_index))?;
+        .ok_or(AnnotationError::InvalidPage(page_index))?;
     
     let mut found = false;
     
-    if let Ok(page) = doc.get_object(*page_id).cloned() {
-        if let Object::Dictionary(mut page_dict) = page {
-            if let Ok(annots_obj) = page_dict.get(b"Annots").cloned() {
-                let mut annots_array: Vec<Object> = Vec::new();
-                
-                // Get the annots array (either direct or reference)
-                if let Object::Reference(annots_ref) = &annots_obj {
-                    if let Ok(Object::Array(arr)) = doc.get_object(*annots_ref) {
-                        annots_array = arr.clone();
-                    }
-                } else if let Object::Array(arr) = &annots_obj {
+    if let Ok(Object::Dictionary(mut page_dict)) = doc.get_object(*page_id).cloned() {
+        if let Ok(annots_obj) = page_dict.get(b"Annots").cloned() {
+            let mut annots_array: Vec<Object> = Vec::new();
+            
+            // Get the annots array (either direct or reference)
+            if let Object::Reference(annots_ref) = &annots_obj {
+                if let Ok(Object::Array(arr)) = doc.get_object(*annots_ref) {
                     annots_array = arr.clone();
                 }
-                
-                // Filter out the annotation that matches the rect
-                let tolerance = 1.0; // 1 PDF point tolerance
-                let filtered: Vec<Object> = annots_array.into_iter().filter(|annot_ref| {
-                    if let Object::Reference(ref_id) = annot_ref {
-                        if let Ok(Object::Dictionary(annot_dict)) = doc.get_object(*ref_id) {
-                            if let Ok(Object::Array(r)) = annot_dict.get(b"Rect") {
-                                if r.len() == 4 {
-                                    if let (Some(x1), Some(y1), Some(x2), Some(y2)) = (
-                                        get_number(&r[0]),
-                                        get_number(&r[1]),
-                                        get_number(&r[2]),
-                                        get_number(&r[3]),
-                                    ) {
-                                        // Check if rects match within tolerance
-                                        if (x1 - rect.x1).abs() < tolerance &&
-                                           (y1 - rect.y1).abs() < tolerance &&
-                                           (x2 - rect.x2).abs() < tolerance &&
-                                           (y2 - rect.y2).abs() < tolerance {
-                                            found = true;
-                                            return false; // Remove this annotation
-                                        }
+            } else if let Object::Array(arr) = &annots_obj {
+                annots_array = arr.clone();
+            }
+            
+            // Filter out the annotation that matches the rect
+            let tolerance = 1.0; // 1 PDF point tolerance
+            let filtered: Vec<Object> = annots_array.into_iter().filter(|annot_ref| {
+                if let Object::Reference(ref_id) = annot_ref {
+                    if let Ok(Object::Dictionary(annot_dict)) = doc.get_object(*ref_id) {
+                        if let Ok(Object::Array(r)) = annot_dict.get(b"Rect") {
+                            if r.len() == 4 {
+                                if let (Some(x1), Some(y1), Some(x2), Some(y2)) = (
+                                    get_number(&r[0]),
+                                    get_number(&r[1]),
+                                    get_number(&r[2]),
+                                    get_number(&r[3]),
+                                ) {
+                                    // Check if rects match within tolerance
+                                    if (x1 - rect.x1).abs() < tolerance &&
+                                       (y1 - rect.y1).abs() < tolerance &&
+                                       (x2 - rect.x2).abs() < tolerance &&
+                                       (y2 - rect.y2).abs() < tolerance {
+                                        found = true;
+                                        return false; // Remove this annotation
                                     }
                                 }
                             }
                         }
                     }
-                    true // Keep this annotation
-                }).collect();
-                
-                // Update the page with filtered annotations
-                if found {
-                    page_dict.set(b"Annots", Object::Array(filtered));
-                    doc.set_object(*page_id, Object::Dictionary(page_dict));
                 }
+                true // Keep this annotation
+            }).collect();
+            
+            // Update the page with filtered annotations
+            if found {
+                page_dict.set(b"Annots", Object::Array(filtered));
+                doc.set_object(*page_id, Object::Dictionary(page_dict));
             }
         }
     }
@@ -601,27 +593,25 @@ pub fn clear_page_annotations(
     
     let pages = doc.get_pages();
     let page_id = pages.get(&(page_index + 1))
-        .ok_or_else(|| AnnotationError::InvalidPage(page_index))?;
+        .ok_or(AnnotationError::InvalidPage(page_index))?;
     
     let mut count = 0;
     
-    if let Ok(page) = doc.get_object(*page_id).cloned() {
-        if let Object::Dictionary(mut page_dict) = page {
-            // Count existing annotations
-            if let Ok(annots_obj) = page_dict.get(b"Annots") {
-                if let Object::Reference(annots_ref) = annots_obj {
-                    if let Ok(Object::Array(arr)) = doc.get_object(*annots_ref) {
-                        count = arr.len();
-                    }
-                } else if let Object::Array(arr) = annots_obj {
+    if let Ok(Object::Dictionary(mut page_dict)) = doc.get_object(*page_id).cloned() {
+        // Count existing annotations
+        if let Ok(annots_obj) = page_dict.get(b"Annots") {
+            if let Object::Reference(annots_ref) = annots_obj {
+                if let Ok(Object::Array(arr)) = doc.get_object(*annots_ref) {
                     count = arr.len();
                 }
+            } else if let Object::Array(arr) = annots_obj {
+                count = arr.len();
             }
-            
-            // Remove Annots key
-            page_dict.remove(b"Annots");
-            doc.set_object(*page_id, Object::Dictionary(page_dict));
         }
+        
+        // Remove Annots key
+        page_dict.remove(b"Annots");
+        doc.set_object(*page_id, Object::Dictionary(page_dict));
     }
     
     if count > 0 {
@@ -0,0 +1,490 @@
+//! Headless command-line mode.
+//!
+//! The app binary doubles as a scripting tool: when the first argument is a
+//! subcommand it runs that against `CachedPdf` / `annotations` and exits
+//! without starting the GUI.
+//!
+//! ```text
+//! kiosk info file.pdf [--json]
+//! kiosk text file.pdf [--pages 3-5]
+//! kiosk render file.pdf [--page 1] [--dpi 150] -o out.png
+//! kiosk search file.pdf "term" [--json]
+//! kiosk search file.pdf '\d+\.\d+\(a\)' --mode regex --pages 2-
+//! kiosk search file.pdf resume --ignore-accents --whole-word
+//! kiosk search file.pdf "force majeure" --join-lines
+//! kiosk search scan.pdf indemnification --mode fuzzy --max-edits 2
+//! kiosk search file.pdf --mode regex -- '-\d+'
+//! kiosk annotations export file.pdf [-o annotations.json]
+//! ```
+//!
+//! Results go to stdout, errors to stderr; the exit code is 0 on success,
+//! 1 on failure and 2 on a usage error. Windows release builds use the GUI
+//! subsystem, so output there is only visible when redirected to a file or
+//! pipe.
+
+use crate::annotations;
+use crate::export::{parse_page_range, DEFAULT_EXPORT_DPI, MAX_EXPORT_DPI};
+use crate::pdf::{
+    encode_rgba_with_quality, CachedPdf, DocumentInfo, PageImageFormat, PageInfo, PdfError,
+    RenderOptions, SearchMode, SearchNormalization, SearchOptions, DEFAULT_JPEG_QUALITY,
+};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
+
+const USAGE: &str = "\
+Usage:
+  kiosk info <file.pdf> [--json] [--password <pw>]
+  kiosk text <file.pdf> [--pages <range>] [--password <pw>]
+  kiosk render <file.pdf> -o <out.png|jpg|webp|tiff> [--page <n>] [--dpi <dpi>]
+               [--format <fmt>] [--quality <1-100>] [--no-annotations] [--password <pw>]
+  kiosk search <file.pdf> <term> [--mode text|regex|query|fuzzy] [--max-edits <n>]
+               [--pages <range>]
+               [--case-sensitive] [--ignore-accents] [--normalize] [--join-lines]
+               [--whole-word] [--max <n>] [--json] [--password <pw>]
+  kiosk annotations export <file.pdf> [-o <out.json>]
+  kiosk help
+
+Pages are 1-based; ranges look like 1-3,5,8-. Arguments after -- are never
+options, e.g. a search term that starts with -. --normalize matches ligatures,
+full-width and other compatibility forms of characters; --join-lines finds
+text across line breaks and end-of-line hyphenation. Fuzzy results are listed
+best first with their score.";
+
+/// First arguments that select the command-line mode.
+const SUBCOMMANDS: &[&str] = &["info", "text", "render", "search", "annotations", "help"];
+
+/// Options that take a value.
+const VALUE_OPTIONS: &[&str] = &[
+    "--pages", "--page", "--dpi", "-o", "--output", "--format", "--quality", "--max", "--password",
+    "--mode", "--max-edits",
+];
+
+/// Options that are plain switches.
+const SWITCHES: &[&str] = &[
+    "--json", "--case-sensitive", "--no-annotations", "--ignore-accents", "--normalize", "--join-lines",
+    "--whole-word",
+];
+
+/// Whether this process was started as `kiosk <subcommand> ...`.
+pub fn is_cli_invocation() -> bool {
+    std::env::args()
+        .nth(1)
+        .is_some_and(|arg| SUBCOMMANDS.contains(&arg.as_str()))
+}
+
+/// Run the subcommand in the process arguments; returns the exit code.
+pub fn run_cli() -> i32 {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let command = match parse_command(&args) {
+        Ok(c) => c,
+        Err(message) => {
+            eprintln!("kiosk: {}\n\n{}", message, USAGE);
+            return 2;
+        }
+    };
+    match execute(command) {
+        Ok(()) => 0,
+        Err(message) => {
+            eprintln!("kiosk: {}", message);
+            1
+        }
+    }
+}
+
+/// A parsed command line.
+#[derive(Debug, PartialEq)]
+enum Command {
+    Help,
+    Info {
+        file: String,
+        password: Option<String>,
+        json: bool,
+    },
+    Text {
+        file: String,
+        password: Option<String>,
+        pages: Option<String>,
+    },
+    Render {
+        file: String,
+        password: Option<String>,
+        /// 1-based
+        page: u32,
+        dpi: f32,
+        output: String,
+        format: PageImageFormat,
+        quality: u8,
+        annotations: bool,
+    },
+    Search {
+        file: String,
+        password: Option<String>,
+        query: String,
+        mode: SearchMode,
+        pages: Option<String>,
+        case_sensitive: bool,
+        normalization: SearchNormalization,
+        whole_word: bool,
+        max_edits: Option<u32>,
+        max_results: usize,
+        json: bool,
+    },
+    ExportAnnotations {
+        file: String,
+        output: Option<String>,
+    },
+}
+
+/// Positional arguments plus options, split without knowing the subcommand.
+struct ParsedArgs {
+    positional: Vec<String>,
+    options: HashMap<String, String>,
+    switches: Vec<String>,
+}
+
+impl ParsedArgs {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut parsed = ParsedArgs {
+            positional: Vec::new(),
+            options: HashMap::new(),
+            switches: Vec::new(),
+        };
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            if arg == "--" {
+                // Everything after `--` is positional, even if it starts with `-`.
+                parsed.positional.extend(iter.cloned());
+                break;
+            } else if VALUE_OPTIONS.contains(&arg.as_str()) {
+                let value = iter.next().ok_or_else(|| format!("{} needs a value", arg))?;
+                // `-o` is short for `--output`
+                let key = if arg == "-o" { "--output" } else { arg.as_str() };
+                parsed.options.insert(key.to_string(), value.clone());
+            } else if SWITCHES.contains(&arg.as_str()) {
+                parsed.switches.push(arg.clone());
+            } else if arg.starts_with('-') && arg.len() > 1 {
+                return Err(format!("Unknown option: {}", arg));
+            } else {
+                parsed.positional.push(arg.clone());
+            }
+        }
+        Ok(parsed)
+    }
+
+    /// Fail on options the subcommand does not take.
+    fn allow(&self, allowed: &[&str]) -> Result<(), String> {
+        let mut given = self.options.keys().chain(self.switches.iter());
+        match given.find(|o| !allowed.contains(&o.as_str())) {
+            Some(option) => Err(format!("{} does not apply here", option)),
+            None => Ok(()),
+        }
+    }
+
+    /// Exactly `N` positional arguments after the subcommand.
+    fn positional<const N: usize>(&self, names: [&str; N]) -> Result<[String; N], String> {
+        self.positional
+            .clone()
+            .try_into()
+            .map_err(|_| format!("Expected arguments: {}", names.join(" ")))
+    }
+
+    fn switch(&self, name: &str) -> bool {
+        self.switches.iter().any(|s| s == name)
+    }
+
+    fn option(&self, name: &str) -> Option<String> {
+        self.options.get(name).cloned()
+    }
+
+    fn number<T: std::str::FromStr>(&self, name: &str, default: T) -> Result<T, String> {
+        match self.options.get(name) {
+            Some(value) => value.parse().map_err(|_| format!("Invalid {}: {}", name, value)),
+            None => Ok(default),
+        }
+    }
+}
+
+fn parse_command(args: &[String]) -> Result<Command, String> {
+    let (subcommand, rest) = args.split_first().ok_or("Missing subcommand")?;
+    let parsed = ParsedArgs::parse(rest)?;
+    let password = parsed.option("--password");
+
+    match subcommand.as_str() {
+        "help" => Ok(Command::Help),
+        "info" => {
+            parsed.allow(&["--json", "--password"])?;
+            let [file] = parsed.positional(["<file.pdf>"])?;
+            Ok(Command::Info { file, password, json: parsed.switch("--json") })
+        }
+        "text" => {
+            parsed.allow(&["--pages", "--password"])?;
+            let [file] = parsed.positional(["<file.pdf>"])?;
+            Ok(Command::Text { file, password, pages: parsed.option("--pages") })
+        }
+        "render" => {
+            parsed.allow(&[
+                "--page", "--dpi", "--output", "--format", "--quality", "--no-annotations", "--password",
+            ])?;
+            let [file] = parsed.positional(["<file.pdf>"])?;
+            let output = parsed.option("--output").ok_or("render needs -o <output file>")?;
+            let format = match parsed.option("--format") {
+                Some(name) => PageImageFormat::from_name(&name),
+                None => Path::new(&output)
+                    .extension()
+                    .and_then(|e| PageImageFormat::from_name(&e.to_string_lossy())),
+            }
+            .filter(|f| *f != PageImageFormat::Rgba)
+            .ok_or("Unknown image format; use --format png|jpeg|webp|tiff")?;
+            let dpi = parsed.number("--dpi", DEFAULT_EXPORT_DPI)?;
+            if !dpi.is_finite() || dpi <= 0.0 || dpi > MAX_EXPORT_DPI {
+                return Err(format!("Invalid --dpi: {} (at most {})", dpi, MAX_EXPORT_DPI));
+            }
+            Ok(Command::Render {
+                file,
+                password,
+                page: parsed.number("--page", 1)?,
+                dpi,
+                output,
+                format,
+                quality: parsed.number("--quality", DEFAULT_JPEG_QUALITY)?,
+                annotations: !parsed.switch("--no-annotations"),
+            })
+        }
+        "search" => {
+            parsed.allow(&[
+                "--mode", "--max-edits", "--pages", "--case-sensitive", "--ignore-accents", "--normalize",
+                "--join-lines", "--whole-word", "--max", "--json", "--password",
+            ])?;
+            let [file, query] = parsed.positional(["<file.pdf>", "<term>"])?;
+            let mode = match parsed.option("--mode").as_deref() {
+                None | Some("text") => SearchMode::Text,
+                Some("regex") => SearchMode::Regex,
+                Some("query") => SearchMode::Query,
+                Some("fuzzy") => SearchMode::Fuzzy,
+                Some(other) => return Err(format!("Unknown --mode: {}", other)),
+            };
+            Ok(Command::Search {
+                file,
+                password,
+                query,
+                mode,
+                pages: parsed.option("--pages"),
+                case_sensitive: parsed.switch("--case-sensitive"),
+                normalization: SearchNormalization {
+                    compatibility: parsed.switch("--normalize"),
+                    ignore_diacritics: parsed.switch("--ignore-accents"),
+                    expand_ligatures: parsed.switch("--normalize"),
+                    join_lines: parsed.switch("--join-lines"),
+                },
+                whole_word: parsed.switch("--whole-word"),
+                max_edits: match parsed.option("--max-edits") {
+                    Some(_) => Some(parsed.number("--max-edits", 0)?),
+                    None => None,
+                },
+                max_results: parsed.number("--max", usize::MAX)?,
+                json: parsed.switch("--json"),
+            })
+        }
+        "annotations" => {
+            parsed.allow(&["--output"])?;
+            let [action, file] = parsed.positional(["export", "<file.pdf>"])?;
+            if action != "export" {
+                return Err(format!("Unknown annotations action: {}", action));
+            }
+            Ok(Command::ExportAnnotations { file, output: parsed.option("--output") })
+        }
+        other => Err(format!("Unknown subcommand: {}", other)),
+    }
+}
+
+fn open(file: &str, password: Option<&str>) -> Result<CachedPdf, String> {
+    let bytes = std::fs::read(file).map_err(|e| format!("{}: {}", file, e))?;
+    CachedPdf::new(bytes, password).map_err(|e| match e {
+        PdfError::PasswordRequired => format!("{}: password required (use --password)", file),
+        e => format!("{}: {}", file, e),
+    })
+}
+
+fn print_json<T: Serialize>(value: &T) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    println!("{}", json);
+    Ok(())
+}
+
+fn execute(command: Command) -> Result<(), String> {
+    match command {
+        Command::Help => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        Command::Info { file, password, json } => {
+            let pdf = open(&file, password.as_deref())?;
+            let info = pdf.get_document_info().map_err(|e| e.to_string())?;
+            let pages = pdf.get_all_page_infos().map_err(|e| e.to_string())?;
+            if json {
+                #[derive(Serialize)]
+                struct Info<'a> {
+                    #[serde(flatten)]
+                    info: &'a DocumentInfo,
+                    pages: &'a [PageInfo],
+                }
+                return print_json(&Info { info: &info, pages: &pages });
+            }
+            println!("Title:       {}", info.title.as_deref().unwrap_or("-"));
+            println!("Author:      {}", info.author.as_deref().unwrap_or("-"));
+            println!("PDF version: {}", info.pdf_version);
+            println!("Pages:       {}", info.page_count);
+            for page in &pages {
+                let rotation = if page.rotation != 0 { format!(", rotated {}", page.rotation) } else { String::new() };
+                println!("  {:>4}: {:.0} x {:.0} pt{}", page.index + 1, page.width, page.height, rotation);
+            }
+            Ok(())
+        }
+        Command::Text { file, password, pages } => {
+            let pdf = open(&file, password.as_deref())?;
+            let page_count = pdf.get_document_info().map_err(|e| e.to_string())?.page_count;
+            let pages = match pages {
+                Some(spec) => parse_page_range(&spec, page_count)?,
+                None => (0..page_count).collect(),
+            };
+            // Pages are separated by a form feed, as pdftotext does.
+            let mut out = std::io::stdout().lock();
+            for (i, &page_index) in pages.iter().enumerate() {
+                let text = pdf.get_page_text(page_index).map_err(|e| e.to_string())?;
+                let separator = if i + 1 < pages.len() { "\n\x0c" } else { "\n" };
+                write!(out, "{}{}", text, separator).map_err(|e| e.to_string())?;
+            }
+            Ok(())
+        }
+        Command::Render { file, password, page, dpi, output, format, quality, annotations } => {
+            let pdf = open(&file, password.as_deref())?;
+            let page_index = page.checked_sub(1).ok_or("--page is 1-based")?;
+            let options = RenderOptions { annotations, ..RenderOptions::default() };
+            pdf.check_render_size(page_index, dpi / 72.0).map_err(|e| e.to_string())?;
+            let (pixels, width, height) = pdf
+                .render_page_to_rgba(page_index, dpi / 72.0, &options)
+                .map_err(|e| e.to_string())?;
+            let data = encode_rgba_with_quality(&pixels, width, height, format, quality)
+                .map_err(|e| e.to_string())?;
+            std::fs::write(&output, data).map_err(|e| format!("{}: {}", output, e))?;
+            eprintln!("Wrote {} ({}x{})", output, width, height);
+            Ok(())
+        }
+        Command::Search {
+            file,
+            password,
+            query,
+            mode,
+            pages,
+            case_sensitive,
+            normalization,
+            whole_word,
+            max_edits,
+            max_results,
+            json,
+        } => {
+            let pdf = open(&file, password.as_deref())?;
+            let pages = match pages {
+                Some(spec) => {
+                    let page_count = pdf.get_document_info().map_err(|e| e.to_string())?.page_count;
+                    Some(parse_page_range(&spec, page_count)?)
+                }
+                None => None,
+            };
+            let options = SearchOptions { case_sensitive, mode, normalization, whole_word, max_edits, pages };
+            let results = pdf
+                .search_text(&query, &options, max_results)
+                .map_err(|e| e.to_string())?;
+            if json {
+                return print_json(&results);
+            }
+            for result in &results {
+                if mode == SearchMode::Fuzzy {
+                    print!("({:.2}) ", result.score);
+                }
+                println!("page {}, char {}: {}", result.page + 1, result.start_index, result.text);
+            }
+            Ok(())
+        }
+        Command::ExportAnnotations { file, output } => {
+            let annotations = annotations::get_annotations(&file).map_err(|e| format!("{}: {}", file, e))?;
+            match output {
+                Some(path) => {
+                    let json = serde_json::to_string_pretty(&annotations).map_err(|e| e.to_string())?;
+                    std::fs::write(&path, json).map_err(|e| format!("{}: {}", path, e))?;
+                    eprintln!("Wrote {} annotations to {}", annotations.len(), path);
+                    Ok(())
+                }
+                None => print_json(&annotations),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(
+            parse_command(&args("render a.pdf --page 2 --dpi 300 -o out.jpg")).unwrap(),
+            Command::Render {
+                file: "a.pdf".into(),
+                password: None,
+                page: 2,
+                dpi: 300.0,
+                output: "out.jpg".into(),
+                format: PageImageFormat::Jpeg,
+                quality: DEFAULT_JPEG_QUALITY,
+                annotations: true,
+            }
+        );
+        assert_eq!(
+            parse_command(&args("annotations export a.pdf")).unwrap(),
+            Command::ExportAnnotations { file: "a.pdf".into(), output: None }
+        );
+        assert!(matches!(
+            parse_command(&args("search a.pdf term --json")).unwrap(),
+            Command::Search { json: true, case_sensitive: false, .. }
+        ));
+        assert!(matches!(
+            parse_command(&args("search a.pdf term --mode regex --pages 2-")).unwrap(),
+            Command::Search { mode: SearchMode::Regex, pages: Some(_), .. }
+        ));
+        assert!(matches!(
+            parse_command(&args("search a.pdf resume --ignore-accents --whole-word")).unwrap(),
+            Command::Search {
+                normalization: SearchNormalization { ignore_diacritics: true, compatibility: false, .. },
+                whole_word: true,
+                ..
+            }
+        ));
+        assert!(matches!(
+            parse_command(&args("search a.pdf term --mode fuzzy --max-edits 2")).unwrap(),
+            Command::Search { mode: SearchMode::Fuzzy, max_edits: Some(2), .. }
+        ));
+        assert!(parse_command(&args("search a.pdf term --mode nearest")).is_err());
+        // Terms starting with `-` after `--`
+        assert!(matches!(
+            parse_command(&args("search a.pdf --mode regex -- -\\d+")).unwrap(),
+            Command::Search { mode: SearchMode::Regex, ref query, .. } if query == "-\\d+"
+        ));
+        assert!(matches!(
+            parse_command(&args("search a.pdf -- -5")).unwrap(),
+            Command::Search { ref query, .. } if query == "-5"
+        ));
+        assert!(parse_command(&args("search a.pdf -5")).is_err());
+        // Missing output, unknown option, option of another subcommand
+        assert!(parse_command(&args("render a.pdf")).is_err());
+        assert!(parse_command(&args("info a.pdf --verbose")).is_err());
+        assert!(parse_command(&args("info a.pdf --dpi 72")).is_err());
+        assert!(parse_command(&args("text")).is_err());
+    }
+}
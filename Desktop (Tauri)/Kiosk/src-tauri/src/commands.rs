@@ -13,17 +13,84 @@
 //! the full PDF byte buffer. The outer documents Mutex is held only for the
 //! Arc clone (~microseconds); the per-document CachedPdf Mutex serializes
 //! operations on the same document without blocking other documents.
+//!
+//! Full-page renders go through a per-document LRU `RenderCache` bounded by a
+//! global byte budget, so revisiting a page does not re-rasterize it.
+//!
+//! Foreground renders are tracked per document (`ForegroundRenders`) so the
+//! background prefetch worker can stay out of their way; see `prefetch`.
+//!
+//! Cache misses are admitted to pdfium one at a time per document by a
+//! `RenderScheduler`: waiting requests run highest priority first, and a
+//! request tagged with a newer render cycle id drops every queued request of
+//! an older cycle before it reaches pdfium.
+//!
+//! Optionally (`set_render_workers`) cache misses are rendered by a pool of
+//! helper processes instead (see `render_worker`), which lets pages of one
+//! document render in parallel and survives pdfium crashes.
+//!
+//! Each document carries an `AccessibilityFilter` (`set_accessibility_filter`)
+//! that is applied to every page, tile and thumbnail rendered for it.
+//!
+//! Pages can be written to image files with `export_pages_as_images` (see
+//! `export`), rendered the same way as on screen.
+//!
+//! Each document also carries `ViewRotations` (`set_view_rotation`): renders
+//! are rotated, and page sizes, character boxes and search highlights are
+//! reported in the rotated view, without modifying the file.
+//!
+//! Layer visibility (`set_layer_visibility`, see `optional_content`) swaps
+//! the document's `CachedPdf` for one opened from a rewritten copy; the
+//! bytes as opened are kept in `DocumentState.layers` meanwhile.
+//!
+//! Heavy pages can be rendered progressively (`start_progressive_render`,
+//! see `progressive`): intermediate frames are published while pdfium draws,
+//! and the render can be paused, resumed and cancelled.
 
 use crate::annotations::{self, AnnotationData, PdfRect, SaveResult};
+use crate::export::{
+    export_file_name, parse_page_range, validate_pattern, ExportProgress, ExportResult,
+    ImageExportOptions, EXPORT_PROGRESS_EVENT, MAX_EXPORT_DPI,
+};
+use crate::optional_content::{
+    read_optional_content, with_layer_visibility, LayerOverrides, OptionalContent,
+};
 use crate::pdf::{
-    CachedPdf, CharRect, DocumentInfo, PageInfo, PdfError, SearchResult,
+    apply_accessibility_filter, encode_rgba, encode_rgba_frame, encode_rgba_with_quality,
+    set_max_render_pixels as set_pdf_max_render_pixels, AccessibilityFilter, CachedPdf, CharRect, DocumentInfo, PageImageFormat, PageInfo, PdfError,
+    CompiledQuery, RenderOptions, SearchIndex, SearchMode, SearchNormalization, SearchOptions, SearchResult,
+    ViewRotation, ViewRotations,
+};
+use crate::prefetch::{
+    ForegroundRenders, Prefetcher, ScrollDirection, ViewportHint, DEFAULT_PREFETCH_PAGES,
+    MAX_PREFETCH_PAGES, PREFETCH_BACKOFF,
+};
+use crate::progressive::{
+    ProgressiveRender, ProgressiveRenders, ProgressiveStatus, ProgressiveUpdate,
+    PROGRESSIVE_FRAME_INTERVAL, PROGRESSIVE_RENDER_EVENT, PROGRESSIVE_SLICE,
+};
+use crate::render_cache::{
+    CachedRender, RenderCache, RenderCacheBudget, RenderCacheKey, RenderCacheStats,
+    DEFAULT_RENDER_CACHE_BUDGET,
+};
+use crate::render_worker::{RenderWorkerPool, MAX_RENDER_WORKERS};
+use crate::search_index::{SearchIndexCache, SEARCH_INDEX_CACHE_DIR};
+use crate::search_session::{
+    SearchResultsPage, SearchSession, SearchSessions, SearchStatus, DEFAULT_SESSION_MAX_RESULTS,
+    SEARCH_PROGRESS_EVENT, SEARCH_PROGRESS_INTERVAL,
+};
+use crate::thumbnail_cache::{
+    content_hash, thumbnail_variant, StoredThumbnail, ThumbnailCache, THUMBNAIL_CACHE_DIR,
 };
 use base64::Engine as _;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
 use std::time::Instant;
-use tauri::State;
+use tauri::ipc::Response;
+use tauri::{AppHandle, Emitter, Manager, State};
 
 /// Phase 4: Result of a page render — raw RGBA pixels + dimensions.
 ///
@@ -39,6 +106,23 @@ pub struct RenderResult {
     pub width: u32,
     /// Pixel height of the rendered image
     pub height: u32,
+    /// Scale the page was rendered at; below the requested scale when the
+    /// page was downscaled to fit the pixel limit (`set_max_render_pixels`)
+    pub scale: f32,
+}
+
+/// Result of a region (tile) render — a `RenderResult` plus the tile origin.
+///
+/// The origin is the pixel-snapped top-left corner of the tile in PDF points,
+/// which may differ slightly from the requested region.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RegionRenderResult {
+    #[serde(flatten)]
+    pub render: RenderResult,
+    /// Left edge of the tile in PDF points (top-left origin)
+    pub x: f32,
+    /// Top edge of the tile in PDF points (top-left origin)
+    pub y: f32,
 }
 
 /// Phase 1 diagnostic flag. Set to true to emit timing logs for mutex
@@ -54,10 +138,146 @@ macro_rules! diag {
     };
 }
 
+// ============================================================================
+// Render Scheduling
+// ============================================================================
+
+/// Error returned for renders dropped by the scheduler. The frontend matches
+/// on this message to ignore superseded renders quietly.
+pub const RENDER_CANCELLED: &str = "Render cancelled";
+
+/// Scheduling priority of a render request. Higher priorities are admitted
+/// to pdfium first; equal priorities run in arrival order.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RenderPriority {
+    /// Thumbnails and other background work
+    Low,
+    /// Quality upgrades and protocol requests
+    #[default]
+    Normal,
+    /// Pages the user is looking at right now
+    High,
+}
+
+/// A render request waiting for its turn.
+struct RenderTicket {
+    seq: u64,
+    priority: RenderPriority,
+    cycle_id: Option<u64>,
+}
+
+#[derive(Default)]
+struct SchedulerQueue {
+    waiting: Vec<RenderTicket>,
+    /// Requests currently holding a render slot
+    running: usize,
+    next_seq: u64,
+    /// Requests tagged with an older cycle id than this are cancelled
+    min_cycle: u64,
+}
+
+impl SchedulerQueue {
+    fn is_cancelled(&self, cycle_id: Option<u64>) -> bool {
+        cycle_id.is_some_and(|c| c < self.min_cycle)
+    }
+
+    /// Sequence number of the live ticket that should run next.
+    fn next_ticket(&self) -> Option<u64> {
+        self.waiting
+            .iter()
+            .filter(|t| !self.is_cancelled(t.cycle_id))
+            .max_by_key(|t| (t.priority, std::cmp::Reverse(t.seq)))
+            .map(|t| t.seq)
+    }
+
+    fn remove(&mut self, seq: u64) {
+        self.waiting.retain(|t| t.seq != seq);
+    }
+}
+
+/// Per-document admission queue in front of pdfium.
+///
+/// At most `concurrency` scheduled renders per document run at a time: one
+/// in process, or one per worker when the worker pool is enabled. Requests carrying
+/// a render cycle id (the frontend's `renderCycleId`) are cancelled while
+/// still queued once a newer cycle has been seen for the document, either
+/// from another render request or from `cancel_renders`. A render that has
+/// already entered pdfium always completes.
+#[derive(Default)]
+pub struct RenderScheduler {
+    queue: Mutex<SchedulerQueue>,
+    changed: Condvar,
+}
+
+/// A render slot of a document, released on drop.
+pub struct RenderSlot<'a>(&'a RenderScheduler);
+
+impl Drop for RenderSlot<'_> {
+    fn drop(&mut self) {
+        self.0.queue.lock().unwrap().running -= 1;
+        self.0.changed.notify_all();
+    }
+}
+
+impl RenderScheduler {
+    /// Wait until this request may render. Fails with `RENDER_CANCELLED` if
+    /// its cycle is superseded before it is admitted.
+    pub fn acquire(
+        &self,
+        priority: RenderPriority,
+        cycle_id: Option<u64>,
+        concurrency: usize,
+    ) -> Result<RenderSlot<'_>, String> {
+        let mut queue = self.queue.lock().unwrap();
+        if queue.is_cancelled(cycle_id) {
+            return Err(RENDER_CANCELLED.to_string());
+        }
+        if let Some(cycle) = cycle_id {
+            if cycle > queue.min_cycle {
+                queue.min_cycle = cycle;
+                self.changed.notify_all();
+            }
+        }
+
+        let seq = queue.next_seq;
+        queue.next_seq += 1;
+        queue.waiting.push(RenderTicket { seq, priority, cycle_id });
+
+        loop {
+            if queue.is_cancelled(cycle_id) {
+                queue.remove(seq);
+                self.changed.notify_all();
+                return Err(RENDER_CANCELLED.to_string());
+            }
+            if queue.running < concurrency.max(1) && queue.next_ticket() == Some(seq) {
+                queue.remove(seq);
+                queue.running += 1;
+                return Ok(RenderSlot(self));
+            }
+            queue = self.changed.wait(queue).unwrap();
+        }
+    }
+
+    /// Cancel every queued request tagged with a cycle older than `cycle_id`.
+    pub fn cancel_before(&self, cycle_id: u64) {
+        let mut queue = self.queue.lock().unwrap();
+        if cycle_id > queue.min_cycle {
+            queue.min_cycle = cycle_id;
+            self.changed.notify_all();
+        }
+    }
+
+    /// Number of requests waiting for the slot.
+    pub fn pending(&self) -> usize {
+        self.queue.lock().unwrap().waiting.len()
+    }
+}
+
 /// Helper: clone the Arc<Mutex<CachedPdf>> out of the locked state map.
 /// The outer documents Mutex is held only for the HashMap lookup + Arc clone,
 /// then released. All expensive operations run against the cloned Arc.
-fn clone_cached_pdf(doc_id: &str, state: &State<AppState>) -> Result<Arc<Mutex<CachedPdf>>, String> {
+pub(crate) fn clone_cached_pdf(doc_id: &str, state: &State<AppState>) -> Result<Arc<Mutex<CachedPdf>>, String> {
     let start = Instant::now();
     let cached = {
         let docs = state.documents.lock().unwrap();
@@ -70,6 +290,235 @@ fn clone_cached_pdf(doc_id: &str, state: &State<AppState>) -> Result<Arc<Mutex<C
     Ok(cached)
 }
 
+/// Shared handles needed to render a page through the cache.
+struct RenderHandles {
+    cached_pdf: Arc<Mutex<CachedPdf>>,
+    render_cache: Arc<Mutex<RenderCache>>,
+    foreground: Arc<ForegroundRenders>,
+    scheduler: Arc<RenderScheduler>,
+    /// Whether the document may be rendered in a worker process
+    isolatable: bool,
+    filter: AccessibilityFilter,
+    rotations: Arc<ViewRotations>,
+    /// Render cache generation the handles were taken at; renders from
+    /// them are only cached while it is current
+    generation: u64,
+}
+
+impl RenderHandles {
+    /// `options` with the document's view rotation of `page_index` applied.
+    fn page_options(&self, page_index: u32, options: &RenderOptions) -> RenderOptions {
+        RenderOptions {
+            rotation: self.rotations.for_page(page_index).then(options.rotation),
+            ..*options
+        }
+    }
+}
+
+/// Helper: clone the render handles for a document under a single brief
+/// hold of the outer documents Mutex.
+fn clone_render_handles(doc_id: &str, state: &AppState) -> Result<RenderHandles, String> {
+    let docs = state.documents.lock().unwrap();
+    let doc_state = docs
+        .get(doc_id)
+        .ok_or_else(|| "Document not found".to_string())?;
+    let generation = doc_state.render_cache.lock().unwrap().generation();
+    Ok(RenderHandles {
+        cached_pdf: Arc::clone(&doc_state.cached_pdf),
+        render_cache: Arc::clone(&doc_state.render_cache),
+        foreground: Arc::clone(&doc_state.foreground),
+        scheduler: Arc::clone(&doc_state.scheduler),
+        isolatable: !doc_state.password_protected,
+        filter: doc_state.filter,
+        rotations: Arc::clone(&doc_state.view_rotations),
+        generation,
+    })
+}
+
+/// Helper: clone the cached PDF and view rotations of a document, for
+/// commands that report geometry in the rotated view.
+fn clone_pdf_and_rotations(
+    doc_id: &str,
+    state: &AppState,
+) -> Result<(Arc<Mutex<CachedPdf>>, Arc<ViewRotations>), String> {
+    let docs = state.documents.lock().unwrap();
+    let doc_state = docs
+        .get(doc_id)
+        .ok_or_else(|| "Document not found".to_string())?;
+    Ok((Arc::clone(&doc_state.cached_pdf), Arc::clone(&doc_state.view_rotations)))
+}
+
+/// Helper: render cache `options_key` for render options plus filter; 0 when
+/// both are the defaults.
+fn render_options_key(options: &RenderOptions, filter: &AccessibilityFilter) -> u64 {
+    options.fingerprint() ^ filter.fingerprint().rotate_left(32)
+}
+
+/// Helper: the worker pool to render `doc_id` with, if the pool is enabled
+/// and the document may leave the process. Registers the document's bytes
+/// with the pool on first use; commands that replace the bytes later
+/// register the new ones themselves.
+fn worker_pool_for(
+    doc_id: &str,
+    handles: &RenderHandles,
+    state: &AppState,
+) -> Option<Arc<RenderWorkerPool>> {
+    if !handles.isolatable {
+        return None;
+    }
+    let pool = state.render_workers.lock().unwrap().clone()?;
+    if !pool.has_document(doc_id) {
+        let bytes = handles.cached_pdf.lock().unwrap().bytes().to_vec();
+        pool.register_document_if_absent(doc_id, bytes);
+    }
+    Some(pool)
+}
+
+/// Render a full page through the document's render cache.
+///
+/// On a miss the request waits its turn in the document's `RenderScheduler`,
+/// then the page is rendered at the key's quantized scale, inserted (unless
+/// the cache was invalidated meanwhile, e.g. by a layer toggle), and the
+/// global budget is enforced. Neither the cache lock nor the outer documents
+/// lock is held while pdfium renders. Each combination of `RenderOptions`
+/// and accessibility filter is cached separately. The page is rotated by
+/// the document's view rotation on top of `options.rotation`. A page too
+/// large for the pixel limit is rendered at the largest scale that fits;
+/// the result records the scale used.
+pub(crate) fn render_page_cached(
+    doc_id: &str,
+    page_index: u32,
+    scale: f32,
+    options: &RenderOptions,
+    priority: RenderPriority,
+    cycle_id: Option<u64>,
+    state: &AppState,
+) -> Result<CachedRender, String> {
+    if !scale.is_finite() || scale <= 0.0 {
+        return Err(format!("Invalid scale: {}", scale));
+    }
+    let handles = clone_render_handles(doc_id, state)?;
+    let options = &handles.page_options(page_index, options);
+    let key = RenderCacheKey::new(page_index, scale, render_options_key(options, &handles.filter));
+
+    if let Some(hit) = handles.render_cache.lock().unwrap().get(&key) {
+        diag!("render cache HIT doc={} page={} scale={:.2}", doc_id, page_index, key.scale());
+        return Ok(hit);
+    }
+
+    let pool = worker_pool_for(doc_id, &handles, state);
+    let concurrency = pool.as_ref().map_or(1, |p| p.size());
+
+    let ((mut pixels, width, height), scale) = {
+        let _foreground = handles.foreground.begin();
+        let _slot = handles
+            .scheduler
+            .acquire(priority, cycle_id, concurrency)
+            .inspect_err(|_| {
+                diag!("render CANCELLED doc={} page={} cycle={:?}", doc_id, page_index, cycle_id);
+            })?;
+
+        // An identical request may have filled the cache while we queued.
+        if let Some(hit) = handles.render_cache.lock().unwrap().get(&key) {
+            return Ok(hit);
+        }
+
+        let pdf = handles.cached_pdf.lock().unwrap();
+        let scale = pdf
+            .effective_render_scale(page_index, key.scale())
+            .map_err(|e| e.to_string())?;
+        match &pool {
+            Some(pool) => {
+                drop(pdf);
+                pool.render_page(doc_id, page_index, scale, *options)
+            }
+            None => pdf.render_page_to_rgba(page_index, scale, options),
+        }
+        .map(|rendered| (rendered, scale))
+        .map_err(|e| e.to_string())?
+    };
+    apply_accessibility_filter(&mut pixels, &handles.filter);
+    let render = CachedRender { pixels: Arc::new(pixels), width, height, scale };
+
+    handles.render_cache.lock().unwrap().insert(handles.generation, key, render.clone());
+    state.enforce_render_budget();
+
+    Ok(render)
+}
+
+/// Render a page into the render cache on behalf of the prefetch worker.
+///
+/// Unlike `render_page_cached` this yields to the foreground: it does not
+/// start while a foreground render of the document is in flight, and it
+/// never blocks on the `CachedPdf` mutex. With the worker pool enabled the
+/// page is rendered by a worker once the foreground is quiet. Returns
+/// `Ok(false)` when the page was already cached or `cancelled` fired while
+/// waiting.
+pub(crate) fn prefetch_page_cached(
+    state: &AppState,
+    doc_id: &str,
+    page_index: u32,
+    scale: f32,
+    options: &RenderOptions,
+    cancelled: &dyn Fn() -> bool,
+) -> Result<bool, String> {
+    let handles = clone_render_handles(doc_id, state)?;
+    let options = &handles.page_options(page_index, options);
+    let key = RenderCacheKey::new(page_index, scale, render_options_key(options, &handles.filter));
+
+    if handles.render_cache.lock().unwrap().contains(&key) {
+        return Ok(false);
+    }
+
+    let pool = worker_pool_for(doc_id, &handles, state);
+
+    let ((mut pixels, width, height), scale) = loop {
+        if cancelled() {
+            return Ok(false);
+        }
+        if handles.foreground.is_active() {
+            std::thread::sleep(PREFETCH_BACKOFF);
+            continue;
+        }
+        if let Ok(pdf) = handles.cached_pdf.try_lock() {
+            let scale = pdf
+                .effective_render_scale(page_index, key.scale())
+                .map_err(|e| e.to_string())?;
+            let rendered = match &pool {
+                Some(pool) => {
+                    drop(pdf);
+                    pool.render_page(doc_id, page_index, scale, *options)
+                }
+                None => pdf.render_page_to_rgba(page_index, scale, options),
+            };
+            break (rendered.map_err(|e| e.to_string())?, scale);
+        }
+        std::thread::sleep(PREFETCH_BACKOFF);
+    };
+    apply_accessibility_filter(&mut pixels, &handles.filter);
+    let render = CachedRender { pixels: Arc::new(pixels), width, height, scale };
+
+    handles.render_cache.lock().unwrap().insert(handles.generation, key, render);
+    state.enforce_render_budget();
+
+    Ok(true)
+}
+
+/// Helper: SHA-256 of a document's bytes, computed on first use and
+/// remembered for the lifetime of the document.
+fn document_content_hash(doc_id: &str, state: &AppState) -> Result<String, String> {
+    let (cached, hash) = {
+        let docs = state.documents.lock().unwrap();
+        let doc_state = docs
+            .get(doc_id)
+            .ok_or_else(|| "Document not found".to_string())?;
+        (Arc::clone(&doc_state.cached_pdf), Arc::clone(&doc_state.content_hash))
+    };
+    Ok(hash
+        .get_or_init(|| content_hash(cached.lock().unwrap().bytes()))
+        .clone())
+}
+
 /// Helper: clone document path out of the locked state map.
 fn clone_doc_path(doc_id: &str, state: &State<AppState>) -> Result<Option<String>, String> {
     let docs = state.documents.lock().unwrap();
@@ -89,6 +538,16 @@ pub struct AppState {
     pub documents: Mutex<HashMap<String, DocumentState>>,
     /// Counter for generating document IDs
     pub next_id: Mutex<u32>,
+    /// Global byte budget shared by every document's render cache
+    pub render_budget: Arc<RenderCacheBudget>,
+    /// Latest viewport hint for the background prefetch worker
+    pub prefetcher: Prefetcher,
+    /// Out-of-process render workers, when enabled via `set_render_workers`
+    pub render_workers: Mutex<Option<Arc<RenderWorkerPool>>>,
+    /// Progressive renders, keyed by render id
+    pub progressive: ProgressiveRenders,
+    /// Search sessions, keyed by search id
+    pub searches: SearchSessions,
 }
 
 /// State for a single loaded document.
@@ -102,6 +561,29 @@ pub struct DocumentState {
     pub cached_pdf: Arc<Mutex<CachedPdf>>,
     /// File path (if loaded from file)
     pub path: Option<String>,
+    /// LRU cache of rendered page bitmaps, bounded by `AppState.render_budget`
+    pub render_cache: Arc<Mutex<RenderCache>>,
+    /// Foreground renders in flight; the prefetch worker yields while non-zero
+    pub foreground: Arc<ForegroundRenders>,
+    /// Number of pages, recorded at load time
+    pub page_count: u32,
+    /// Priority/cancellation queue for renders of this document
+    pub scheduler: Arc<RenderScheduler>,
+    /// Opened with a password; such documents are never sent to workers
+    pub password_protected: bool,
+    /// SHA-256 of the document bytes, computed lazily for on-disk caches
+    pub content_hash: Arc<OnceLock<String>>,
+    /// Accessibility filter applied to every render of this document
+    pub filter: AccessibilityFilter,
+    /// View-only page rotations; replaced wholesale by `set_view_rotation`
+    pub view_rotations: Arc<ViewRotations>,
+    /// Layer visibility overrides; `None` while every layer is at its default
+    pub layers: Option<LayerOverrides>,
+    /// Held while the document is re-opened from new bytes (layer changes,
+    /// reloads after a save), so two of them can't drop each other's change
+    pub reopen_lock: Arc<Mutex<()>>,
+    /// Full-text index, set once loaded or built in the background
+    pub search_index: Arc<OnceLock<SearchIndex>>,
 }
 
 impl AppState {
@@ -109,6 +591,45 @@ impl AppState {
         Self {
             documents: Mutex::new(HashMap::new()),
             next_id: Mutex::new(1),
+            render_budget: Arc::new(RenderCacheBudget::new(DEFAULT_RENDER_CACHE_BUDGET)),
+            prefetcher: Prefetcher::new(),
+            render_workers: Mutex::new(None),
+            progressive: ProgressiveRenders::default(),
+            searches: SearchSessions::default(),
+        }
+    }
+
+    /// Create an empty render cache drawing from the global budget.
+    fn new_render_cache(&self) -> Arc<Mutex<RenderCache>> {
+        Arc::new(Mutex::new(RenderCache::new(Arc::clone(&self.render_budget))))
+    }
+
+    /// Evict least recently used bitmaps, across all documents, until the
+    /// global render budget is respected.
+    ///
+    /// Must not be called while holding any document's render cache lock.
+    pub fn enforce_render_budget(&self) {
+        if !self.render_budget.is_over_budget() {
+            return;
+        }
+
+        let caches: Vec<Arc<Mutex<RenderCache>>> = {
+            let docs = self.documents.lock().unwrap();
+            docs.values().map(|d| Arc::clone(&d.render_cache)).collect()
+        };
+
+        while self.render_budget.is_over_budget() {
+            let oldest = caches
+                .iter()
+                .filter_map(|c| c.lock().unwrap().oldest_tick().map(|tick| (tick, c)))
+                .min_by_key(|(tick, _)| *tick);
+
+            match oldest {
+                Some((_, cache)) => {
+                    cache.lock().unwrap().evict_oldest();
+                }
+                None => break,
+            }
         }
     }
 
@@ -156,7 +677,7 @@ pub enum LoadPdfResult {
 /// response is `PasswordRequired`, prompt the user and retry with the
 /// password. The password is used only for this call and is never stored.
 #[tauri::command]
-pub fn load_pdf(path: String, password: Option<String>, state: State<AppState>) -> LoadPdfResult {
+pub fn load_pdf(path: String, password: Option<String>, app: AppHandle, state: State<AppState>) -> LoadPdfResult {
     diag!("load_pdf START path={}", path);
     let load_start = Instant::now();
 
@@ -190,9 +711,21 @@ pub fn load_pdf(path: String, password: Option<String>, state: State<AppState>)
             DocumentState {
                 cached_pdf: Arc::new(Mutex::new(cached_pdf)),
                 path: Some(path),
+                render_cache: state.new_render_cache(),
+                foreground: Arc::default(),
+                page_count: info.page_count,
+                scheduler: Arc::default(),
+                password_protected: password.is_some(),
+                content_hash: Arc::default(),
+                filter: AccessibilityFilter::None,
+                view_rotations: Arc::default(),
+                layers: None,
+                reopen_lock: Arc::default(),
+                search_index: Arc::default(),
             },
         );
     }
+    build_search_index(&app, &id);
 
     LoadPdfResult::Success { data: LoadResult { id, info } }
 }
@@ -202,7 +735,12 @@ pub fn load_pdf(path: String, password: Option<String>, state: State<AppState>)
 /// PHASE 3: Same persistent-handle strategy as load_pdf.
 /// Password support: same semantics as load_pdf.
 #[tauri::command]
-pub fn load_pdf_bytes(bytes: Vec<u8>, password: Option<String>, state: State<AppState>) -> LoadPdfResult {
+pub fn load_pdf_bytes(
+    bytes: Vec<u8>,
+    password: Option<String>,
+    app: AppHandle,
+    state: State<AppState>,
+) -> LoadPdfResult {
     diag!("load_pdf_bytes START len={}", bytes.len());
     let load_start = Instant::now();
 
@@ -229,9 +767,21 @@ pub fn load_pdf_bytes(bytes: Vec<u8>, password: Option<String>, state: State<App
             DocumentState {
                 cached_pdf: Arc::new(Mutex::new(cached_pdf)),
                 path: None,
+                render_cache: state.new_render_cache(),
+                foreground: Arc::default(),
+                page_count: info.page_count,
+                scheduler: Arc::default(),
+                password_protected: password.is_some(),
+                content_hash: Arc::default(),
+                filter: AccessibilityFilter::None,
+                view_rotations: Arc::default(),
+                layers: None,
+                reopen_lock: Arc::default(),
+                search_index: Arc::default(),
             },
         );
     }
+    build_search_index(&app, &id);
 
     LoadPdfResult::Success { data: LoadResult { id, info } }
 }
@@ -239,8 +789,21 @@ pub fn load_pdf_bytes(bytes: Vec<u8>, password: Option<String>, state: State<App
 /// Close a document and free its resources.
 #[tauri::command]
 pub fn close_pdf(doc_id: String, state: State<AppState>) -> Result<(), String> {
-    let mut docs = state.documents.lock().unwrap();
-    docs.remove(&doc_id);
+    let removed = {
+        let mut docs = state.documents.lock().unwrap();
+        docs.remove(&doc_id)
+    };
+    // Release cached bitmaps now, even if an in-flight render still holds
+    // a reference to the cache, and drop queued renders tagged with a cycle.
+    if let Some(doc_state) = removed {
+        doc_state.render_cache.lock().unwrap().clear();
+        doc_state.scheduler.cancel_before(u64::MAX);
+    }
+    state.progressive.cancel_document(&doc_id);
+    state.searches.cancel_document(&doc_id);
+    if let Some(pool) = state.render_workers.lock().unwrap().as_ref() {
+        pool.forget_document(&doc_id);
+    }
     Ok(())
 }
 
@@ -254,7 +817,7 @@ pub fn get_document_info(doc_id: String, state: State<AppState>) -> Result<Docum
     pdf.get_document_info().map_err(|e| e.to_string())
 }
 
-/// Get page info for a specific page.
+/// Get page info for a specific page, sized for its view rotation.
 ///
 /// PHASE 3: Uses cached PdfDocument — no re-parse.
 #[tauri::command]
@@ -263,9 +826,11 @@ pub fn get_page_info(
     page_index: u32,
     state: State<AppState>,
 ) -> Result<PageInfo, String> {
-    let cached = clone_cached_pdf(&doc_id, &state)?;
+    let (cached, rotations) = clone_pdf_and_rotations(&doc_id, &state)?;
     let pdf = cached.lock().unwrap();
-    pdf.get_page_info(page_index).map_err(|e| e.to_string())
+    let mut info = pdf.get_page_info(page_index).map_err(|e| e.to_string())?;
+    rotations.for_page(page_index).rotate_page_info(&mut info);
+    Ok(info)
 }
 
 /// Render a page to raw RGBA pixels.
@@ -274,6 +839,14 @@ pub fn get_page_info(
 /// * `doc_id` - Document ID from load_pdf
 /// * `page_index` - 0-based page index
 /// * `scale` - Render scale (1.0 = 72 DPI, 2.0 = 144 DPI, etc.)
+/// * `options` - Annotations, forms, anti-aliasing, background, grayscale
+///   and color mode (see `RenderOptions`; defaults when omitted)
+/// * `priority` - Scheduling priority (default `normal`)
+/// * `cycle_id` - Render cycle id; queued renders of older cycles are dropped
+///
+/// Runs on the blocking thread pool, so concurrent renders queue in the
+/// document's `RenderScheduler` (highest priority first) and
+/// `cancel_renders` can reach them while they wait.
 ///
 /// PHASE 1 FIX (C-3): outer documents Mutex released before rendering.
 /// PHASE 3 FIX: Uses cached PdfDocument — no re-bind or re-parse per call.
@@ -281,41 +854,164 @@ pub fn get_page_info(
 /// PNG encode on the Rust side and PNG decode on the browser side.
 /// Pixel data is base64-encoded for efficient JSON transport.
 #[tauri::command]
-pub fn render_page(
+pub async fn render_page(
     doc_id: String,
     page_index: u32,
     scale: f32,
-    state: State<AppState>,
+    options: Option<RenderOptions>,
+    priority: Option<RenderPriority>,
+    cycle_id: Option<u64>,
+    app: AppHandle,
 ) -> Result<RenderResult, String> {
-    diag!("render_page START doc={} page={} scale={:.2}", doc_id, page_index, scale);
-    let render_start = Instant::now();
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<AppState>();
+        diag!("render_page START doc={} page={} scale={:.2}", doc_id, page_index, scale);
+        let render_start = Instant::now();
 
-    // Clone Arc (outer documents mutex held only for this)
-    let cached = clone_cached_pdf(&doc_id, &state)?;
+        // Served from the render cache when possible; otherwise rendered with
+        // the per-document mutex held for the render only.
+        let CachedRender { pixels, width, height, scale } = render_page_cached(
+            &doc_id,
+            page_index,
+            scale,
+            &options.unwrap_or_default(),
+            priority.unwrap_or_default(),
+            cycle_id,
+            &state,
+        )?;
 
-    // Render using persistent document (per-document mutex held for render only)
-    let pdf = cached.lock().unwrap();
-    let (pixels, width, height) = pdf
-        .render_page_to_rgba(page_index, scale)
-        .map_err(|e| e.to_string())?;
+        // Base64-encode for efficient IPC transport
+        let b64_start = Instant::now();
+        let pixels_b64 = base64::engine::general_purpose::STANDARD.encode(pixels.as_slice());
+        diag!("render_page base64 encode: {:?} raw={}B b64={}B",
+              b64_start.elapsed(), pixels.len(), pixels_b64.len());
+
+        diag!("render_page COMPLETE doc={} page={} {}x{} elapsed={:?}",
+              doc_id, page_index, width, height, render_start.elapsed());
 
-    // Base64-encode for efficient IPC transport
-    let b64_start = Instant::now();
-    let pixels_b64 = base64::engine::general_purpose::STANDARD.encode(&pixels);
-    diag!("render_page base64 encode: {:?} raw={}B b64={}B",
-          b64_start.elapsed(), pixels.len(), pixels_b64.len());
+        Ok(RenderResult {
+            pixels: pixels_b64,
+            width,
+            height,
+            scale,
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Render a page to raw RGBA pixels, returned as a binary IPC response.
+///
+/// Same arguments as `render_page`, but the pixels travel through Tauri's
+/// raw response channel instead of a base64 JSON string: no 33% size
+/// overhead, no base64 encode here and no decode in the WebView, where the
+/// response arrives as an `ArrayBuffer`. See `RGBA_FRAME_HEADER_LEN` for
+/// the layout. `render_page` remains available for JSON callers.
+#[tauri::command]
+pub async fn render_page_binary(
+    doc_id: String,
+    page_index: u32,
+    scale: f32,
+    options: Option<RenderOptions>,
+    priority: Option<RenderPriority>,
+    cycle_id: Option<u64>,
+    app: AppHandle,
+) -> Result<Response, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<AppState>();
+        diag!("render_page_binary START doc={} page={} scale={:.2}", doc_id, page_index, scale);
+        let render_start = Instant::now();
 
-    diag!("render_page COMPLETE doc={} page={} {}x{} elapsed={:?}",
-          doc_id, page_index, width, height, render_start.elapsed());
+        let CachedRender { pixels, width, height, .. } = render_page_cached(
+            &doc_id,
+            page_index,
+            scale,
+            &options.unwrap_or_default(),
+            priority.unwrap_or_default(),
+            cycle_id,
+            &state,
+        )?;
 
-    Ok(RenderResult {
-        pixels: pixels_b64,
-        width,
-        height,
+        let body = encode_rgba_frame(&pixels, width, height);
+
+        diag!("render_page_binary COMPLETE doc={} page={} {}x{} bytes={} elapsed={:?}",
+              doc_id, page_index, width, height, body.len(), render_start.elapsed());
+
+        Ok(Response::new(body))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Render a rectangular region (tile) of a page to raw RGBA pixels.
+///
+/// # Arguments
+/// * `doc_id` - Document ID from load_pdf
+/// * `page_index` - 0-based page index
+/// * `x`, `y`, `width`, `height` - Region in PDF points (top-left origin of
+///   the rotated view)
+/// * `scale` - Render scale (1.0 = 72 DPI, 2.0 = 144 DPI, etc.)
+/// * `options`, `priority`, `cycle_id` - As for `render_page` (tiles never
+///   include form fields)
+///
+/// Used for deep zoom: the viewer requests only the visible tiles instead of
+/// rasterizing the whole page at a huge scale. Runs on the blocking thread
+/// pool like `render_page`.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn render_page_region(
+    doc_id: String,
+    page_index: u32,
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+    scale: f32,
+    options: Option<RenderOptions>,
+    priority: Option<RenderPriority>,
+    cycle_id: Option<u64>,
+    app: AppHandle,
+) -> Result<RegionRenderResult, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<AppState>();
+        diag!("render_page_region START doc={} page={} region=({:.1},{:.1} {:.1}x{:.1}) scale={:.2}",
+              doc_id, page_index, x, y, width, height, scale);
+        let render_start = Instant::now();
+
+        let handles = clone_render_handles(&doc_id, &state)?;
+
+        let mut region = {
+            let _foreground = handles.foreground.begin();
+            let _slot = handles
+                .scheduler
+                .acquire(priority.unwrap_or_default(), cycle_id, 1)?;
+            let options = handles.page_options(page_index, &options.unwrap_or_default());
+            let pdf = handles.cached_pdf.lock().unwrap();
+            pdf.render_page_region(page_index, x, y, width, height, scale, &options)
+                .map_err(|e| e.to_string())?
+        };
+        apply_accessibility_filter(&mut region.pixels, &handles.filter);
+
+        diag!("render_page_region COMPLETE doc={} page={} {}x{} elapsed={:?}",
+              doc_id, page_index, region.width, region.height, render_start.elapsed());
+
+        Ok(RegionRenderResult {
+            render: RenderResult {
+                pixels: base64::engine::general_purpose::STANDARD.encode(&region.pixels),
+                width: region.width,
+                height: region.height,
+                scale,
+            },
+            x: region.x,
+            y: region.y,
+        })
     })
+    .await
+    .map_err(|e| e.to_string())?
 }
 
-/// Get character bounding boxes for text selection.
+/// Get character bounding boxes for text selection, in the page's rotated
+/// view.
 ///
 /// PHASE 3: Uses cached PdfDocument — no re-parse.
 #[tauri::command]
@@ -324,9 +1020,15 @@ pub fn get_char_rects(
     page_index: u32,
     state: State<AppState>,
 ) -> Result<Vec<CharRect>, String> {
-    let cached = clone_cached_pdf(&doc_id, &state)?;
+    let (cached, rotations) = clone_pdf_and_rotations(&doc_id, &state)?;
     let pdf = cached.lock().unwrap();
-    pdf.get_char_rects(page_index).map_err(|e| e.to_string())
+    let mut rects = pdf.get_char_rects(page_index).map_err(|e| e.to_string())?;
+    let rotation = rotations.for_page(page_index);
+    if rotation != ViewRotation::None {
+        let page = pdf.get_page_info(page_index).map_err(|e| e.to_string())?;
+        rotation.rotate_char_rects(&mut rects, page.width, page.height);
+    }
+    Ok(rects)
 }
 
 /// Get plain text content of a page.
@@ -343,92 +1045,1339 @@ pub fn get_page_text(
     pdf.get_page_text(page_index).map_err(|e| e.to_string())
 }
 
-/// Search for text across all pages.
+/// Search for text across all pages. Highlight rects are in each page's
+/// rotated view.
+///
+/// `mode` selects how `query` is read: a literal string (default), a regular
+/// expression, terms with quoted phrases and AND/OR, or an approximate match
+/// within `max_edits` character edits (see `pdf::search`). Approximate
+/// results come best `score` first.
+/// `pages` limits the search to a 1-based page range such as `1-3,5`.
+/// Long documents are better searched with `start_search`, which streams
+/// results and can be cancelled. Once the document's full-text index is
+/// ready (see `build_search_index`) searches are answered from it.
+/// `normalization` makes accents, ligatures and compatibility forms such as
+/// full-width letters insignificant, and can join text across line breaks and
+/// end-of-line hyphenation; with `whole_word` matches must start and end at
+/// word boundaries.
 ///
 /// PHASE 3: Uses cached PdfDocument — no re-parse.
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub fn search_text(
     doc_id: String,
     query: String,
     case_sensitive: bool,
     max_results: Option<usize>,
+    mode: Option<SearchMode>,
+    pages: Option<String>,
+    normalization: Option<SearchNormalization>,
+    whole_word: Option<bool>,
+    max_edits: Option<u32>,
     state: State<AppState>,
 ) -> Result<Vec<SearchResult>, String> {
-    let cached = clone_cached_pdf(&doc_id, &state)?;
+    let (cached, rotations) = clone_pdf_and_rotations(&doc_id, &state)?;
+    let options =
+        search_options(&doc_id, case_sensitive, mode, pages, normalization, whole_word, max_edits, &state)?;
+    let index = state
+        .documents
+        .lock()
+        .unwrap()
+        .get(&doc_id)
+        .map(|doc_state| Arc::clone(&doc_state.search_index));
+    let max_results = max_results.unwrap_or(50);
     let pdf = cached.lock().unwrap();
-    pdf.search_text(&query, case_sensitive, max_results.unwrap_or(50))
-        .map_err(|e| e.to_string())
+    let mut results = match index.as_ref().and_then(|index| index.get()) {
+        Some(index) => pdf.search_text_indexed(index, &query, &options, max_results),
+        None => pdf.search_text(&query, &options, max_results),
+    }
+    .map_err(|e| e.to_string())?;
+    rotate_search_results(&pdf, &rotations, &mut results)?;
+    Ok(results)
+}
+
+/// Helper: `SearchOptions` from the search command arguments; `pages` is a
+/// 1-based page range checked against the document's page count.
+#[allow(clippy::too_many_arguments)]
+fn search_options(
+    doc_id: &str,
+    case_sensitive: bool,
+    mode: Option<SearchMode>,
+    pages: Option<String>,
+    normalization: Option<SearchNormalization>,
+    whole_word: Option<bool>,
+    max_edits: Option<u32>,
+    state: &AppState,
+) -> Result<SearchOptions, String> {
+    let pages = match pages {
+        Some(spec) => {
+            let page_count = state
+                .documents
+                .lock()
+                .unwrap()
+                .get(doc_id)
+                .ok_or_else(|| "Document not found".to_string())?
+                .page_count;
+            Some(parse_page_range(&spec, page_count)?)
+        }
+        None => None,
+    };
+    Ok(SearchOptions {
+        case_sensitive,
+        mode: mode.unwrap_or_default(),
+        normalization: normalization.unwrap_or_default(),
+        whole_word: whole_word.unwrap_or(false),
+        max_edits,
+        pages,
+    })
+}
+
+/// Helper: move search highlight rects into each page's rotated view.
+fn rotate_search_results(
+    pdf: &CachedPdf,
+    rotations: &ViewRotations,
+    results: &mut [SearchResult],
+) -> Result<(), String> {
+    for result in results {
+        let rotation = rotations.for_page(result.page);
+        if rotation != ViewRotation::None {
+            let page = pdf.get_page_info(result.page).map_err(|e| e.to_string())?;
+            rotation.rotate_text_rects(&mut result.rects, page.width, page.height);
+        }
+    }
+    Ok(())
 }
 
-/// Get all page infos for the document.
+/// Get all page infos for the document, each sized for its view rotation.
 ///
 /// PHASE 3: Uses cached PdfDocument — no re-parse.
 #[tauri::command]
 pub fn get_all_page_infos(doc_id: String, state: State<AppState>) -> Result<Vec<PageInfo>, String> {
-    let cached = clone_cached_pdf(&doc_id, &state)?;
+    let (cached, rotations) = clone_pdf_and_rotations(&doc_id, &state)?;
     let pdf = cached.lock().unwrap();
-    pdf.get_all_page_infos().map_err(|e| e.to_string())
+    let mut infos = pdf.get_all_page_infos().map_err(|e| e.to_string())?;
+    for info in &mut infos {
+        rotations.for_page(info.index).rotate_page_info(info);
+    }
+    Ok(infos)
 }
 
 // ============================================================================
-// Annotation Commands
+// Render Cache Commands
 // ============================================================================
 
-/// Get file path for a loaded document.
+/// Get render cache statistics for one document, or summed across all open
+/// documents when `doc_id` is omitted.
 #[tauri::command]
-pub fn get_document_path(doc_id: String, state: State<AppState>) -> Result<Option<String>, String> {
-    clone_doc_path(&doc_id, &state)
+pub fn get_render_cache_stats(
+    doc_id: Option<String>,
+    state: State<AppState>,
+) -> Result<RenderCacheStats, String> {
+    let caches: Vec<Arc<Mutex<RenderCache>>> = {
+        let docs = state.documents.lock().unwrap();
+        match &doc_id {
+            Some(id) => vec![Arc::clone(
+                &docs.get(id).ok_or_else(|| "Document not found".to_string())?.render_cache,
+            )],
+            None => docs.values().map(|d| Arc::clone(&d.render_cache)).collect(),
+        }
+    };
+
+    let mut total = RenderCacheStats {
+        budget_bytes: state.render_budget.max_bytes(),
+        ..Default::default()
+    };
+    for cache in caches {
+        let stats = cache.lock().unwrap().stats();
+        total.hits += stats.hits;
+        total.misses += stats.misses;
+        total.entries += stats.entries;
+        total.bytes += stats.bytes;
+    }
+    Ok(total)
 }
 
-/// Get existing annotations from a PDF file.
+/// Set the global byte budget for rendered bitmaps across all documents,
+/// evicting least recently used entries if the cache is now over budget.
 #[tauri::command]
-pub fn get_annotations(path: String) -> Result<Vec<AnnotationData>, String> {
-    annotations::get_annotations(&path).map_err(|e| e.to_string())
+pub fn set_render_cache_budget(budget_bytes: usize, state: State<AppState>) -> Result<(), String> {
+    state.render_budget.set_max_bytes(budget_bytes);
+    state.enforce_render_budget();
+    Ok(())
 }
 
-/// Save annotations to a PDF file.
-/// If dest_path is None, saves to the original file.
+/// Set the largest bitmap, in pixels, a single render may allocate (at
+/// least 1). Larger full pages are rendered at the largest scale that fits,
+/// reported in `RenderResult.scale`; larger tiles and exports fail with a
+/// "exceeds the limit" error. Cached renders are dropped so pages come back
+/// at the new limit.
 #[tauri::command]
-pub fn save_annotations(
-    source_path: String,
-    dest_path: Option<String>,
-    annotations_data: Vec<AnnotationData>,
-) -> Result<SaveResult, String> {
-    let dest = dest_path.unwrap_or_else(|| source_path.clone());
-    
-    // If saving to the same file, we need to use a temp file first
-    if dest == source_path {
-        let temp_path = format!("{}.tmp", source_path);
-        
-        // Save to temp file
-        let result = annotations::save_annotations(&source_path, &temp_path, annotations_data)
-            .map_err(|e| e.to_string())?;
-        
-        // Replace original with temp
-        std::fs::rename(&temp_path, &source_path)
-            .map_err(|e| format!("Failed to replace original file: {}", e))?;
-        
-        Ok(SaveResult {
-            success: true,
-            path: source_path,
-            annotations_count: result.annotations_count,
-        })
-    } else {
-        annotations::save_annotations(&source_path, &dest, annotations_data)
-            .map_err(|e| e.to_string())
+pub fn set_max_render_pixels(max_pixels: u64, state: State<AppState>) -> Result<(), String> {
+    diag!("set_max_render_pixels {}", max_pixels);
+    set_pdf_max_render_pixels(max_pixels);
+    let caches: Vec<Arc<Mutex<RenderCache>>> = {
+        let docs = state.documents.lock().unwrap();
+        docs.values().map(|d| Arc::clone(&d.render_cache)).collect()
+    };
+    for cache in caches {
+        cache.lock().unwrap().invalidate();
     }
+    Ok(())
+}
+
+/// Drop every queued render of `doc_id` tagged with a cycle older than
+/// `cycle_id`. Renders already inside pdfium still complete.
+///
+/// Called when the viewer invalidates its render cycle without issuing a new
+/// render (e.g. document switch or layout reset).
+#[tauri::command(async)]
+pub fn cancel_renders(doc_id: String, cycle_id: u64, state: State<AppState>) -> Result<(), String> {
+    let scheduler = {
+        let docs = state.documents.lock().unwrap();
+        let doc_state = docs
+            .get(&doc_id)
+            .ok_or_else(|| "Document not found".to_string())?;
+        Arc::clone(&doc_state.scheduler)
+    };
+    diag!("cancel_renders doc={} before cycle={} pending={}", doc_id, cycle_id, scheduler.pending());
+    scheduler.cancel_before(cycle_id);
+    Ok(())
 }
 
-/// Remove a specific annotation from a PDF.
+// ============================================================================
+// Accessibility Commands
+// ============================================================================
+
+/// Select the accessibility filter applied to every page, tile and
+/// thumbnail render of `doc_id` (`{ "filter": "none" }` to turn it off).
+///
+/// Cached renders are dropped, so `kiosk://` URLs revalidate to the new look.
 #[tauri::command]
-pub fn remove_annotation(
-    source_path: String,
-    dest_path: Option<String>,
-    page_index: u32,
-    rect_x1: f64,
+pub fn set_accessibility_filter(
+    doc_id: String,
+    filter: AccessibilityFilter,
+    state: State<AppState>,
+) -> Result<(), String> {
+    let render_cache = {
+        let mut docs = state.documents.lock().unwrap();
+        let doc_state = docs
+            .get_mut(&doc_id)
+            .ok_or_else(|| "Document not found".to_string())?;
+        if doc_state.filter == filter {
+            return Ok(());
+        }
+        doc_state.filter = filter;
+        Arc::clone(&doc_state.render_cache)
+    };
+    diag!("set_accessibility_filter doc={} filter={:?}", doc_id, filter);
+
+    render_cache.lock().unwrap().invalidate();
+    Ok(())
+}
+
+// ============================================================================
+// View Rotation Commands
+// ============================================================================
+
+/// Rotate the view of `doc_id` by `rotation` degrees clockwise (a multiple
+/// of 90) without modifying the file.
+///
+/// With `page_index` only that page is rotated; without it every page is,
+/// and earlier per-page rotations are cleared. Page infos, character boxes
+/// and search rects fetched afterwards describe the rotated view, so the
+/// viewer should refetch them.
+#[tauri::command]
+pub fn set_view_rotation(
+    doc_id: String,
+    rotation: ViewRotation,
+    page_index: Option<u32>,
+    state: State<AppState>,
+) -> Result<(), String> {
+    let render_cache = {
+        let mut docs = state.documents.lock().unwrap();
+        let doc_state = docs
+            .get_mut(&doc_id)
+            .ok_or_else(|| "Document not found".to_string())?;
+        let mut rotations = ViewRotations::clone(&doc_state.view_rotations);
+        match page_index {
+            Some(page) if page >= doc_state.page_count => {
+                return Err(PdfError::InvalidPage(page).to_string());
+            }
+            Some(page) => {
+                rotations.pages.insert(page, rotation);
+            }
+            None => {
+                rotations.global = rotation;
+                rotations.pages.clear();
+            }
+        }
+        doc_state.view_rotations = Arc::new(rotations);
+        Arc::clone(&doc_state.render_cache)
+    };
+    diag!("set_view_rotation doc={} page={:?} rotation={}", doc_id, page_index, rotation.degrees());
+
+    render_cache.lock().unwrap().invalidate();
+    Ok(())
+}
+
+// ============================================================================
+// Optional Content Commands
+// ============================================================================
+
+/// Helper: the lock serializing re-opens of `doc_id`.
+fn reopen_lock(doc_id: &str, state: &AppState) -> Result<Arc<Mutex<()>>, String> {
+    let docs = state.documents.lock().unwrap();
+    let doc_state = docs
+        .get(doc_id)
+        .ok_or_else(|| "Document not found".to_string())?;
+    Ok(Arc::clone(&doc_state.reopen_lock))
+}
+
+/// Helper: the bytes `doc_id` was opened from and its layer overrides
+/// (empty when there are none).
+fn layer_source(doc_id: &str, state: &AppState) -> Result<LayerOverrides, String> {
+    let (cached, layers) = {
+        let docs = state.documents.lock().unwrap();
+        let doc_state = docs
+            .get(doc_id)
+            .ok_or_else(|| "Document not found".to_string())?;
+        // The password is not kept, so the file could not be re-opened.
+        if doc_state.password_protected {
+            return Err("Layers are not supported for password-protected documents".to_string());
+        }
+        (Arc::clone(&doc_state.cached_pdf), doc_state.layers.clone())
+    };
+    Ok(layers.unwrap_or_else(|| LayerOverrides {
+        original: Arc::new(cached.lock().unwrap().bytes().to_vec()),
+        visibility: HashMap::new(),
+    }))
+}
+
+/// List the optional content groups (layers) of `doc_id`: names, default
+/// and current visibility, and the layers panel tree from `/OCProperties`.
+/// A document without layers has none.
+#[tauri::command]
+pub async fn get_optional_content(doc_id: String, app: AppHandle) -> Result<OptionalContent, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<AppState>();
+        let source = layer_source(&doc_id, &state)?;
+        let mut content = read_optional_content(&source.original)?;
+        content.apply(&source.visibility);
+        Ok(content)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Show or hide layers of `doc_id`. `layers` maps group ids to visibility;
+/// groups it omits keep their current state. Returns the updated layers.
+///
+/// The document is re-opened from a copy whose default configuration has
+/// the chosen visibility, so pages, tiles, thumbnails and exports rendered
+/// afterwards honor it; cached renders are dropped. Once every layer is
+/// back at its default the original document is used again. Concurrent
+/// calls for one document are applied one after the other.
+#[tauri::command]
+pub async fn set_layer_visibility(
+    doc_id: String,
+    layers: HashMap<String, bool>,
+    app: AppHandle,
+) -> Result<OptionalContent, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<AppState>();
+        apply_layer_visibility(&doc_id, layers, &state)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+fn apply_layer_visibility(
+    doc_id: &str,
+    layers: HashMap<String, bool>,
+    state: &AppState,
+) -> Result<OptionalContent, String> {
+    let apply_start = Instant::now();
+    // Held from reading the current overrides to storing the new ones, so a
+    // concurrent change starts from this one's result.
+    let reopen_lock = reopen_lock(doc_id, state)?;
+    let _reopening = reopen_lock.lock().unwrap();
+    let LayerOverrides { original, mut visibility } = layer_source(doc_id, state)?;
+    let mut content = read_optional_content(&original)?;
+    for (id, visible) in layers {
+        let group = content
+            .group(&id)
+            .ok_or_else(|| format!("Unknown layer: {}", id))?;
+        if visible == group.default_visible {
+            visibility.remove(&id);
+        } else {
+            visibility.insert(id, visible);
+        }
+    }
+    content.apply(&visibility);
+
+    let bytes = if visibility.is_empty() {
+        original.to_vec()
+    } else {
+        with_layer_visibility(&original, &content.groups)?
+    };
+    let cached_pdf = CachedPdf::new(bytes, None).map_err(|e| e.to_string())?;
+    // Workers that have the document open reopen it with the new bytes.
+    if let Some(pool) = state.render_workers.lock().unwrap().as_ref() {
+        pool.register_document(doc_id, cached_pdf.bytes().to_vec());
+    }
+
+    let render_cache = {
+        let mut docs = state.documents.lock().unwrap();
+        let doc_state = docs
+            .get_mut(doc_id)
+            .ok_or_else(|| "Document not found".to_string())?;
+        doc_state.cached_pdf = Arc::new(Mutex::new(cached_pdf));
+        // Thumbnails on disk are keyed by the bytes actually rendered.
+        doc_state.content_hash = Arc::default();
+        doc_state.layers = (!visibility.is_empty()).then_some(LayerOverrides { original, visibility });
+        Arc::clone(&doc_state.render_cache)
+    };
+    render_cache.lock().unwrap().invalidate();
+    diag!("set_layer_visibility doc={} in {:?}", doc_id, apply_start.elapsed());
+
+    Ok(content)
+}
+
+// ============================================================================
+// Thumbnail Commands
+// ============================================================================
+
+/// Smallest and largest accepted `max_dim` for thumbnails, in pixels.
+const THUMBNAIL_MIN_DIM: u32 = 16;
+const THUMBNAIL_MAX_DIM: u32 = 1024;
+
+/// An encoded page thumbnail.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ThumbnailResult {
+    /// 0-based page index
+    pub page_index: u32,
+    /// Pixel width of the thumbnail
+    pub width: u32,
+    /// Pixel height of the thumbnail
+    pub height: u32,
+    /// Encoding of `data`
+    pub format: PageImageFormat,
+    /// Base64-encoded image bytes
+    pub data: String,
+    /// Taken from the page's embedded `/Thumb` image rather than rendered
+    pub embedded: bool,
+}
+
+/// Render thumbnails for a batch of pages.
+///
+/// # Arguments
+/// * `doc_id` - Document ID from load_pdf
+/// * `pages` - 0-based page indices
+/// * `max_dim` - Maximum width/height of each thumbnail in pixels
+/// * `format` - `jpeg` (default), `webp` or `png`
+/// * `options` - Render options, as for `render_page`
+///
+/// Thumbnails follow the document's view rotation. Embedded `/Thumb` images
+/// are used when present and `options` are the defaults. Results are cached
+/// on disk under the app data directory, keyed by the document's content
+/// hash, page and options, so reopening a document shows its thumbnails
+/// without rendering. Pages that fail are logged and left out of the result.
+///
+/// Runs on the blocking thread pool: hashing the document, rendering and
+/// disk I/O never hold up the UI, and each page waits for its own
+/// low-priority render slot.
+#[tauri::command]
+pub async fn render_thumbnails(
+    doc_id: String,
+    pages: Vec<u32>,
+    max_dim: u32,
+    format: Option<PageImageFormat>,
+    options: Option<RenderOptions>,
+    app: AppHandle,
+) -> Result<Vec<ThumbnailResult>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<AppState>();
+        let format = format.unwrap_or(PageImageFormat::Jpeg);
+        let options = options.unwrap_or_default();
+        thumbnails(&app, &doc_id, pages, max_dim, format, &options, &state)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+fn thumbnails(
+    app: &AppHandle,
+    doc_id: &str,
+    pages: Vec<u32>,
+    max_dim: u32,
+    format: PageImageFormat,
+    options: &RenderOptions,
+    state: &AppState,
+) -> Result<Vec<ThumbnailResult>, String> {
+    if format == PageImageFormat::Rgba {
+        return Err("Unsupported thumbnail format: rgba".to_string());
+    }
+    let max_dim = max_dim.clamp(THUMBNAIL_MIN_DIM, THUMBNAIL_MAX_DIM);
+    let batch_start = Instant::now();
+
+    let handles = clone_render_handles(doc_id, state)?;
+    let hash = document_content_hash(doc_id, state)?;
+    let disk_cache = match app.path().app_data_dir() {
+        Ok(dir) => Some(ThumbnailCache::new(dir.join(THUMBNAIL_CACHE_DIR))),
+        Err(e) => {
+            diag!("render_thumbnails: no app data dir, disk cache disabled: {}", e);
+            None
+        }
+    };
+
+    let mut results = Vec::with_capacity(pages.len());
+    let mut disk_hits = 0;
+    for page_index in pages {
+        let options = handles.page_options(page_index, options);
+        let variant = thumbnail_variant(&options, &handles.filter);
+        if let Some(stored) = disk_cache
+            .as_ref()
+            .and_then(|c| c.get(&hash, page_index, max_dim, format, variant.as_deref()))
+        {
+            disk_hits += 1;
+            results.push(thumbnail_result(page_index, format, stored));
+            continue;
+        }
+
+        // Each page takes its own low-priority slot so visible renders can
+        // run between thumbnails.
+        let rendered = {
+            let _slot = handles.scheduler.acquire(RenderPriority::Low, None, 1)?;
+            let pdf = handles.cached_pdf.lock().unwrap();
+            pdf.render_thumbnail(page_index, max_dim, &options)
+        };
+        let stored = rendered.and_then(|mut thumb| {
+            apply_accessibility_filter(&mut thumb.pixels, &handles.filter);
+            Ok(StoredThumbnail {
+                width: thumb.width,
+                height: thumb.height,
+                embedded: thumb.embedded,
+                data: encode_rgba(&thumb.pixels, thumb.width, thumb.height, format)?,
+            })
+        });
+        let stored = match stored {
+            Ok(s) => s,
+            Err(e) => {
+                diag!("render_thumbnails doc={} page={} failed: {}", doc_id, page_index, e);
+                continue;
+            }
+        };
+
+        if let Some(cache) = &disk_cache {
+            if let Err(e) = cache.put(&hash, page_index, max_dim, format, variant.as_deref(), &stored) {
+                diag!("render_thumbnails: failed to write disk cache: {}", e);
+            }
+        }
+        results.push(thumbnail_result(page_index, format, stored));
+    }
+
+    diag!("render_thumbnails COMPLETE doc={} count={} disk_hits={} elapsed={:?}",
+          doc_id, results.len(), disk_hits, batch_start.elapsed());
+
+    Ok(results)
+}
+
+fn thumbnail_result(page_index: u32, format: PageImageFormat, stored: StoredThumbnail) -> ThumbnailResult {
+    ThumbnailResult {
+        page_index,
+        width: stored.width,
+        height: stored.height,
+        format,
+        data: base64::engine::general_purpose::STANDARD.encode(&stored.data),
+        embedded: stored.embedded,
+    }
+}
+
+// ============================================================================
+// Export Commands
+// ============================================================================
+
+/// Render pages of `doc_id` to image files (see `ImageExportOptions`).
+///
+/// Pages are rendered as the viewer shows them, with the document's view
+/// rotation and accessibility filter, and with annotations unless
+/// `options.annotations` is off; `options.apply_view_filters` off leaves
+/// the filter out. An `export-progress` event (`ExportProgress`) follows
+/// every page. A page that fails is logged and listed in the result, and
+/// the export carries on with the rest; that includes pages too large for
+/// the pixel limit at the requested DPI, which are never downscaled, and
+/// pages whose file already exists unless `options.overwrite` is set.
+///
+/// Runs on the blocking thread pool; each page takes its own low-priority
+/// render slot so the viewer stays responsive meanwhile.
+#[tauri::command]
+pub async fn export_pages_as_images(
+    doc_id: String,
+    options: ImageExportOptions,
+    app: AppHandle,
+) -> Result<ExportResult, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<AppState>();
+        export_pages(&app, &doc_id, &options, &state)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+fn export_pages(
+    app: &AppHandle,
+    doc_id: &str,
+    options: &ImageExportOptions,
+    state: &AppState,
+) -> Result<ExportResult, String> {
+    if !options.dpi.is_finite() || options.dpi <= 0.0 || options.dpi > MAX_EXPORT_DPI {
+        return Err(format!("Invalid DPI: {} (at most {})", options.dpi, MAX_EXPORT_DPI));
+    }
+    if options.format == PageImageFormat::Rgba {
+        return Err("Unsupported export format: rgba".to_string());
+    }
+    let export_start = Instant::now();
+
+    let handles = clone_render_handles(doc_id, state)?;
+    let (page_count, name) = {
+        let docs = state.documents.lock().unwrap();
+        let doc_state = docs
+            .get(doc_id)
+            .ok_or_else(|| "Document not found".to_string())?;
+        let name = doc_state
+            .path
+            .as_deref()
+            .and_then(|p| Path::new(p).file_stem())
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "document".to_string());
+        (doc_state.page_count, name)
+    };
+    let pages = match &options.pages {
+        Some(spec) => parse_page_range(spec, page_count)?,
+        None => (0..page_count).collect(),
+    };
+    validate_pattern(&options.pattern, pages.len())?;
+
+    let directory = PathBuf::from(&options.directory);
+    std::fs::create_dir_all(&directory)
+        .map_err(|e| format!("Failed to create {}: {}", directory.display(), e))?;
+
+    let render_options = RenderOptions { annotations: options.annotations, ..RenderOptions::default() };
+    let scale = options.dpi / 72.0;
+    let mut result = ExportResult { files: Vec::new(), failed: Vec::new() };
+
+    for (done, &page_index) in pages.iter().enumerate() {
+        let path = directory.join(export_file_name(
+            &options.pattern,
+            &name,
+            page_index,
+            page_count,
+            options.format,
+        ));
+        let path = match export_page(&handles, page_index, scale, &render_options, options, &path) {
+            Ok(()) => {
+                let path = path.to_string_lossy().into_owned();
+                result.files.push(path.clone());
+                Some(path)
+            }
+            Err(e) => {
+                diag!("export_pages_as_images doc={} page={} failed: {}", doc_id, page_index, e);
+                result.failed.push(page_index);
+                None
+            }
+        };
+
+        let progress = ExportProgress {
+            doc_id: doc_id.to_string(),
+            page_index,
+            completed: done + 1,
+            total: pages.len(),
+            path,
+        };
+        if let Err(e) = app.emit(EXPORT_PROGRESS_EVENT, &progress) {
+            diag!("export_pages_as_images: failed to emit progress: {}", e);
+        }
+    }
+
+    diag!("export_pages_as_images COMPLETE doc={} files={} failed={} elapsed={:?}",
+          doc_id, result.files.len(), result.failed.len(), export_start.elapsed());
+
+    Ok(result)
+}
+
+/// Render, filter, encode and write one exported page.
+fn export_page(
+    handles: &RenderHandles,
+    page_index: u32,
+    scale: f32,
+    render_options: &RenderOptions,
+    options: &ImageExportOptions,
+    path: &Path,
+) -> Result<(), String> {
+    let exists = || format!("{} already exists", path.display());
+    if !options.overwrite && path.exists() {
+        return Err(exists());
+    }
+    let (mut pixels, width, height) = {
+        let _slot = handles.scheduler.acquire(RenderPriority::Low, None, 1)?;
+        let pdf = handles.cached_pdf.lock().unwrap();
+        pdf.check_render_size(page_index, scale).map_err(|e| e.to_string())?;
+        pdf.render_page_to_rgba(page_index, scale, &handles.page_options(page_index, render_options))
+            .map_err(|e| e.to_string())?
+    };
+    if options.apply_view_filters {
+        apply_accessibility_filter(&mut pixels, &handles.filter);
+    }
+
+    let data = encode_rgba_with_quality(&pixels, width, height, options.format, options.quality)
+        .map_err(|e| e.to_string())?;
+    // Without `overwrite`, a file created while the page rendered is kept too.
+    let written = if options.overwrite {
+        std::fs::write(path, data)
+    } else {
+        std::fs::File::options()
+            .write(true)
+            .create_new(true)
+            .open(path)
+            .and_then(|mut file| file.write_all(&data))
+    };
+    written.map_err(|e| match e.kind() {
+        std::io::ErrorKind::AlreadyExists => exists(),
+        _ => format!("Failed to write {}: {}", path.display(), e),
+    })
+}
+
+// ============================================================================
+// Progressive Render Commands
+// ============================================================================
+
+/// Start rendering a page progressively and return the render id.
+///
+/// Progress is reported through `progressive-render` events
+/// (`ProgressiveUpdate`); the pixels of the latest frame are fetched with
+/// `get_progressive_frame`. The final frame matches `render_page` with the
+/// same arguments and is inserted into the render cache; a page already in
+/// the cache completes at once.
+#[tauri::command]
+pub fn start_progressive_render(
+    doc_id: String,
+    page_index: u32,
+    scale: f32,
+    options: Option<RenderOptions>,
+    app: AppHandle,
+    state: State<AppState>,
+) -> Result<u64, String> {
+    if !scale.is_finite() || scale <= 0.0 {
+        return Err(format!("Invalid scale: {}", scale));
+    }
+    if !state.documents.lock().unwrap().contains_key(&doc_id) {
+        return Err("Document not found".to_string());
+    }
+    let (render_id, render) = state.progressive.start(&doc_id, page_index);
+    diag!("start_progressive_render doc={} page={} scale={:.2} id={}", doc_id, page_index, scale, render_id);
+
+    let options = options.unwrap_or_default();
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<AppState>();
+        let render_start = Instant::now();
+        let (status, error) = match progressive_render(&app, render_id, &render, scale, &options, &state) {
+            Ok(true) => (ProgressiveStatus::Complete, None),
+            Ok(false) => (ProgressiveStatus::Cancelled, None),
+            Err(e) => (ProgressiveStatus::Failed, Some(e)),
+        };
+        if status != ProgressiveStatus::Complete {
+            state.progressive.remove(render_id);
+        }
+        diag!("progressive render {:?} id={} elapsed={:?}", status, render_id, render_start.elapsed());
+        emit_progressive_update(&app, render_id, &render, status, error);
+    });
+    Ok(render_id)
+}
+
+/// Draw `render` slice by slice, publishing frames on the way. Returns
+/// `Ok(false)` when cancelled.
+fn progressive_render(
+    app: &AppHandle,
+    render_id: u64,
+    render: &ProgressiveRender,
+    scale: f32,
+    options: &RenderOptions,
+    state: &AppState,
+) -> Result<bool, String> {
+    let page_index = render.page_index;
+    let handles = clone_render_handles(&render.doc_id, state)?;
+    let options = &handles.page_options(page_index, options);
+    let key = RenderCacheKey::new(page_index, scale, render_options_key(options, &handles.filter));
+
+    if let Some(hit) = handles.render_cache.lock().unwrap().get(&key) {
+        render.set_frame(hit, true);
+        return Ok(true);
+    }
+
+    let (job, scale) = {
+        let mut pdf = handles.cached_pdf.lock().unwrap();
+        let scale = pdf
+            .effective_render_scale(page_index, key.scale())
+            .map_err(|e| e.to_string())?;
+        let job = pdf
+            .begin_progressive_render(page_index, scale, options)
+            .map_err(|e| e.to_string())?;
+        (job, scale)
+    };
+    let publish = |status: ProgressiveStatus| -> Result<(), String> {
+        let (mut pixels, width, height) = handles
+            .cached_pdf
+            .lock()
+            .unwrap()
+            .progressive_frame(job)
+            .map_err(|e| e.to_string())?;
+        apply_accessibility_filter(&mut pixels, &handles.filter);
+        render.set_frame(CachedRender { pixels: Arc::new(pixels), width, height, scale }, false);
+        emit_progressive_update(app, render_id, render, status, None);
+        Ok(())
+    };
+
+    let mut last_frame = Instant::now();
+    let drawn = loop {
+        if render.is_paused() {
+            publish(ProgressiveStatus::Paused)?;
+        }
+        if !render.wait_while_paused() {
+            break Ok(false);
+        }
+        let done = {
+            let _foreground = handles.foreground.begin();
+            handles
+                .cached_pdf
+                .lock()
+                .unwrap()
+                .continue_progressive_render(job, PROGRESSIVE_SLICE)
+        };
+        match done {
+            Ok(true) => break Ok(true),
+            Ok(false) if last_frame.elapsed() >= PROGRESSIVE_FRAME_INTERVAL => {
+                publish(ProgressiveStatus::Rendering)?;
+                last_frame = Instant::now();
+            }
+            Ok(false) => {}
+            Err(e) => break Err(e.to_string()),
+        }
+    };
+    if !matches!(drawn, Ok(true)) {
+        handles.cached_pdf.lock().unwrap().end_progressive_render(job);
+        return drawn;
+    }
+
+    let (mut pixels, width, height) = {
+        let _foreground = handles.foreground.begin();
+        handles
+            .cached_pdf
+            .lock()
+            .unwrap()
+            .finish_progressive_render(job)
+            .map_err(|e| e.to_string())?
+    };
+    apply_accessibility_filter(&mut pixels, &handles.filter);
+    let final_render = CachedRender { pixels: Arc::new(pixels), width, height, scale };
+
+    handles.render_cache.lock().unwrap().insert(handles.generation, key, final_render.clone());
+    state.enforce_render_budget();
+    render.set_frame(final_render, true);
+    Ok(true)
+}
+
+/// Helper: emit a `ProgressiveUpdate` for `render`.
+fn emit_progressive_update(
+    app: &AppHandle,
+    render_id: u64,
+    render: &ProgressiveRender,
+    status: ProgressiveStatus,
+    error: Option<String>,
+) {
+    let (width, height) = render.frame().map_or((0, 0), |(frame, _)| (frame.width, frame.height));
+    let update = ProgressiveUpdate {
+        render_id,
+        doc_id: render.doc_id.clone(),
+        page_index: render.page_index,
+        width,
+        height,
+        status,
+        error,
+    };
+    if let Err(e) = app.emit(PROGRESSIVE_RENDER_EVENT, &update) {
+        diag!("progressive render: failed to emit update: {}", e);
+    }
+}
+
+/// Helper: look up a progressive render by id.
+fn progressive_render_by_id(render_id: u64, state: &AppState) -> Result<Arc<ProgressiveRender>, String> {
+    state
+        .progressive
+        .get(render_id)
+        .ok_or_else(|| format!("Progressive render {} not found", render_id))
+}
+
+/// Latest frame of a progressive render, in the `render_page_binary`
+/// format. Fetching the final frame releases the render.
+#[tauri::command]
+pub fn get_progressive_frame(render_id: u64, state: State<AppState>) -> Result<Response, String> {
+    let render = progressive_render_by_id(render_id, &state)?;
+    let (frame, complete) = render
+        .frame()
+        .ok_or_else(|| "No frame rendered yet".to_string())?;
+    if complete {
+        state.progressive.remove(render_id);
+    }
+    Ok(Response::new(encode_rgba_frame(&frame.pixels, frame.width, frame.height)))
+}
+
+/// Pause a progressive render after its current slice.
+#[tauri::command]
+pub fn pause_progressive_render(render_id: u64, state: State<AppState>) -> Result<(), String> {
+    progressive_render_by_id(render_id, &state)?.pause();
+    Ok(())
+}
+
+#[tauri::command]
+pub fn resume_progressive_render(render_id: u64, state: State<AppState>) -> Result<(), String> {
+    progressive_render_by_id(render_id, &state)?.resume();
+    Ok(())
+}
+
+/// Cancel a progressive render, or release a completed one whose final
+/// frame was not fetched.
+#[tauri::command]
+pub fn cancel_progressive_render(render_id: u64, state: State<AppState>) -> Result<(), String> {
+    let render = state
+        .progressive
+        .remove(render_id)
+        .ok_or_else(|| format!("Progressive render {} not found", render_id))?;
+    render.cancel();
+    Ok(())
+}
+
+// ============================================================================
+// Search Index
+// ============================================================================
+
+/// Load the full-text index of a newly opened document from the on-disk
+/// cache (see `search_index`), or build and store it, on the blocking
+/// thread pool. Building extracts one page at a time, yielding to
+/// foreground renders, and stops when the document is closed or reloaded.
+fn build_search_index(app: &AppHandle, doc_id: &str) {
+    let app = app.clone();
+    let doc_id = doc_id.to_string();
+    tauri::async_runtime::spawn_blocking(move || {
+        let build_start = Instant::now();
+        match load_or_build_search_index(&app, &doc_id, &app.state::<AppState>()) {
+            Ok(source) => diag!("search index doc={} {} in {:?}", doc_id, source, build_start.elapsed()),
+            Err(e) => diag!("search index doc={} not built: {}", doc_id, e),
+        }
+    });
+}
+
+/// Returns how the index was obtained, for diagnostics.
+fn load_or_build_search_index(app: &AppHandle, doc_id: &str, state: &AppState) -> Result<&'static str, String> {
+    let (cached, content_hash_slot, foreground, slot, page_count, persist) = {
+        let docs = state.documents.lock().unwrap();
+        let doc_state = docs
+            .get(doc_id)
+            .ok_or_else(|| "Document not found".to_string())?;
+        (
+            Arc::clone(&doc_state.cached_pdf),
+            Arc::clone(&doc_state.content_hash),
+            Arc::clone(&doc_state.foreground),
+            Arc::clone(&doc_state.search_index),
+            doc_state.page_count,
+            !doc_state.password_protected,
+        )
+    };
+    let disk_cache = match app.path().app_data_dir() {
+        Ok(dir) if persist => Some(SearchIndexCache::new(dir.join(SEARCH_INDEX_CACHE_DIR))),
+        Ok(_) => None,
+        Err(e) => {
+            diag!("search index: no app data dir, disk cache disabled: {}", e);
+            None
+        }
+    };
+    // Hashed from the same snapshot as the text, in case the document is
+    // reloaded meanwhile.
+    let hash = disk_cache.as_ref().map(|_| {
+        content_hash_slot
+            .get_or_init(|| content_hash(cached.lock().unwrap().bytes()))
+            .clone()
+    });
+
+    if let (Some(cache), Some(hash)) = (&disk_cache, &hash) {
+        if let Some(index) = cache.get(hash).filter(|index| index.page_count() == page_count) {
+            let _ = slot.set(index);
+            return Ok("loaded");
+        }
+    }
+
+    let mut index = SearchIndex::default();
+    for page_index in 0..page_count {
+        while foreground.is_active() {
+            std::thread::sleep(PREFETCH_BACKOFF);
+        }
+        let current = state
+            .documents
+            .lock()
+            .unwrap()
+            .get(doc_id)
+            .is_some_and(|d| Arc::ptr_eq(&d.search_index, &slot));
+        if !current {
+            return Err("document closed or reloaded".to_string());
+        }
+        // A page whose text can't be read is indexed as empty, matching
+        // `search_text`, which skips it.
+        let chars = cached.lock().unwrap().page_chars(page_index).unwrap_or_default();
+        index.add_page(chars);
+    }
+
+    if let (Some(cache), Some(hash)) = (&disk_cache, &hash) {
+        if let Err(e) = cache.put(hash, &index) {
+            diag!("search index: failed to store: {}", e);
+        }
+    }
+    let _ = slot.set(index);
+    Ok("built")
+}
+
+// ============================================================================
+// Search Session Commands
+// ============================================================================
+
+/// Start a search and return its id. Takes the same arguments as
+/// `search_text`; an invalid query fails here rather than in the search.
+///
+/// Results arrive page by page in `search-progress` events (`SearchUpdate`)
+/// and are kept in the session for `get_search_results`, up to
+/// `max_results` (default `DEFAULT_SESSION_MAX_RESULTS`). Highlight rects
+/// are in each page's rotated view as of the search.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub fn start_search(
+    doc_id: String,
+    query: String,
+    case_sensitive: bool,
+    max_results: Option<usize>,
+    mode: Option<SearchMode>,
+    pages: Option<String>,
+    normalization: Option<SearchNormalization>,
+    whole_word: Option<bool>,
+    max_edits: Option<u32>,
+    app: AppHandle,
+    state: State<AppState>,
+) -> Result<u64, String> {
+    let options =
+        search_options(&doc_id, case_sensitive, mode, pages, normalization, whole_word, max_edits, &state)?;
+    let query = CompiledQuery::new(&query, &options).map_err(|e| e.to_string())?;
+    let (cached, rotations) = clone_pdf_and_rotations(&doc_id, &state)?;
+    let page_count = state
+        .documents
+        .lock()
+        .unwrap()
+        .get(&doc_id)
+        .ok_or_else(|| "Document not found".to_string())?
+        .page_count;
+    let pages = options.page_indices(page_count);
+    let max_results = max_results.unwrap_or(DEFAULT_SESSION_MAX_RESULTS).max(1);
+    let (search_id, session) = state.searches.start(&doc_id, pages.len() as u32, max_results);
+    diag!("start_search doc={} pages={} id={}", doc_id, pages.len(), search_id);
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let search_start = Instant::now();
+        let mut reported = 0;
+        let mut last_report = Instant::now();
+        let mut status = SearchStatus::Complete;
+        for page_index in pages {
+            if session.is_cancelled() {
+                status = SearchStatus::Cancelled;
+                break;
+            }
+            let found = {
+                let pdf = cached.lock().unwrap();
+                pdf.search_page(page_index, &query)
+                    .map_err(|e| e.to_string())
+                    .and_then(|mut results| {
+                        rotate_search_results(&pdf, &rotations, &mut results)?;
+                        Ok(results)
+                    })
+            };
+            let found = found.unwrap_or_default();
+            let had_results = !found.is_empty();
+            let more = session.add_page(found);
+            if !more {
+                break;
+            }
+            if had_results || last_report.elapsed() >= SEARCH_PROGRESS_INTERVAL {
+                reported = emit_search_update(&app, search_id, &session, reported);
+                last_report = Instant::now();
+            }
+        }
+        session.finish(status);
+        diag!("search {:?} id={} elapsed={:?}", status, search_id, search_start.elapsed());
+        emit_search_update(&app, search_id, &session, reported);
+    });
+    Ok(search_id)
+}
+
+/// Helper: emit a `SearchUpdate` with the results from index `reported` on;
+/// returns the number of results reported so far.
+fn emit_search_update(app: &AppHandle, search_id: u64, session: &SearchSession, reported: usize) -> usize {
+    let update = session.update(search_id, reported);
+    let total = update.total_results;
+    if let Err(e) = app.emit(SEARCH_PROGRESS_EVENT, &update) {
+        diag!("search: failed to emit update: {}", e);
+    }
+    total
+}
+
+/// Up to `limit` results (default 50) of a search, starting at `offset`.
+/// Works while the search is still running.
+#[tauri::command]
+pub fn get_search_results(
+    search_id: u64,
+    offset: Option<usize>,
+    limit: Option<usize>,
+    state: State<AppState>,
+) -> Result<SearchResultsPage, String> {
+    let session = state
+        .searches
+        .get(search_id)
+        .ok_or_else(|| format!("Search {} not found", search_id))?;
+    Ok(session.results(offset.unwrap_or(0), limit.unwrap_or(50)))
+}
+
+/// Cancel a search, or release a finished one and its results.
+#[tauri::command]
+pub fn cancel_search(search_id: u64, state: State<AppState>) -> Result<(), String> {
+    let session = state
+        .searches
+        .remove(search_id)
+        .ok_or_else(|| format!("Search {} not found", search_id))?;
+    session.cancel();
+    Ok(())
+}
+
+// ============================================================================
+// Render Worker Commands
+// ============================================================================
+
+/// Enable the out-of-process render worker pool with `count` workers, or
+/// disable it with `count` = 0 (the default).
+///
+/// With workers enabled, full-page renders of documents opened without a
+/// password run in helper processes: pages of one document render in
+/// parallel and a pdfium crash only fails the page being rendered.
+/// Region renders and text queries stay in process.
+#[tauri::command]
+pub fn set_render_workers(count: usize, state: State<AppState>) -> Result<(), String> {
+    let pool = match count.min(MAX_RENDER_WORKERS) {
+        0 => None,
+        n => Some(Arc::new(RenderWorkerPool::new(n).map_err(|e| e.to_string())?)),
+    };
+    diag!("set_render_workers count={}", pool.as_ref().map_or(0, |p| p.size()));
+
+    // The previous pool's processes exit once in-flight renders drop it.
+    *state.render_workers.lock().unwrap() = pool;
+    Ok(())
+}
+
+// ============================================================================
+// Prefetch Commands
+// ============================================================================
+
+/// Report the current viewport so the background worker can prefetch the
+/// pages around it.
+///
+/// # Arguments
+/// * `doc_id` - Document ID from load_pdf
+/// * `visible_pages` - 0-based indices of the pages currently visible
+/// * `direction` - Scroll direction (`forward`, `backward` or `none`)
+/// * `scale` - Render scale the viewer uses for these pages
+/// * `options` - Render options the viewer uses for these pages
+/// * `prefetch_pages` - Pages to prefetch on each side (default 2, max 8)
+///
+/// Each hint replaces the previous one; pass no visible pages to stop
+/// prefetching.
+#[tauri::command]
+pub fn set_viewport_hint(
+    doc_id: String,
+    visible_pages: Vec<u32>,
+    direction: ScrollDirection,
+    scale: f32,
+    options: Option<RenderOptions>,
+    prefetch_pages: Option<u32>,
+    state: State<AppState>,
+) -> Result<(), String> {
+    if !scale.is_finite() || scale <= 0.0 {
+        return Err(format!("Invalid scale: {}", scale));
+    }
+
+    let page_count = {
+        let docs = state.documents.lock().unwrap();
+        docs.get(&doc_id)
+            .ok_or_else(|| "Document not found".to_string())?
+            .page_count
+    };
+
+    state.prefetcher.submit(ViewportHint {
+        doc_id,
+        visible_pages,
+        direction,
+        scale,
+        options: options.unwrap_or_default(),
+        pages_ahead: prefetch_pages
+            .unwrap_or(DEFAULT_PREFETCH_PAGES)
+            .min(MAX_PREFETCH_PAGES),
+        page_count,
+    });
+    Ok(())
+}
+
+// ============================================================================
+// Annotation Commands
+// ============================================================================
+
+/// Helper: `path` canonicalized, or as given when it can't be resolved.
+fn canonical_path(path: &str) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| PathBuf::from(path))
+}
+
+/// Helper: reload every open document backed by `path` after a command
+/// rewrote that file, so pages, thumbnails and search show the saved
+/// content. Paths are compared canonicalized.
+///
+/// The document's render cache, content hash and search index are reset,
+/// and its layer overrides dropped with the old bytes. A password-protected
+/// document can't be reopened (the password is not kept): only its cached
+/// renders are dropped, and the frontend must reopen it to see the change.
+fn reload_documents_for_path(path: &str, app: &AppHandle, state: &AppState) {
+    let target = canonical_path(path);
+    let backed: Vec<(String, bool, Arc<Mutex<()>>)> = {
+        let docs = state.documents.lock().unwrap();
+        docs.iter()
+            .filter(|(_, d)| d.path.as_deref().is_some_and(|p| canonical_path(p) == target))
+            .map(|(id, d)| (id.clone(), d.password_protected, Arc::clone(&d.reopen_lock)))
+            .collect()
+    };
+    if backed.is_empty() {
+        return;
+    }
+    let bytes = match std::fs::read(path) {
+        Ok(b) => Some(b),
+        Err(e) => {
+            diag!("reload {}: failed to read: {}", path, e);
+            None
+        }
+    };
+
+    for (doc_id, password_protected, reopen_lock) in backed {
+        // A layer change in flight finishes first; its overrides are then
+        // dropped with the old bytes.
+        let _reopening = reopen_lock.lock().unwrap();
+        let reloaded = match &bytes {
+            Some(bytes) if !password_protected => CachedPdf::new(bytes.clone(), None)
+                .inspect_err(|e| diag!("reload doc={}: failed to open: {}", doc_id, e))
+                .ok(),
+            _ => None,
+        };
+        let is_reloaded = reloaded.is_some();
+        if let Some(cached_pdf) = &reloaded {
+            // Workers that have the document open reopen it with the new bytes.
+            if let Some(pool) = state.render_workers.lock().unwrap().as_ref() {
+                pool.register_document(&doc_id, cached_pdf.bytes().to_vec());
+            }
+        }
+
+        let render_cache = {
+            let mut docs = state.documents.lock().unwrap();
+            let Some(doc_state) = docs.get_mut(&doc_id) else { continue };
+            if let Some(cached_pdf) = reloaded {
+                doc_state.cached_pdf = Arc::new(Mutex::new(cached_pdf));
+                doc_state.content_hash = Arc::default();
+                doc_state.layers = None;
+                doc_state.search_index = Arc::default();
+            }
+            Arc::clone(&doc_state.render_cache)
+        };
+        render_cache.lock().unwrap().invalidate();
+        if is_reloaded {
+            build_search_index(app, &doc_id);
+        }
+        diag!("reload doc={} from {} reloaded={}", doc_id, path, is_reloaded);
+    }
+}
+
+/// Get file path for a loaded document.
+#[tauri::command]
+pub fn get_document_path(doc_id: String, state: State<AppState>) -> Result<Option<String>, String> {
+    clone_doc_path(&doc_id, &state)
+}
+
+/// Get existing annotations from a PDF file.
+#[tauri::command]
+pub fn get_annotations(path: String) -> Result<Vec<AnnotationData>, String> {
+    annotations::get_annotations(&path).map_err(|e| e.to_string())
+}
+
+/// Save annotations to a PDF file.
+/// If dest_path is None, saves to the original file.
+///
+/// Open documents backed by the written file are reloaded from it (see
+/// `reload_documents_for_path`).
+#[tauri::command]
+pub fn save_annotations(
+    source_path: String,
+    dest_path: Option<String>,
+    annotations_data: Vec<AnnotationData>,
+    app: AppHandle,
+    state: State<AppState>,
+) -> Result<SaveResult, String> {
+    let dest = dest_path.unwrap_or_else(|| source_path.clone());
+    
+    // If saving to the same file, we need to use a temp file first
+    if dest == source_path {
+        let temp_path = format!("{}.tmp", source_path);
+        
+        // Save to temp file
+        let result = annotations::save_annotations(&source_path, &temp_path, annotations_data)
+            .map_err(|e| e.to_string())?;
+        
+        // Replace original with temp
+        std::fs::rename(&temp_path, &source_path)
+            .map_err(|e| format!("Failed to replace original file: {}", e))?;
+        reload_documents_for_path(&source_path, &app, &state);
+        
+        Ok(SaveResult {
+            success: true,
+            path: source_path,
+            annotations_count: result.annotations_count,
+        })
+    } else {
+        let result = annotations::save_annotations(&source_path, &dest, annotations_data)
+            .map_err(|e| e.to_string())?;
+        reload_documents_for_path(&dest, &app, &state);
+        Ok(result)
+    }
+}
+
+/// Remove a specific annotation from a PDF. Open documents backed by the
+/// written file are reloaded, as for `save_annotations`.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub fn remove_annotation(
+    source_path: String,
+    dest_path: Option<String>,
+    page_index: u32,
+    rect_x1: f64,
     rect_y1: f64,
     rect_x2: f64,
     rect_y2: f64,
+    app: AppHandle,
+    state: State<AppState>,
 ) -> Result<bool, String> {
     let dest = dest_path.unwrap_or_else(|| source_path.clone());
     let rect = PdfRect { x1: rect_x1, y1: rect_y1, x2: rect_x2, y2: rect_y2 };
@@ -442,6 +2391,7 @@ pub fn remove_annotation(
         if result {
             std::fs::rename(&temp_path, &source_path)
                 .map_err(|e| format!("Failed to replace original file: {}", e))?;
+            reload_documents_for_path(&source_path, &app, &state);
         } else {
             // Clean up temp file if annotation wasn't found
             let _ = std::fs::remove_file(&temp_path);
@@ -449,17 +2399,24 @@ pub fn remove_annotation(
         
         Ok(result)
     } else {
-        annotations::remove_annotation(&source_path, &dest, page_index, &rect)
-            .map_err(|e| e.to_string())
+        let result = annotations::remove_annotation(&source_path, &dest, page_index, &rect)
+            .map_err(|e| e.to_string())?;
+        if result {
+            reload_documents_for_path(&dest, &app, &state);
+        }
+        Ok(result)
     }
 }
 
-/// Clear all annotations from a page.
+/// Clear all annotations from a page. Open documents backed by the written
+/// file are reloaded, as for `save_annotations`.
 #[tauri::command]
 pub fn clear_page_annotations(
     source_path: String,
     dest_path: Option<String>,
     page_index: u32,
+    app: AppHandle,
+    state: State<AppState>,
 ) -> Result<usize, String> {
     let dest = dest_path.unwrap_or_else(|| source_path.clone());
     
@@ -472,13 +2429,74 @@ pub fn clear_page_annotations(
         if count > 0 {
             std::fs::rename(&temp_path, &source_path)
                 .map_err(|e| format!("Failed to replace original file: {}", e))?;
+            reload_documents_for_path(&source_path, &app, &state);
         } else {
             let _ = std::fs::remove_file(&temp_path);
         }
         
         Ok(count)
     } else {
-        annotations::clear_page_annotations(&source_path, &dest, page_index)
-            .map_err(|e| e.to_string())
+        let count = annotations::clear_page_annotations(&source_path, &dest, page_index)
+            .map_err(|e| e.to_string())?;
+        if count > 0 {
+            reload_documents_for_path(&dest, &app, &state);
+        }
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scheduler_priority_and_cancellation() {
+        let scheduler = RenderScheduler::default();
+        {
+            let mut queue = scheduler.queue.lock().unwrap();
+            queue.waiting.push(RenderTicket { seq: 0, priority: RenderPriority::Low, cycle_id: None });
+            queue.waiting.push(RenderTicket { seq: 1, priority: RenderPriority::High, cycle_id: Some(1) });
+            queue.waiting.push(RenderTicket { seq: 2, priority: RenderPriority::High, cycle_id: Some(2) });
+            // Highest priority first, earliest arrival among equals.
+            assert_eq!(queue.next_ticket(), Some(1));
+            queue.waiting.clear();
+        }
+
+        scheduler.cancel_before(2);
+        {
+            let mut queue = scheduler.queue.lock().unwrap();
+            queue.waiting.push(RenderTicket { seq: 1, priority: RenderPriority::High, cycle_id: Some(1) });
+            queue.waiting.push(RenderTicket { seq: 0, priority: RenderPriority::Low, cycle_id: None });
+            // Cycle 1 is superseded; untagged requests are never cancelled.
+            assert_eq!(queue.next_ticket(), Some(0));
+            queue.waiting.clear();
+        }
+
+        assert_eq!(
+            scheduler.acquire(RenderPriority::High, Some(1), 1).err().as_deref(),
+            Some(RENDER_CANCELLED)
+        );
+        let slot = scheduler.acquire(RenderPriority::Normal, Some(2), 1);
+        assert!(slot.is_ok());
+        drop(slot);
+        assert_eq!(scheduler.queue.lock().unwrap().running, 0);
+    }
+
+    #[test]
+    fn test_scheduler_drops_superseded_render() {
+        let scheduler = RenderScheduler::default();
+        let busy = scheduler.acquire(RenderPriority::Normal, None, 1).unwrap();
+        std::thread::scope(|scope| {
+            let stale = scope.spawn(|| scheduler.acquire(RenderPriority::Low, Some(1), 1).map(drop));
+            while scheduler.pending() == 0 {
+                std::thread::yield_now();
+            }
+            let current = scope.spawn(|| scheduler.acquire(RenderPriority::High, Some(2), 1).map(drop));
+            // Queuing cycle 2 drops the waiting cycle 1 render.
+            assert_eq!(stale.join().unwrap().err().as_deref(), Some(RENDER_CANCELLED));
+            drop(busy);
+            assert!(current.join().unwrap().is_ok());
+        });
+        assert_eq!(scheduler.queue.lock().unwrap().running, 0);
     }
 }
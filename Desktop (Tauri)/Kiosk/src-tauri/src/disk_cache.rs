@@ -0,0 +1,155 @@
+//! Housekeeping shared by the on-disk caches under the app data directory.
+//!
+//! Cache files are written to a temporary file and renamed into place
+//! (`write_atomic`), so readers never see a partial file. The temporary name
+//! is unique to the process and the write, so two windows storing the same
+//! entry at once don't write into each other's file.
+//!
+//! Each cache is kept within a byte budget by `trim`, run in the background
+//! at startup. Its entries (the files and directories directly under the
+//! cache directory) are deleted least recently used first, going by
+//! modification time; caches `touch` the files they read, so that time is
+//! the last use. Temporary files left behind by an interrupted write are
+//! deleted too.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime};
+
+/// Extension of temporary files.
+const TEMP_EXTENSION: &str = "tmp";
+
+/// Temporary files older than this belong to a write that never finished.
+const STALE_TEMP_AGE: Duration = Duration::from_secs(60 * 60);
+
+/// Write `data` to `path` through a temporary file in the same directory.
+pub fn write_atomic(path: &Path, data: &[u8]) -> io::Result<()> {
+    static NEXT_TEMP: AtomicU64 = AtomicU64::new(0);
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "cache path has no file name"))?;
+    let temp_path = path.with_file_name(format!(
+        "{}.{}-{}.{}",
+        file_name.to_string_lossy(),
+        std::process::id(),
+        NEXT_TEMP.fetch_add(1, Ordering::Relaxed),
+        TEMP_EXTENSION
+    ));
+    fs::write(&temp_path, data)?;
+    fs::rename(&temp_path, path).inspect_err(|_| {
+        let _ = fs::remove_file(&temp_path);
+    })
+}
+
+/// Mark a cache file as just used. Best effort: a file that can't be
+/// touched is merely evicted earlier.
+pub fn touch(path: &Path) {
+    if let Ok(file) = fs::File::options().write(true).open(path) {
+        let _ = file.set_modified(SystemTime::now());
+    }
+}
+
+/// One file or directory directly under a cache directory.
+struct CacheEntry {
+    path: PathBuf,
+    bytes: u64,
+    last_used: SystemTime,
+}
+
+/// Bytes held by `path` and its latest modification time. Stale temporary
+/// files are deleted on the way and not counted.
+fn usage(path: &Path, metadata: &fs::Metadata) -> io::Result<(u64, SystemTime)> {
+    let modified = metadata.modified()?;
+    if !metadata.is_dir() {
+        let is_temp = path.extension().is_some_and(|ext| ext == TEMP_EXTENSION);
+        let age = SystemTime::now().duration_since(modified).unwrap_or_default();
+        if is_temp && age > STALE_TEMP_AGE {
+            fs::remove_file(path)?;
+            return Ok((0, SystemTime::UNIX_EPOCH));
+        }
+        return Ok((metadata.len(), modified));
+    }
+    let (mut bytes, mut last_used) = (0, modified);
+    for child in fs::read_dir(path)? {
+        let child = child?;
+        let (child_bytes, child_used) = usage(&child.path(), &child.metadata()?)?;
+        bytes += child_bytes;
+        last_used = last_used.max(child_used);
+    }
+    Ok((bytes, last_used))
+}
+
+/// Delete least recently used entries of the cache in `dir` until it holds
+/// at most `max_bytes`. Returns the number of bytes freed; a missing
+/// directory is an empty cache.
+pub fn trim(dir: &Path, max_bytes: u64) -> io::Result<u64> {
+    let children = match fs::read_dir(dir) {
+        Ok(children) => children,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e),
+    };
+    let mut entries = Vec::new();
+    for child in children {
+        let child = child?;
+        let path = child.path();
+        let (bytes, last_used) = usage(&path, &child.metadata()?)?;
+        entries.push(CacheEntry { path, bytes, last_used });
+    }
+
+    entries.sort_by_key(|entry| entry.last_used);
+    let mut total: u64 = entries.iter().map(|entry| entry.bytes).sum();
+    let mut freed = 0;
+    for entry in entries {
+        if total <= max_bytes {
+            break;
+        }
+        let removed = if entry.path.is_dir() {
+            fs::remove_dir_all(&entry.path)
+        } else {
+            fs::remove_file(&entry.path)
+        };
+        // An entry that can't be deleted (e.g. in use) still counts.
+        if removed.is_ok() {
+            total -= entry.bytes;
+            freed += entry.bytes;
+        }
+    }
+    Ok(freed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trim_least_recently_used() {
+        let dir = std::env::temp_dir().join(format!("kiosk-disk-cache-{}", std::process::id()));
+        let now = SystemTime::now();
+        let write = |name: &str, len: usize, age_secs: u64| {
+            let path = dir.join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            write_atomic(&path, &vec![0; len]).unwrap();
+            let file = fs::File::options().write(true).open(&path).unwrap();
+            file.set_modified(now - Duration::from_secs(age_secs)).unwrap();
+        };
+        write("old.idx", 400, 300);
+        write("doc/0_128.jpg", 300, 500);
+        write("doc/1_128.jpg", 300, 10);
+        write("new.idx", 400, 100);
+        write("new.idx.1-0.tmp", 50, 2 * 60 * 60);
+
+        // `old.idx` is least recently used; `doc` was written to just now.
+        assert_eq!(trim(&dir, 1000).unwrap(), 400);
+        assert!(!dir.join("old.idx").exists() && !dir.join("new.idx.1-0.tmp").exists());
+        assert!(dir.join("doc/0_128.jpg").exists() && dir.join("new.idx").exists());
+
+        touch(&dir.join("new.idx"));
+        assert_eq!(trim(&dir, 500).unwrap(), 600);
+        assert!(!dir.join("doc").exists() && dir.join("new.idx").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(trim(&dir, 0).unwrap(), 0);
+    }
+}
@@ -0,0 +1,202 @@
+//! Export of rendered pages to image files.
+//!
+//! `export_pages_as_images` (see `commands`) renders each requested page at
+//! the requested DPI and writes it into a folder, one file per page, named
+//! from a pattern:
+//!
+//! ```text
+//! {name}-{page}       ->  report-01.png, report-02.png, ... (default)
+//! slide_{page}        ->  slide_01.png, ...
+//! ```
+//!
+//! `{name}` is the document's file name without extension and `{page}` the
+//! 1-based page number, zero-padded to the width of the page count so the
+//! files sort in page order. The extension is added from the format.
+//!
+//! Existing files are left alone unless `overwrite` is set: their pages fail
+//! and are listed in the result like any other failed page.
+//!
+//! Page ranges use the print-dialog syntax: `1-3,5,8-` (1-based, open
+//! ranges run to the last page).
+
+use crate::pdf::{PageImageFormat, DEFAULT_JPEG_QUALITY};
+use serde::{Deserialize, Serialize};
+
+/// Event emitted after each exported page.
+pub const EXPORT_PROGRESS_EVENT: &str = "export-progress";
+
+/// Resolution used when none is given.
+pub const DEFAULT_EXPORT_DPI: f32 = 150.0;
+
+/// Highest accepted resolution. An A4 page at 1200 DPI is ~140 MP.
+pub const MAX_EXPORT_DPI: f32 = 1200.0;
+
+/// File name pattern used when none is given.
+pub const DEFAULT_EXPORT_PATTERN: &str = "{name}-{page}";
+
+fn default_dpi() -> f32 {
+    DEFAULT_EXPORT_DPI
+}
+
+fn default_format() -> PageImageFormat {
+    PageImageFormat::Png
+}
+
+fn default_quality() -> u8 {
+    DEFAULT_JPEG_QUALITY
+}
+
+fn default_pattern() -> String {
+    DEFAULT_EXPORT_PATTERN.to_string()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// What to export and where.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImageExportOptions {
+    /// Page range such as `1-3,5` (1-based); all pages when omitted
+    #[serde(default)]
+    pub pages: Option<String>,
+    /// Output resolution in dots per inch
+    #[serde(default = "default_dpi")]
+    pub dpi: f32,
+    /// `png` (default), `jpeg`, `tiff` or `webp`
+    #[serde(default = "default_format")]
+    pub format: PageImageFormat,
+    /// JPEG quality, 1-100
+    #[serde(default = "default_quality")]
+    pub quality: u8,
+    /// Destination folder, created if missing
+    pub directory: String,
+    /// File name pattern without extension (see module docs)
+    #[serde(default = "default_pattern")]
+    pub pattern: String,
+    /// Burn annotations into the images
+    #[serde(default = "default_true")]
+    pub annotations: bool,
+    /// Apply the document's accessibility filter, as page renders and
+    /// thumbnails do; off exports the page's own colors
+    #[serde(default = "default_true")]
+    pub apply_view_filters: bool,
+    /// Replace files that already exist; otherwise such pages fail
+    #[serde(default)]
+    pub overwrite: bool,
+}
+
+/// Payload of `EXPORT_PROGRESS_EVENT`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportProgress {
+    pub doc_id: String,
+    /// 0-based index of the page just processed
+    pub page_index: u32,
+    /// Pages processed so far, including this one
+    pub completed: usize,
+    /// Pages to export in total
+    pub total: usize,
+    /// Path of the written file; `None` when the page failed
+    pub path: Option<String>,
+}
+
+/// Outcome of an export.
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportResult {
+    /// Paths of the written files, in page order
+    pub files: Vec<String>,
+    /// 0-based indices of pages that failed; see the log for details
+    pub failed: Vec<u32>,
+}
+
+/// Parse a 1-based page range (`1-3,5,8-`) into 0-based page indices, in
+/// the order given, without duplicates.
+pub fn parse_page_range(spec: &str, page_count: u32) -> Result<Vec<u32>, String> {
+    let invalid = || format!("Invalid page range: {}", spec);
+    let page = |s: &str| -> Result<u32, String> {
+        let n = s.trim().parse::<u32>().map_err(|_| invalid())?;
+        if n == 0 || n > page_count {
+            return Err(format!("Page {} is out of range (1-{})", n, page_count));
+        }
+        Ok(n)
+    };
+
+    let mut pages = Vec::new();
+    for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let (first, last) = match part.split_once('-') {
+            Some((a, b)) => (
+                if a.trim().is_empty() { 1 } else { page(a)? },
+                if b.trim().is_empty() { page_count } else { page(b)? },
+            ),
+            None => (page(part)?, page(part)?),
+        };
+        if first > last {
+            return Err(invalid());
+        }
+        for n in first..=last {
+            if !pages.contains(&(n - 1)) {
+                pages.push(n - 1);
+            }
+        }
+    }
+
+    if pages.is_empty() {
+        return Err(invalid());
+    }
+    Ok(pages)
+}
+
+/// File name for `page_index` from `pattern` (see module docs).
+pub fn export_file_name(
+    pattern: &str,
+    name: &str,
+    page_index: u32,
+    page_count: u32,
+    format: PageImageFormat,
+) -> String {
+    let width = page_count.max(1).to_string().len();
+    let page = format!("{:0width$}", page_index + 1, width = width);
+    format!("{}.{}", pattern.replace("{name}", name).replace("{page}", &page), format.extension())
+}
+
+/// Check that `pattern` names plain files in the destination folder and
+/// that `page_count` pages get distinct names.
+pub fn validate_pattern(pattern: &str, page_count: usize) -> Result<(), String> {
+    if pattern.trim().is_empty()
+        || pattern.contains(['/', '\\'])
+        || pattern.contains("..")
+    {
+        return Err(format!("Invalid file name pattern: {}", pattern));
+    }
+    if page_count > 1 && !pattern.contains("{page}") {
+        return Err("File name pattern must contain {page} to export several pages".to_string());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_page_range() {
+        assert_eq!(parse_page_range("1-3,5", 10).unwrap(), vec![0, 1, 2, 4]);
+        assert_eq!(parse_page_range("8-", 10).unwrap(), vec![7, 8, 9]);
+        assert_eq!(parse_page_range("-2, 2, 1", 10).unwrap(), vec![0, 1]);
+        assert!(parse_page_range("0", 10).is_err());
+        assert!(parse_page_range("11", 10).is_err());
+        assert!(parse_page_range("3-1", 10).is_err());
+        assert!(parse_page_range("a", 10).is_err());
+        assert!(parse_page_range("", 10).is_err());
+    }
+
+    #[test]
+    fn test_export_file_name() {
+        let name = export_file_name(DEFAULT_EXPORT_PATTERN, "report", 4, 120, PageImageFormat::Jpeg);
+        assert_eq!(name, "report-005.jpg");
+        assert!(validate_pattern("slide_{page}", 3).is_ok());
+        assert!(validate_pattern("cover", 1).is_ok());
+        assert!(validate_pattern("cover", 2).is_err());
+        assert!(validate_pattern("../{page}", 2).is_err());
+    }
+}
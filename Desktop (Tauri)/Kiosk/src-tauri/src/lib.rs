@@ -4,18 +4,56 @@
 // with glyph-accurate text selection and native-grade output.
 
 mod annotations;
+mod cli;
 mod commands;
+mod disk_cache;
+mod export;
+mod optional_content;
 mod pdf;
+mod prefetch;
+mod progressive;
+mod protocol;
+mod render_cache;
+mod render_worker;
+mod search_index;
+mod search_session;
+mod thumbnail_cache;
 
 use commands::{
     close_pdf, get_all_page_infos, get_char_rects, get_document_info, get_page_info,
-    get_page_text, load_pdf, load_pdf_bytes, render_page, search_text, AppState,
+    get_page_text, load_pdf, load_pdf_bytes, render_page, render_page_binary, render_page_region,
+    search_text, cancel_renders, AppState,
+    // Render cache commands
+    get_render_cache_stats, set_render_cache_budget, set_max_render_pixels,
+    // Accessibility commands
+    set_accessibility_filter,
+    // View rotation commands
+    set_view_rotation,
+    // Optional content commands
+    get_optional_content, set_layer_visibility,
+    // Thumbnail commands
+    render_thumbnails,
+    // Export commands
+    export_pages_as_images,
+    // Progressive render commands
+    start_progressive_render, get_progressive_frame, pause_progressive_render,
+    resume_progressive_render, cancel_progressive_render,
+    // Search session commands
+    start_search, get_search_results, cancel_search,
+    // Render worker commands
+    set_render_workers,
+    // Prefetch commands
+    set_viewport_hint,
     // Annotation commands
     get_annotations, save_annotations, remove_annotation, clear_page_annotations,
     get_document_path,
 };
 use std::sync::Mutex;
+use tauri::Manager;
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 use tauri::{Emitter, RunEvent};
+use search_index::{SearchIndexCache, SEARCH_INDEX_CACHE_DIR};
+use thumbnail_cache::{ThumbnailCache, THUMBNAIL_CACHE_DIR};
 
 /// Stores the file path that was passed to the app on launch (if any).
 /// This is used to open PDFs when the app is launched via file association.
@@ -33,6 +71,7 @@ fn is_pdf_file(path: &str) -> bool {
 }
 
 /// Check if a path string looks like a PDF (extension check only, for URLs before download).
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 fn looks_like_pdf(path: &str) -> bool {
     path.to_lowercase().ends_with(".pdf")
 }
@@ -86,6 +125,7 @@ fn get_pdf_from_args() -> Option<String> {
 }
 
 /// Convert a URL to a file path string, handling macOS file:// URLs properly.
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 fn url_to_file_path(url: &url::Url) -> Option<String> {
     // Use the URL's to_file_path method for proper handling
     url.to_file_path()
@@ -94,6 +134,7 @@ fn url_to_file_path(url: &url::Url) -> Option<String> {
 }
 
 /// Handle file associations - extract PDF paths from URLs and emit to frontend.
+#[cfg(any(target_os = "macos", target_os = "ios"))]
 fn handle_file_associations<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>, urls: Vec<url::Url>) {
     eprintln!("[Kiosk] Received file open event with {} URLs", urls.len());
     
@@ -129,6 +170,18 @@ fn handle_file_associations<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>,
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    // Render worker processes re-launch this executable; serve renders over
+    // stdin/stdout instead of starting the app.
+    if render_worker::is_worker_invocation() {
+        render_worker::run_worker();
+        return;
+    }
+
+    // `kiosk info|text|render|search|annotations ...` runs headless.
+    if cli::is_cli_invocation() {
+        std::process::exit(cli::run_cli());
+    }
+
     // Initialize app state
     let app_state = AppState::new();
 
@@ -143,6 +196,8 @@ pub fn run() {
         .plugin(tauri_plugin_dialog::init())
         .manage(app_state)
         .manage(LaunchFile(Mutex::new(launch_file)))
+        // kiosk://render/{doc_id}/{page} — page images for <img>/fetch
+        .register_asynchronous_uri_scheme_protocol(protocol::SCHEME, protocol::handle_request)
         .invoke_handler(tauri::generate_handler![
             // PDF loading and viewing
             load_pdf,
@@ -153,17 +208,66 @@ pub fn run() {
             get_page_info,
             get_all_page_infos,
             render_page,
+            render_page_binary,
+            render_page_region,
+            cancel_renders,
             get_char_rects,
             get_page_text,
             search_text,
             get_launch_file,
+            // Render cache commands
+            get_render_cache_stats,
+            set_render_cache_budget,
+            set_max_render_pixels,
+            // Accessibility commands
+            set_accessibility_filter,
+            // View rotation commands
+            set_view_rotation,
+            // Optional content commands
+            get_optional_content,
+            set_layer_visibility,
+            // Thumbnail commands
+            render_thumbnails,
+            // Export commands
+            export_pages_as_images,
+            // Progressive render commands
+            start_progressive_render,
+            get_progressive_frame,
+            pause_progressive_render,
+            resume_progressive_render,
+            cancel_progressive_render,
+            // Search session commands
+            start_search,
+            get_search_results,
+            cancel_search,
+            // Render worker commands
+            set_render_workers,
+            // Prefetch commands
+            set_viewport_hint,
             // Annotation commands
             get_annotations,
             save_annotations,
             remove_annotation,
             clear_page_annotations,
         ])
-        .setup(|_app| {
+        .setup(|app| {
+            // Background renderer for pages around the viewport
+            prefetch::spawn_worker(app.handle().clone());
+            // Keep the on-disk caches within their budgets
+            if let Ok(dir) = app.path().app_data_dir() {
+                std::thread::spawn(move || {
+                    let thumbnails = ThumbnailCache::new(dir.join(THUMBNAIL_CACHE_DIR));
+                    match thumbnails.trim() {
+                        Ok(freed) => eprintln!("[Kiosk] Thumbnail cache trimmed, {} bytes freed", freed),
+                        Err(e) => eprintln!("[Kiosk] Failed to trim thumbnail cache: {}", e),
+                    }
+                    let search_indexes = SearchIndexCache::new(dir.join(SEARCH_INDEX_CACHE_DIR));
+                    match search_indexes.trim() {
+                        Ok(freed) => eprintln!("[Kiosk] Search index cache trimmed, {} bytes freed", freed),
+                        Err(e) => eprintln!("[Kiosk] Failed to trim search index cache: {}", e),
+                    }
+                });
+            }
             eprintln!("[Kiosk] App setup complete");
             Ok(())
         })
@@ -171,6 +275,7 @@ pub fn run() {
         .expect("error while building tauri application");
 
     // Run with event handler for macOS file associations
+    #[cfg_attr(not(any(target_os = "macos", target_os = "ios")), allow(unused_variables))]
     app.run(|app_handle, event| {
         match event {
             // Handle macOS "Open With" / double-click file associations
@@ -0,0 +1,333 @@
+//! Optional content groups (layers).
+//!
+//! Engineering drawings and maps put parts of their pages in Optional
+//! Content Groups (OCGs), listed in the catalog's `/OCProperties`. Its
+//! default configuration `/D` says which groups start visible
+//! (`/BaseState`, `/ON`, `/OFF`) and how a layers panel should nest them
+//! (`/Order`).
+//!
+//! pdfium always renders with the default configuration and has no API to
+//! change it, so visibility is changed by rewriting `/D` in a copy of the
+//! document (`with_layer_visibility`) and rendering that copy instead.
+//!
+//! Groups are identified by their object id, formatted as `"{num} {gen}"`.
+
+use lopdf::{decode_text_string, Dictionary, Document, Object, ObjectId};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+/// Nesting limit for `/Order`, which may be malformed or cyclic.
+const MAX_ORDER_DEPTH: usize = 32;
+
+/// One optional content group.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OptionalContentGroup {
+    /// Object id, `"{num} {gen}"`
+    pub id: String,
+    /// Display name from the group's `/Name`
+    pub name: String,
+    /// Visible in the document's default configuration
+    pub default_visible: bool,
+    /// Visible with the current overrides applied
+    pub visible: bool,
+    /// Listed in `/Locked`: the author asks viewers not to toggle it
+    pub locked: bool,
+}
+
+/// An entry of the layers panel tree.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LayerNode {
+    /// Group shown at this entry; `None` for a label that only heads children
+    pub group: Option<String>,
+    /// Text of a group-less heading
+    pub label: Option<String>,
+    pub children: Vec<LayerNode>,
+}
+
+/// A document's optional content.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OptionalContent {
+    /// Every group, in `/OCGs` order; empty when the document has no layers
+    pub groups: Vec<OptionalContentGroup>,
+    /// Layers panel tree from `/D /Order`; groups it omits are appended at
+    /// the top level
+    pub order: Vec<LayerNode>,
+}
+
+impl OptionalContent {
+    /// Set `visible` from `visibility` (by group id), defaulting to each
+    /// group's default visibility.
+    pub fn apply(&mut self, visibility: &HashMap<String, bool>) {
+        for group in &mut self.groups {
+            group.visible = visibility.get(&group.id).copied().unwrap_or(group.default_visible);
+        }
+    }
+
+    pub fn group(&self, id: &str) -> Option<&OptionalContentGroup> {
+        self.groups.iter().find(|g| g.id == id)
+    }
+}
+
+/// Visibility chosen by the user, together with the document bytes as
+/// opened. Kept while any group differs from its default.
+#[derive(Debug, Clone)]
+pub struct LayerOverrides {
+    pub original: Arc<Vec<u8>>,
+    /// Groups whose visibility differs from the default, by id
+    pub visibility: HashMap<String, bool>,
+}
+
+fn group_key(id: ObjectId) -> String {
+    format!("{} {}", id.0, id.1)
+}
+
+fn load(bytes: &[u8]) -> Result<Document, String> {
+    Document::load_mem(bytes).map_err(|e| format!("Failed to read layers: {}", e))
+}
+
+fn resolve_dict<'a>(doc: &'a Document, object: &'a Object) -> Option<&'a Dictionary> {
+    doc.dereference(object).ok()?.1.as_dict().ok()
+}
+
+fn resolve_array<'a>(doc: &'a Document, object: &'a Object) -> Option<&'a Vec<Object>> {
+    doc.dereference(object).ok()?.1.as_array().ok()
+}
+
+/// The catalog's `/OCProperties`, if any.
+fn oc_properties(doc: &Document) -> Option<&Dictionary> {
+    resolve_dict(doc, doc.catalog().ok()?.get(b"OCProperties").ok()?)
+}
+
+/// Read the groups and layers panel tree of a document.
+pub fn read_optional_content(bytes: &[u8]) -> Result<OptionalContent, String> {
+    let doc = load(bytes)?;
+    let Some(properties) = oc_properties(&doc) else {
+        return Ok(OptionalContent::default());
+    };
+    let config = properties.get(b"D").ok().and_then(|d| resolve_dict(&doc, d));
+    let config_array = |key: &[u8]| {
+        config
+            .and_then(|c| c.get(key).ok())
+            .and_then(|a| resolve_array(&doc, a))
+    };
+    let config_ids = |key: &[u8]| -> HashSet<ObjectId> {
+        config_array(key)
+            .map(|a| a.iter().filter_map(|o| o.as_reference().ok()).collect())
+            .unwrap_or_default()
+    };
+
+    // `/BaseState` is `/ON` unless stated otherwise; `/Unchanged` only
+    // matters for alternate configurations and reads as `/ON` here.
+    let base_off = config
+        .and_then(|c| c.get(b"BaseState").ok())
+        .and_then(|s| s.as_name().ok())
+        == Some(b"OFF".as_slice());
+    let (on, off, locked) = (config_ids(b"ON"), config_ids(b"OFF"), config_ids(b"Locked"));
+
+    let mut known = HashSet::new();
+    let mut groups = Vec::new();
+    let ocgs = properties.get(b"OCGs").ok().and_then(|a| resolve_array(&doc, a));
+    for id in ocgs.into_iter().flatten().filter_map(|o| o.as_reference().ok()) {
+        if !known.insert(id) {
+            continue;
+        }
+        let name = doc
+            .get_dictionary(id)
+            .ok()
+            .and_then(|d| d.get(b"Name").ok())
+            .and_then(|n| doc.dereference(n).ok())
+            .and_then(|(_, n)| decode_text_string(n).ok())
+            .unwrap_or_else(|| group_key(id));
+        let default_visible = if base_off { on.contains(&id) } else { !off.contains(&id) };
+        groups.push(OptionalContentGroup {
+            id: group_key(id),
+            name,
+            default_visible,
+            visible: default_visible,
+            locked: locked.contains(&id),
+        });
+    }
+
+    let mut order = config_array(b"Order")
+        .map(|items| order_nodes(&doc, items, &known, 0))
+        .unwrap_or_default();
+    let mut listed = HashSet::new();
+    collect_groups(&order, &mut listed);
+    for group in &groups {
+        if !listed.contains(&group.id) {
+            order.push(LayerNode { group: Some(group.id.clone()), ..LayerNode::default() });
+        }
+    }
+
+    Ok(OptionalContent { groups, order })
+}
+
+/// Build panel nodes from an `/Order` array.
+///
+/// A nested array lists the children of the group before it, or, when it
+/// starts with a string, forms a labelled heading of its own.
+fn order_nodes(doc: &Document, items: &[Object], known: &HashSet<ObjectId>, depth: usize) -> Vec<LayerNode> {
+    let mut nodes: Vec<LayerNode> = Vec::new();
+    if depth > MAX_ORDER_DEPTH {
+        return nodes;
+    }
+    for item in items {
+        if let Ok(id) = item.as_reference() {
+            if known.contains(&id) {
+                nodes.push(LayerNode { group: Some(group_key(id)), ..LayerNode::default() });
+                continue;
+            }
+        }
+        let Some(array) = resolve_array(doc, item) else {
+            continue;
+        };
+        let (label, rest) = match array.first() {
+            Some(first @ Object::String(..)) => (decode_text_string(first).ok(), &array[1..]),
+            _ => (None, array.as_slice()),
+        };
+        let children = order_nodes(doc, rest, known, depth + 1);
+        match nodes.last_mut() {
+            Some(parent) if label.is_none() && parent.group.is_some() && parent.children.is_empty() => {
+                parent.children = children;
+            }
+            _ => nodes.push(LayerNode { group: None, label, children }),
+        }
+    }
+    nodes
+}
+
+fn collect_groups(nodes: &[LayerNode], into: &mut HashSet<String>) {
+    for node in nodes {
+        into.extend(node.group.clone());
+        collect_groups(&node.children, into);
+    }
+}
+
+/// Copy of a document whose default configuration shows exactly the
+/// `groups` marked `visible`.
+///
+/// Embedded page thumbnails are dropped from the copy, since they show
+/// the original default state.
+pub fn with_layer_visibility(bytes: &[u8], groups: &[OptionalContentGroup]) -> Result<Vec<u8>, String> {
+    let mut doc = load(bytes)?;
+    let mut properties = oc_properties(&doc)
+        .cloned()
+        .ok_or_else(|| "Document has no layers".to_string())?;
+    let mut config = properties
+        .get(b"D")
+        .ok()
+        .and_then(|d| resolve_dict(&doc, d))
+        .cloned()
+        .unwrap_or_default();
+
+    let ids: HashMap<String, ObjectId> = properties
+        .get(b"OCGs")
+        .ok()
+        .and_then(|a| resolve_array(&doc, a))
+        .into_iter()
+        .flatten()
+        .filter_map(|o| o.as_reference().ok())
+        .map(|id| (group_key(id), id))
+        .collect();
+    let references = |visible: bool| -> Vec<Object> {
+        groups
+            .iter()
+            .filter(|g| g.visible == visible)
+            .filter_map(|g| ids.get(&g.id))
+            .map(|id| Object::Reference(*id))
+            .collect()
+    };
+
+    config.set("BaseState", Object::Name(b"ON".to_vec()));
+    config.set("ON", Object::Array(references(true)));
+    config.set("OFF", Object::Array(references(false)));
+    // Automatic states (e.g. by zoom) would override the chosen visibility.
+    config.remove(b"AS");
+    properties.set("D", Object::Dictionary(config));
+    doc.catalog_mut()
+        .map_err(|e| format!("Failed to read layers: {}", e))?
+        .set("OCProperties", Object::Dictionary(properties));
+
+    for page_id in doc.get_pages().into_values() {
+        if let Ok(Object::Dictionary(page)) = doc.get_object_mut(page_id) {
+            page.remove(b"Thumb");
+        }
+    }
+
+    let mut out = Vec::new();
+    doc.save_to(&mut out)
+        .map_err(|e| format!("Failed to apply layer visibility: {}", e))?;
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::dictionary;
+
+    /// A one-page document with "Walls" (on), its child "Doors" (off) and
+    /// "Notes" (on) under a "Markup" heading.
+    fn layered_document() -> Vec<u8> {
+        let mut doc = Document::with_version("1.7");
+        let walls = doc.add_object(dictionary! { "Type" => "OCG", "Name" => Object::string_literal("Walls") });
+        let doors = doc.add_object(dictionary! { "Type" => "OCG", "Name" => Object::string_literal("Doors") });
+        let notes = doc.add_object(dictionary! { "Type" => "OCG", "Name" => Object::string_literal("Notes") });
+        let pages_id = doc.new_object_id();
+        let page = doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "MediaBox" => vec![0.into(), 0.into(), 100.into(), 100.into()],
+        });
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! { "Type" => "Pages", "Kids" => vec![page.into()], "Count" => 1 }),
+        );
+        let catalog = doc.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+            "OCProperties" => dictionary! {
+                "OCGs" => vec![walls.into(), doors.into(), notes.into()],
+                "D" => dictionary! {
+                    "OFF" => vec![doors.into()],
+                    "Order" => vec![
+                        walls.into(),
+                        vec![doors.into()].into(),
+                        vec![Object::string_literal("Markup"), notes.into()].into(),
+                    ],
+                },
+            },
+        });
+        doc.trailer.set("Root", catalog);
+        let mut bytes = Vec::new();
+        doc.save_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_read_and_override_layers() {
+        let bytes = layered_document();
+        let content = read_optional_content(&bytes).unwrap();
+        let names: Vec<_> = content.groups.iter().map(|g| (g.name.as_str(), g.default_visible)).collect();
+        assert_eq!(names, vec![("Walls", true), ("Doors", false), ("Notes", true)]);
+
+        assert_eq!(content.order.len(), 2);
+        assert_eq!(content.order[0].group.as_deref(), Some(content.groups[0].id.as_str()));
+        assert_eq!(content.order[0].children[0].group.as_deref(), Some(content.groups[1].id.as_str()));
+        assert_eq!(content.order[1].label.as_deref(), Some("Markup"));
+        assert_eq!(content.order[1].children.len(), 1);
+
+        // Show the doors, hide the notes.
+        let mut changed = content.clone();
+        let visibility = HashMap::from([
+            (content.groups[1].id.clone(), true),
+            (content.groups[2].id.clone(), false),
+        ]);
+        changed.apply(&visibility);
+        let rewritten = with_layer_visibility(&bytes, &changed.groups).unwrap();
+        let reread = read_optional_content(&rewritten).unwrap();
+        let defaults: Vec<_> = reread.groups.iter().map(|g| g.default_visible).collect();
+        assert_eq!(defaults, vec![true, true, false]);
+        assert!(read_optional_content(b"%PDF-1.7 not a pdf").is_err());
+    }
+}
@@ -0,0 +1,159 @@
+//! Color modes applied to rendered page pixels.
+//!
+//! Dark mode in the viewer chrome is not enough when the page bitmaps are
+//! still white paper, so `CachedPdf` can remap page colors before the pixels
+//! leave it:
+//!
+//! - `Inverted`: inverts lightness while preserving hue and saturation
+//!   (black text on white becomes white on black, but a red heading stays
+//!   red). Raster images can optionally be left untouched.
+//! - `Sepia`: the classic sepia tone matrix.
+//! - `Custom`: maps white to a paper color and black to an ink color, with
+//!   intermediate tones interpolated by luminance.
+
+use serde::{Deserialize, Serialize};
+
+/// Color mapping applied to rendered pages.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "lowercase")]
+pub enum ColorMode {
+    /// Colors as authored
+    #[default]
+    Normal,
+    /// Lightness inverted, hue preserved
+    Inverted {
+        /// Leave raster image objects as authored
+        #[serde(default)]
+        preserve_images: bool,
+    },
+    /// Sepia tone
+    Sepia,
+    /// White mapped to `paper`, black mapped to `ink` (RGB)
+    Custom { paper: [u8; 3], ink: [u8; 3] },
+}
+
+impl ColorMode {
+    pub fn is_normal(&self) -> bool {
+        *self == ColorMode::Normal
+    }
+
+    /// Whether raster images should be excluded from the mapping.
+    pub fn preserves_images(&self) -> bool {
+        matches!(self, ColorMode::Inverted { preserve_images: true })
+    }
+
+    /// Stable name for on-disk cache entries; `None` for `Normal`.
+    pub fn cache_tag(&self) -> Option<String> {
+        let hex = |[r, g, b]: [u8; 3]| format!("{:02x}{:02x}{:02x}", r, g, b);
+        match self {
+            ColorMode::Normal => None,
+            ColorMode::Inverted { preserve_images: false } => Some("invert".to_string()),
+            ColorMode::Inverted { preserve_images: true } => Some("invert-keepimg".to_string()),
+            ColorMode::Sepia => Some("sepia".to_string()),
+            ColorMode::Custom { paper, ink } => Some(format!("custom-{}-{}", hex(*paper), hex(*ink))),
+        }
+    }
+}
+
+/// Rectangle in bitmap pixels, `[x0, x1) x [y0, y1)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PixelRect {
+    pub x0: u32,
+    pub y0: u32,
+    pub x1: u32,
+    pub y1: u32,
+}
+
+/// Apply `mode` in place to tightly packed RGBA pixels, leaving pixels
+/// inside any of the `skip` rectangles untouched.
+pub fn apply_color_mode(pixels: &mut [u8], width: u32, mode: &ColorMode, skip: &[PixelRect]) {
+    let map: fn(&ColorMode, u8, u8, u8) -> [u8; 3] = match mode {
+        ColorMode::Normal => return,
+        ColorMode::Inverted { .. } => |_, r, g, b| invert_lightness(r, g, b),
+        ColorMode::Sepia => |_, r, g, b| sepia(r, g, b),
+        ColorMode::Custom { .. } => |mode, r, g, b| match mode {
+            ColorMode::Custom { paper, ink } => duotone(r, g, b, *paper, *ink),
+            _ => [r, g, b],
+        },
+    };
+
+    let row_len = width as usize * 4;
+    if row_len == 0 {
+        return;
+    }
+    for (y, row) in pixels.chunks_exact_mut(row_len).enumerate() {
+        let y = y as u32;
+        let row_skips: Vec<&PixelRect> = skip.iter().filter(|r| y >= r.y0 && y < r.y1).collect();
+
+        for (x, px) in row.chunks_exact_mut(4).enumerate() {
+            let x = x as u32;
+            if row_skips.iter().any(|r| x >= r.x0 && x < r.x1) {
+                continue;
+            }
+            let [r, g, b] = map(mode, px[0], px[1], px[2]);
+            px[0] = r;
+            px[1] = g;
+            px[2] = b;
+        }
+    }
+}
+
+/// Invert HSL lightness, keeping hue and saturation.
+///
+/// In HSL every channel is `L + C * f(H)` with chroma `C = (1 - |2L - 1|) S`.
+/// `L -> 1 - L` leaves `|2L - 1|` and therefore `C` unchanged, so each channel
+/// just shifts by `(1 - L) - L`, i.e. `255 - max - min` in 8-bit terms.
+fn invert_lightness(r: u8, g: u8, b: u8) -> [u8; 3] {
+    let max = r.max(g).max(b) as i32;
+    let min = r.min(g).min(b) as i32;
+    let shift = 255 - max - min;
+    [
+        (r as i32 + shift) as u8,
+        (g as i32 + shift) as u8,
+        (b as i32 + shift) as u8,
+    ]
+}
+
+fn sepia(r: u8, g: u8, b: u8) -> [u8; 3] {
+    let (r, g, b) = (r as f32, g as f32, b as f32);
+    [
+        (0.393 * r + 0.769 * g + 0.189 * b).min(255.0) as u8,
+        (0.349 * r + 0.686 * g + 0.168 * b).min(255.0) as u8,
+        (0.272 * r + 0.534 * g + 0.131 * b).min(255.0) as u8,
+    ]
+}
+
+fn duotone(r: u8, g: u8, b: u8, paper: [u8; 3], ink: [u8; 3]) -> [u8; 3] {
+    let luma = (0.2126 * r as f32 + 0.7152 * g as f32 + 0.0722 * b as f32) / 255.0;
+    let mix = |i: usize| (ink[i] as f32 + (paper[i] as f32 - ink[i] as f32) * luma).round() as u8;
+    [mix(0), mix(1), mix(2)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invert_lightness_preserves_hue() {
+        assert_eq!(invert_lightness(255, 255, 255), [0, 0, 0]);
+        assert_eq!(invert_lightness(0, 0, 0), [255, 255, 255]);
+        // Pure red keeps its hue (and lightness 0.5 stays put).
+        assert_eq!(invert_lightness(255, 0, 0), [255, 0, 0]);
+        // Dark blue becomes light blue.
+        assert_eq!(invert_lightness(0, 0, 100), [155, 155, 255]);
+    }
+
+    #[test]
+    fn test_apply_color_mode_skips_rects() {
+        // 2x1 white pixels; the second one is inside an image rect.
+        let mut pixels = vec![255u8; 8];
+        let skip = [PixelRect { x0: 1, y0: 0, x1: 2, y1: 1 }];
+        apply_color_mode(&mut pixels, 2, &ColorMode::Inverted { preserve_images: true }, &skip);
+        assert_eq!(pixels, vec![0, 0, 0, 255, 255, 255, 255, 255]);
+
+        let mut pixels = vec![255u8, 255, 255, 255, 0, 0, 0, 255];
+        let mode = ColorMode::Custom { paper: [250, 240, 220], ink: [40, 30, 20] };
+        apply_color_mode(&mut pixels, 2, &mode, &[]);
+        assert_eq!(pixels, vec![250, 240, 220, 255, 40, 30, 20, 255]);
+    }
+}
@@ -0,0 +1,165 @@
+//! Encoding of rendered RGBA pixel buffers into transport/image formats.
+//!
+//! Render paths in `CachedPdf` produce tightly packed RGBA. This module turns
+//! those buffers into whatever a consumer asks for: the raw binary frame used
+//! by IPC and the `kiosk://` protocol, or a standard image file format
+//! (PNG, WebP, JPEG and TIFF).
+
+use super::PdfError;
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::PngEncoder;
+use image::codecs::tiff::TiffEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::{ExtendedColorType, ImageEncoder};
+use serde::{Deserialize, Serialize};
+use std::io::Cursor;
+
+/// Size in bytes of the header that prefixes a raw RGBA frame.
+///
+/// Layout (all little-endian `u32`): `[width][height][stride]`, followed by
+/// `stride * height` bytes of RGBA pixels, row-major, top-to-bottom.
+pub const RGBA_FRAME_HEADER_LEN: usize = 12;
+
+/// JPEG quality used when the caller does not specify one.
+pub const DEFAULT_JPEG_QUALITY: u8 = 85;
+
+/// Output format for an encoded page image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PageImageFormat {
+    /// Raw RGBA frame (see `RGBA_FRAME_HEADER_LEN`)
+    Rgba,
+    /// Lossless PNG
+    Png,
+    /// Lossless WebP
+    Webp,
+    /// Lossy JPEG (alpha is dropped)
+    Jpeg,
+    /// Uncompressed TIFF
+    Tiff,
+}
+
+impl PageImageFormat {
+    /// Parse a format name as used in URLs and command arguments.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "rgba" | "raw" => Some(Self::Rgba),
+            "png" => Some(Self::Png),
+            "webp" => Some(Self::Webp),
+            "jpeg" | "jpg" => Some(Self::Jpeg),
+            "tiff" | "tif" => Some(Self::Tiff),
+            _ => None,
+        }
+    }
+
+    /// MIME type to report for this format.
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            Self::Rgba => "application/octet-stream",
+            Self::Png => "image/png",
+            Self::Webp => "image/webp",
+            Self::Jpeg => "image/jpeg",
+            Self::Tiff => "image/tiff",
+        }
+    }
+
+    /// File extension for this format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Rgba => "rgba",
+            Self::Png => "png",
+            Self::Webp => "webp",
+            Self::Jpeg => "jpg",
+            Self::Tiff => "tiff",
+        }
+    }
+}
+
+/// Pack RGBA pixels behind the raw frame header.
+///
+/// Pixels from `CachedPdf` are tightly packed, so the stride is always
+/// `width * 4`; it is still sent explicitly so readers never have to
+/// assume it.
+pub fn encode_rgba_frame(pixels: &[u8], width: u32, height: u32) -> Vec<u8> {
+    let stride = width * 4;
+    let mut frame = Vec::with_capacity(RGBA_FRAME_HEADER_LEN + pixels.len());
+    frame.extend_from_slice(&width.to_le_bytes());
+    frame.extend_from_slice(&height.to_le_bytes());
+    frame.extend_from_slice(&stride.to_le_bytes());
+    frame.extend_from_slice(pixels);
+    frame
+}
+
+/// Encode tightly packed RGBA pixels into the requested format.
+pub fn encode_rgba(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    format: PageImageFormat,
+) -> Result<Vec<u8>, PdfError> {
+    encode_rgba_with_quality(pixels, width, height, format, DEFAULT_JPEG_QUALITY)
+}
+
+/// Like `encode_rgba`, with an explicit JPEG quality (1-100). Other formats
+/// are lossless and ignore `jpeg_quality`.
+pub fn encode_rgba_with_quality(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    format: PageImageFormat,
+    jpeg_quality: u8,
+) -> Result<Vec<u8>, PdfError> {
+    let mut out = Vec::new();
+
+    match format {
+        PageImageFormat::Rgba => return Ok(encode_rgba_frame(pixels, width, height)),
+        PageImageFormat::Png => PngEncoder::new(&mut out)
+            .write_image(pixels, width, height, ExtendedColorType::Rgba8)
+            .map_err(|e| PdfError::ImageError(e.to_string()))?,
+        PageImageFormat::Webp => WebPEncoder::new_lossless(&mut out)
+            .encode(pixels, width, height, ExtendedColorType::Rgba8)
+            .map_err(|e| PdfError::ImageError(e.to_string()))?,
+        PageImageFormat::Jpeg => {
+            // Rendered pages are opaque, so dropping alpha loses nothing.
+            let rgb: Vec<u8> = pixels
+                .chunks_exact(4)
+                .flat_map(|px| [px[0], px[1], px[2]])
+                .collect();
+            JpegEncoder::new_with_quality(&mut out, jpeg_quality.clamp(1, 100))
+                .encode(&rgb, width, height, ExtendedColorType::Rgb8)
+                .map_err(|e| PdfError::ImageError(e.to_string()))?
+        }
+        // The TIFF writer needs to seek back to patch offsets.
+        PageImageFormat::Tiff => TiffEncoder::new(Cursor::new(&mut out))
+            .write_image(pixels, width, height, ExtendedColorType::Rgba8)
+            .map_err(|e| PdfError::ImageError(e.to_string()))?,
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rgba_frame_header() {
+        let pixels = vec![0u8; 3 * 2 * 4];
+        let frame = encode_rgba_frame(&pixels, 3, 2);
+
+        assert_eq!(frame.len(), RGBA_FRAME_HEADER_LEN + pixels.len());
+        assert_eq!(&frame[0..4], &3u32.to_le_bytes());
+        assert_eq!(&frame[4..8], &2u32.to_le_bytes());
+        assert_eq!(&frame[8..12], &12u32.to_le_bytes());
+    }
+
+    #[test]
+    fn test_encode_png_signature() {
+        let pixels = vec![255u8; 4 * 4 * 4];
+        let png = encode_rgba(&pixels, 4, 4, PageImageFormat::Png).unwrap();
+        assert_eq!(&png[0..8], b"\x89PNG\r\n\x1a\n");
+
+        let tiff = encode_rgba(&pixels, 4, 4, PageImageFormat::Tiff).unwrap();
+        assert_eq!(&tiff[0..4], b"II*\0");
+    }
+}
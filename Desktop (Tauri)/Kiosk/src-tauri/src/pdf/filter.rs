@@ -0,0 +1,239 @@
+//! Accessibility filters applied to rendered page pixels.
+//!
+//! Unlike `ColorMode`, which restyles the page, these filters adapt it to
+//! the reader: high-contrast thresholding, grayscale, and simulation or
+//! correction of the three dichromacies. A filter is selected per document
+//! and applied as the last step of every page, tile and thumbnail render.
+//!
+//! Color vision simulation uses the Machado et al. (2009) matrices at full
+//! severity, in linear RGB. Correction is daltonization: the information
+//! lost under simulation is shifted into channels the reader can still
+//! distinguish.
+
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::OnceLock;
+
+/// Luminance threshold used when none is given.
+pub const DEFAULT_CONTRAST_THRESHOLD: u8 = 128;
+
+fn default_contrast_threshold() -> u8 {
+    DEFAULT_CONTRAST_THRESHOLD
+}
+
+/// Post-processing filter applied to every render of a document.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "filter", rename_all = "snake_case")]
+pub enum AccessibilityFilter {
+    #[default]
+    None,
+    /// Pure black and white: luminance at or above `threshold` becomes white
+    HighContrast {
+        #[serde(default = "default_contrast_threshold")]
+        threshold: u8,
+    },
+    Grayscale,
+    /// Green-blind; simulated, or corrected when `correct` is set
+    Deuteranopia {
+        #[serde(default)]
+        correct: bool,
+    },
+    /// Red-blind; simulated, or corrected when `correct` is set
+    Protanopia {
+        #[serde(default)]
+        correct: bool,
+    },
+    /// Blue-blind; simulated, or corrected when `correct` is set
+    Tritanopia {
+        #[serde(default)]
+        correct: bool,
+    },
+}
+
+impl AccessibilityFilter {
+    pub fn is_none(&self) -> bool {
+        *self == AccessibilityFilter::None
+    }
+
+    /// Render cache fingerprint; 0 for `None`.
+    pub fn fingerprint(&self) -> u64 {
+        if self.is_none() {
+            return 0;
+        }
+        let mut hasher = DefaultHasher::new();
+        self.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Stable name for on-disk cache entries; `None` for no filter.
+    pub fn cache_tag(&self) -> Option<String> {
+        let mode = |correct: bool| if correct { "fix" } else { "sim" };
+        match self {
+            AccessibilityFilter::None => None,
+            AccessibilityFilter::HighContrast { threshold } => Some(format!("contrast{}", threshold)),
+            AccessibilityFilter::Grayscale => Some("gray".to_string()),
+            AccessibilityFilter::Deuteranopia { correct } => Some(format!("deutan-{}", mode(*correct))),
+            AccessibilityFilter::Protanopia { correct } => Some(format!("protan-{}", mode(*correct))),
+            AccessibilityFilter::Tritanopia { correct } => Some(format!("tritan-{}", mode(*correct))),
+        }
+    }
+}
+
+/// Apply `filter` in place to tightly packed RGBA pixels. Alpha is kept.
+pub fn apply_accessibility_filter(pixels: &mut [u8], filter: &AccessibilityFilter) {
+    match *filter {
+        AccessibilityFilter::None => {}
+        AccessibilityFilter::HighContrast { threshold } => {
+            for px in pixels.chunks_exact_mut(4) {
+                let value = if luma(px) >= threshold { 255 } else { 0 };
+                px[..3].fill(value);
+            }
+        }
+        AccessibilityFilter::Grayscale => {
+            for px in pixels.chunks_exact_mut(4) {
+                let value = luma(px);
+                px[..3].fill(value);
+            }
+        }
+        AccessibilityFilter::Deuteranopia { correct } => {
+            apply_color_vision(pixels, &DEUTERANOPIA, &RED_GREEN_SHIFT, correct)
+        }
+        AccessibilityFilter::Protanopia { correct } => {
+            apply_color_vision(pixels, &PROTANOPIA, &RED_GREEN_SHIFT, correct)
+        }
+        AccessibilityFilter::Tritanopia { correct } => {
+            apply_color_vision(pixels, &TRITANOPIA, &BLUE_YELLOW_SHIFT, correct)
+        }
+    }
+}
+
+type Matrix = [[f32; 3]; 3];
+
+const PROTANOPIA: Matrix = [
+    [0.152_286, 1.052_583, -0.204_868],
+    [0.114_503, 0.786_281, 0.099_216],
+    [-0.003_882, -0.048_116, 1.051_998],
+];
+
+const DEUTERANOPIA: Matrix = [
+    [0.367_322, 0.860_646, -0.227_968],
+    [0.280_085, 0.672_501, 0.047_413],
+    [-0.011_820, 0.042_940, 0.968_881],
+];
+
+const TRITANOPIA: Matrix = [
+    [1.255_528, -0.076_749, -0.178_779],
+    [-0.078_411, 0.930_809, 0.147_602],
+    [0.004_733, 0.691_367, 0.303_900],
+];
+
+/// Daltonization: lost red/green contrast moves into green and blue.
+const RED_GREEN_SHIFT: Matrix = [[0.0, 0.0, 0.0], [0.7, 1.0, 0.0], [0.7, 0.0, 1.0]];
+
+/// Daltonization: lost blue/yellow contrast moves into red and green.
+const BLUE_YELLOW_SHIFT: Matrix = [[1.0, 0.0, 0.7], [0.0, 1.0, 0.7], [0.0, 0.0, 0.0]];
+
+fn apply_color_vision(pixels: &mut [u8], simulate: &Matrix, shift: &Matrix, correct: bool) {
+    let to_linear = srgb_to_linear_table();
+    for px in pixels.chunks_exact_mut(4) {
+        let rgb = [
+            to_linear[px[0] as usize],
+            to_linear[px[1] as usize],
+            to_linear[px[2] as usize],
+        ];
+        let simulated = multiply(simulate, rgb);
+        let out = if correct {
+            let lost = [rgb[0] - simulated[0], rgb[1] - simulated[1], rgb[2] - simulated[2]];
+            let compensation = multiply(shift, lost);
+            [
+                rgb[0] + compensation[0],
+                rgb[1] + compensation[1],
+                rgb[2] + compensation[2],
+            ]
+        } else {
+            simulated
+        };
+        px[0] = linear_to_srgb(out[0]);
+        px[1] = linear_to_srgb(out[1]);
+        px[2] = linear_to_srgb(out[2]);
+    }
+}
+
+fn multiply(m: &Matrix, v: [f32; 3]) -> [f32; 3] {
+    [
+        m[0][0] * v[0] + m[0][1] * v[1] + m[0][2] * v[2],
+        m[1][0] * v[0] + m[1][1] * v[1] + m[1][2] * v[2],
+        m[2][0] * v[0] + m[2][1] * v[1] + m[2][2] * v[2],
+    ]
+}
+
+/// Rec. 709 luminance of an RGBA pixel, in 0..=255.
+fn luma(px: &[u8]) -> u8 {
+    (0.2126 * px[0] as f32 + 0.7152 * px[1] as f32 + 0.0722 * px[2] as f32).round() as u8
+}
+
+fn srgb_to_linear_table() -> &'static [f32; 256] {
+    static TABLE: OnceLock<[f32; 256]> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        let mut table = [0.0; 256];
+        for (i, entry) in table.iter_mut().enumerate() {
+            let c = i as f32 / 255.0;
+            *entry = if c <= 0.040_45 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            };
+        }
+        table
+    })
+}
+
+/// Resolution of the linear-to-sRGB lookup table.
+const LINEAR_STEPS: usize = 4096;
+
+fn linear_to_srgb(value: f32) -> u8 {
+    static TABLE: OnceLock<Vec<u8>> = OnceLock::new();
+    let table = TABLE.get_or_init(|| {
+        (0..=LINEAR_STEPS)
+            .map(|i| {
+                let c = i as f32 / LINEAR_STEPS as f32;
+                let encoded = if c <= 0.003_130_8 {
+                    c * 12.92
+                } else {
+                    1.055 * c.powf(1.0 / 2.4) - 0.055
+                };
+                (encoded * 255.0).round() as u8
+            })
+            .collect()
+    });
+    table[(value.clamp(0.0, 1.0) * LINEAR_STEPS as f32).round() as usize]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accessibility_filters() {
+        let mut pixels = vec![200, 200, 200, 255, 60, 60, 60, 128];
+        apply_accessibility_filter(&mut pixels, &AccessibilityFilter::HighContrast { threshold: 128 });
+        assert_eq!(pixels, vec![255, 255, 255, 255, 0, 0, 0, 128]);
+
+        // Grays are unchanged by simulation and correction alike.
+        for filter in [
+            AccessibilityFilter::Deuteranopia { correct: false },
+            AccessibilityFilter::Protanopia { correct: true },
+            AccessibilityFilter::Tritanopia { correct: false },
+        ] {
+            let mut gray = vec![255, 255, 255, 255, 0, 0, 0, 255];
+            apply_accessibility_filter(&mut gray, &filter);
+            assert_eq!(gray, vec![255, 255, 255, 255, 0, 0, 0, 255], "{:?}", filter);
+        }
+
+        // A protanope cannot tell pure red from a dim yellow-ish tone.
+        let mut red = vec![255, 0, 0, 255];
+        apply_accessibility_filter(&mut red, &AccessibilityFilter::Protanopia { correct: false });
+        assert!(red[0] < 128 && red[1] > red[2], "{:?}", red);
+    }
+}
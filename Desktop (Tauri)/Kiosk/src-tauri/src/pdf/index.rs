@@ -0,0 +1,411 @@
+//! Full-text index of a document.
+//!
+//! Extracting a page's text is the slow part of a search, and a search
+//! repeats it for every page. A `SearchIndex` holds the text of every
+//! character of the document, exactly as pdfium reports it, plus an inverted
+//! index from each word to the pages it occurs on. As a query word may be
+//! part of a longer word, the words containing it are found in a sorted
+//! table of every suffix of every word, built on first use:
+//!
+//! - matches are found in the stored text, under the same `PageText` rules
+//!   as a live search, so results are identical;
+//! - in `text` and `query` mode, pages lacking a word of the query are
+//!   skipped without looking at their text;
+//! - only pages with matches are opened in pdfium, for highlight rects.
+//!
+//! Words are taken from the page text as `Folding::loosest` reads it, so
+//! one index serves every case and normalization option: joining lines
+//! only merges words, and a word of the text under any other options is
+//! part of one of them. The index serializes to a compact binary
+//! form (`to_bytes`) for the on-disk cache in `search_index`.
+
+use super::renderer::{PdfError, SearchResult};
+use super::search::{
+    is_ranked, is_word_char, page_results, parse_terms, rank_results, CompiledQuery, Folding, PageText, SearchMode,
+    SearchOptions, TopMatches,
+};
+use pdfium_render::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use std::sync::OnceLock;
+
+/// Leading bytes of a serialized index.
+const MAGIC: &[u8; 4] = b"KSIX";
+
+/// Serialized format version; bump when the layout or what is stored
+/// changes, so old files are rebuilt.
+const FORMAT_VERSION: u32 = 2;
+
+/// Text of one page's characters.
+struct IndexedPage {
+    /// Text of every character, concatenated
+    text: String,
+    /// End offset in `text` of each character
+    ends: Vec<u32>,
+}
+
+impl IndexedPage {
+    /// Each character's text, as `PageText::new` takes it.
+    fn chars(&self) -> impl Iterator<Item = Option<&str>> {
+        let starts = std::iter::once(0).chain(self.ends.iter().copied());
+        starts
+            .zip(self.ends.iter().copied())
+            .map(|(start, end)| Some(&self.text[start as usize..end as usize]))
+    }
+}
+
+/// The words of text folded with `Folding::loosest`.
+fn words(folded: &str) -> impl Iterator<Item = &str> {
+    folded.split(|c: char| !is_word_char(c)).filter(|w| !w.is_empty())
+}
+
+/// Every suffix of every indexed word, sorted, so that the words containing
+/// a string are one binary search away rather than a scan of the vocabulary.
+struct SuffixTable {
+    words: Vec<String>,
+    /// Word and byte offset of each suffix, ordered by suffix text
+    suffixes: Vec<(u32, u32)>,
+}
+
+impl SuffixTable {
+    fn new<'a>(words: impl Iterator<Item = &'a String>) -> Self {
+        let words: Vec<String> = words.cloned().collect();
+        let mut suffixes: Vec<(u32, u32)> = words
+            .iter()
+            .enumerate()
+            .flat_map(|(w, word)| word.char_indices().map(move |(at, _)| (w as u32, at as u32)))
+            .collect();
+        let suffix = |&(w, at): &(u32, u32)| &words[w as usize][at as usize..];
+        suffixes.sort_unstable_by(|a, b| suffix(a).cmp(suffix(b)));
+        SuffixTable { words, suffixes }
+    }
+
+    fn suffix(&self, (w, at): (u32, u32)) -> &str {
+        &self.words[w as usize][at as usize..]
+    }
+
+    /// The words containing `part`, once per occurrence.
+    fn containing<'a>(&'a self, part: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        let start = self.suffixes.partition_point(|&s| self.suffix(s) < part);
+        self.suffixes[start..]
+            .iter()
+            .take_while(move |&&s| self.suffix(s).starts_with(part))
+            .map(move |&(w, _)| self.words[w as usize].as_str())
+    }
+}
+
+/// Text and word index of a whole document.
+#[derive(Default)]
+pub struct SearchIndex {
+    pages: Vec<IndexedPage>,
+    /// Pages each folded word occurs on, ascending
+    words: HashMap<String, Vec<u32>>,
+    /// Suffixes of `words`, built by the first search
+    suffixes: OnceLock<SuffixTable>,
+}
+
+impl SearchIndex {
+    /// Append the next page, given the text of each of its characters.
+    pub fn add_page<S: AsRef<str>>(&mut self, chars: impl IntoIterator<Item = Option<S>>) {
+        let page_index = self.pages.len() as u32;
+        let mut page = IndexedPage { text: String::new(), ends: Vec::new() };
+        for c in chars {
+            if let Some(c) = c {
+                page.text.push_str(c.as_ref());
+            }
+            page.ends.push(page.text.len() as u32);
+        }
+        let text = PageText::new(page.chars(), Folding::loosest());
+        for word in words(text.folded()) {
+            let pages = self.words.entry(word.to_string()).or_default();
+            if pages.last() != Some(&page_index) {
+                pages.push(page_index);
+            }
+        }
+        self.suffixes = OnceLock::new();
+        self.pages.push(page);
+    }
+
+    pub fn page_count(&self) -> u32 {
+        self.pages.len() as u32
+    }
+
+    /// Pages on which every word of `term` occurs inside some word, or
+    /// `None` when `term` has no words.
+    fn term_pages(&self, term: &str) -> Option<HashSet<u32>> {
+        let suffixes = self.suffixes.get_or_init(|| SuffixTable::new(self.words.keys()));
+        let mut found: Option<HashSet<u32>> = None;
+        for word in words(&Folding::loosest().fold(term)) {
+            let pages: HashSet<u32> = suffixes
+                .containing(word)
+                .flat_map(|indexed| self.words[indexed].iter().copied())
+                .collect();
+            found = Some(match found {
+                Some(found) => found.intersection(&pages).copied().collect(),
+                None => pages,
+            });
+        }
+        found
+    }
+
+    /// Pages that can contain a match of `query`; `None` when any page can.
+    fn candidate_pages(&self, query: &str, options: &SearchOptions) -> Result<Option<HashSet<u32>>, PdfError> {
+        Ok(match options.mode {
+            SearchMode::Text => self.term_pages(query),
+            SearchMode::Regex | SearchMode::Fuzzy => None,
+            SearchMode::Query => {
+                let mut candidates = HashSet::new();
+                for terms in parse_terms(query)? {
+                    let mut pages: Option<HashSet<u32>> = None;
+                    for term in &terms {
+                        match (self.term_pages(term), pages) {
+                            (None, found) => pages = found,
+                            (Some(term_pages), None) => pages = Some(term_pages),
+                            (Some(term_pages), Some(found)) => {
+                                pages = Some(found.intersection(&term_pages).copied().collect())
+                            }
+                        }
+                    }
+                    match pages {
+                        Some(pages) => candidates.extend(pages),
+                        None => return Ok(None),
+                    }
+                }
+                Some(candidates)
+            }
+        })
+    }
+
+    /// Serialize for `from_bytes`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        write_varint(&mut out, self.pages.len() as u64);
+        for page in &self.pages {
+            write_varint(&mut out, page.ends.len() as u64);
+            let mut start = 0;
+            for &end in &page.ends {
+                write_varint(&mut out, (end - start) as u64);
+                start = end;
+            }
+            out.extend_from_slice(page.text.as_bytes());
+        }
+        write_varint(&mut out, self.words.len() as u64);
+        for (word, pages) in &self.words {
+            write_varint(&mut out, word.len() as u64);
+            out.extend_from_slice(word.as_bytes());
+            write_varint(&mut out, pages.len() as u64);
+            let mut previous = 0;
+            for &page in pages {
+                write_varint(&mut out, (page - previous) as u64);
+                previous = page;
+            }
+        }
+        out
+    }
+
+    /// Read an index written by `to_bytes`. Returns `None` for data from
+    /// another format version or that is corrupt.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let mut reader = Reader { bytes, pos: 0 };
+        if reader.take(4)? != MAGIC || u32::from_le_bytes(reader.take(4)?.try_into().ok()?) != FORMAT_VERSION {
+            return None;
+        }
+        let mut index = SearchIndex::default();
+        for _ in 0..reader.varint()? {
+            let count = reader.varint()? as usize;
+            let mut ends = Vec::with_capacity(count.min(bytes.len()));
+            let mut end = 0u32;
+            for _ in 0..count {
+                end = end.checked_add(u32::try_from(reader.varint()?).ok()?)?;
+                ends.push(end);
+            }
+            let text = std::str::from_utf8(reader.take(end as usize)?).ok()?.to_string();
+            if ends.iter().any(|&e| !text.is_char_boundary(e as usize)) {
+                return None;
+            }
+            index.pages.push(IndexedPage { text, ends });
+        }
+        for _ in 0..reader.varint()? {
+            let len = reader.varint()? as usize;
+            let word = std::str::from_utf8(reader.take(len)?).ok()?.to_string();
+            let count = reader.varint()? as usize;
+            let mut pages = Vec::with_capacity(count.min(bytes.len()));
+            let mut page = 0u32;
+            for _ in 0..count {
+                page = page.checked_add(u32::try_from(reader.varint()?).ok()?)?;
+                pages.push(page);
+            }
+            index.words.insert(word, pages);
+        }
+        (reader.pos == bytes.len()).then_some(index)
+    }
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// Cursor over a serialized index.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let slice = self.bytes.get(self.pos..self.pos.checked_add(len)?)?;
+        self.pos += len;
+        Some(slice)
+    }
+
+    fn varint(&mut self) -> Option<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = *self.take(1)?.first()?;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+        None
+    }
+}
+
+/// The text of each character of a page, for `SearchIndex::add_page`.
+pub(super) fn page_chars(page: &PdfPage) -> Result<Vec<Option<String>>, PdfError> {
+    let text_page = page
+        .text()
+        .map_err(|e| PdfError::RenderError(e.to_string()))?;
+    Ok(text_page.chars().iter().map(|c| c.unicode_string()).collect())
+}
+
+/// `search::search_document` answered from `index`: the same results, with
+/// only pages that have matches opened in pdfium.
+pub(super) fn search_indexed(
+    document: &PdfDocument,
+    index: &SearchIndex,
+    query: &str,
+    options: &SearchOptions,
+    max_results: usize,
+) -> Result<Vec<SearchResult>, PdfError> {
+    let candidates = index.candidate_pages(query, options)?;
+    let query = CompiledQuery::new(query, options)?;
+    let page_text = |page_index: u32| PageText::new(index.pages[page_index as usize].chars(), query.folding);
+
+    // Ranked: only the pages holding the best matches are opened.
+    if is_ranked(options) {
+        let mut top = TopMatches::new(max_results);
+        for page_index in options.page_indices(index.page_count()) {
+            top.add(page_index, query.matches(&page_text(page_index)));
+        }
+        let mut results = Vec::new();
+        for (page_index, matches) in top.into_pages() {
+            results.extend(indexed_page_results(document, page_index, &page_text(page_index), matches));
+        }
+        return Ok(rank_results(results, options, max_results));
+    }
+
+    let mut results = Vec::new();
+    for page_index in options.page_indices(index.page_count()) {
+        if results.len() >= max_results {
+            break;
+        }
+        if candidates.as_ref().is_some_and(|pages| !pages.contains(&page_index)) {
+            continue;
+        }
+        let text = page_text(page_index);
+        let mut matches = query.matches(&text);
+        if matches.is_empty() {
+            continue;
+        }
+        matches.truncate(max_results - results.len());
+        results.extend(indexed_page_results(document, page_index, &text, matches));
+    }
+    Ok(results)
+}
+
+/// Results for `matches` in the indexed `text` of a page, with rects from
+/// pdfium; none when the page can't be read.
+fn indexed_page_results(
+    document: &PdfDocument,
+    page_index: u32,
+    text: &PageText,
+    matches: Vec<(Range<usize>, f32)>,
+) -> Vec<SearchResult> {
+    let Ok(page) = document.pages().get(page_index as u16) else {
+        return Vec::new();
+    };
+    let Ok(text_page) = page.text() else {
+        return Vec::new();
+    };
+    page_results(&page, page_index, &text_page.chars(), text, matches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdf::SearchNormalization;
+
+    fn chars(text: &str) -> Vec<Option<String>> {
+        text.chars().map(|c| Some(c.to_string())).collect()
+    }
+
+    #[test]
+    fn test_index_roundtrip_and_candidates() {
+        let mut index = SearchIndex::default();
+        index.add_page(chars("Force Majeure clause"));
+        index.add_page(vec![Some("\u{FB01}"), None, Some("le"), Some(" "), Some("RÉSUMÉ")]);
+        index.add_page(chars("Indemnification, force inter-\r\nnational"));
+
+        let index = SearchIndex::from_bytes(&index.to_bytes()).unwrap();
+        assert_eq!(index.page_count(), 3);
+        let page: Vec<_> = index.pages[1].chars().collect();
+        assert_eq!(page, vec![Some("\u{FB01}"), Some(""), Some("le"), Some(" "), Some("RÉSUMÉ")]);
+
+        let text = SearchOptions::default();
+        let pages = |query: &str, options: &SearchOptions| {
+            let mut pages: Vec<u32> = index.candidate_pages(query, options).unwrap()?.into_iter().collect();
+            pages.sort_unstable();
+            Some(pages)
+        };
+        assert_eq!(pages("force", &text), Some(vec![0, 2]));
+        assert_eq!(pages("indemnif", &text), Some(vec![2]));
+        assert_eq!(pages("rce maj", &text), Some(vec![0]));
+        assert_eq!(pages("ation", &text), Some(vec![2]));
+        assert_eq!(pages("forced", &text), Some(Vec::new()));
+        assert_eq!(pages("file resume", &text), Some(vec![1]));
+        assert_eq!(pages("...", &text), None);
+        assert_eq!(pages("international", &text), Some(vec![2]));
+        assert_eq!(pages("inter-", &text), Some(vec![2]));
+
+        let query = SearchOptions { mode: SearchMode::Query, ..SearchOptions::default() };
+        assert_eq!(pages("clause OR indemnification", &query), Some(vec![0, 2]));
+        assert_eq!(pages("force clause", &query), Some(vec![0]));
+        let regex = SearchOptions { mode: SearchMode::Regex, ..SearchOptions::default() };
+        assert_eq!(pages("f.rce", &regex), None);
+        let fuzzy = SearchOptions { mode: SearchMode::Fuzzy, ..SearchOptions::default() };
+        assert_eq!(pages("frce", &fuzzy), None);
+
+        let all = SearchNormalization {
+            compatibility: true,
+            ignore_diacritics: true,
+            expand_ligatures: true,
+            join_lines: false,
+        };
+        let options = SearchOptions { normalization: all, ..SearchOptions::default() };
+        let query = CompiledQuery::new("file résumé", &options).unwrap();
+        let text = PageText::new(index.pages[1].chars(), query.folding);
+        assert_eq!(query.matches(&text), vec![(0..5, 1.0)]);
+
+        let mut corrupt = index.to_bytes();
+        corrupt.pop();
+        assert!(SearchIndex::from_bytes(&corrupt).is_none());
+        assert!(SearchIndex::from_bytes(b"KSIX\x01\0\0\0\0\0").is_none());
+    }
+}
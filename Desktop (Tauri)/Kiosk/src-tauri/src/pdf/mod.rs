@@ -5,7 +5,31 @@
 //! - Glyph-accurate character bounding boxes for text selection
 //! - Page metadata and navigation
 //! - Persistent document handle caching (Phase 3)
+//! - Encoding of rendered pixels to raw frames, PNG and WebP
+//! - Color modes (inverted, sepia, custom paper/ink) applied to renders
+//! - Accessibility filters (high contrast, grayscale, color vision)
+//! - View-only page rotation, reflected in render and text geometry
+//! - Separate content and annotation layers for viewer-side compositing
+//! - Progressive rendering of heavy pages in time slices
+//! - A per-render pixel limit, with full pages downscaled to fit
+//! - Unicode-aware text, regex and boolean query search reported in
+//!   character indices, optionally insensitive to accents, ligatures and
+//!   character width, and restricted to whole words
+//! - A full-text index answering searches without re-extracting page text
 
+mod color;
+mod encode;
+mod filter;
+mod index;
+mod progressive;
 mod renderer;
+mod rotation;
+mod search;
 
+pub use color::*;
+pub use encode::*;
+pub use filter::*;
+pub use index::SearchIndex;
 pub use renderer::*;
+pub use rotation::*;
+pub use search::{CompiledQuery, SearchMode, SearchNormalization, SearchOptions};
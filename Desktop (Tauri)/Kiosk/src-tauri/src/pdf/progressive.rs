@@ -0,0 +1,224 @@
+//! Progressive rendering of heavy pages.
+//!
+//! A page with a huge vector map can spend seconds in pdfium, and a plain
+//! render shows nothing until it finishes. pdfium's progressive API
+//! (`FPDF_RenderPageBitmap_Start` / `FPDF_RenderPage_Continue`) instead
+//! draws in slices, and the partly drawn bitmap can be shown in between.
+//!
+//! Each job draws a scratch copy of the page (`CachedPdf::scratch_page`)
+//! into a bitmap of its own, so the document stays usable between slices:
+//! the caller advances a job a time budget at a time under the document
+//! lock, can simply stop calling to pause, and ends the job to cancel.
+//!
+//! The scratch copy has no form environment, so intermediate frames never
+//! contain form fields; `finish_progressive_render` falls back to a regular
+//! render for the final frame when form fields should be drawn.
+
+use super::renderer::{recolor, CachedPdf, PdfError, RenderOptions, ScratchPage};
+use pdfium_render::prelude::*;
+use std::collections::HashMap;
+use std::os::raw::{c_int, c_void};
+use std::time::{Duration, Instant};
+
+// Render flags (fpdfview.h) and progress status codes (fpdf_progressive.h).
+const FPDF_ANNOT: c_int = 0x01;
+const FPDF_GRAYSCALE: c_int = 0x08;
+const FPDF_RENDER_NO_SMOOTHTEXT: c_int = 0x1000;
+const FPDF_RENDER_NO_SMOOTHIMAGE: c_int = 0x2000;
+const FPDF_RENDER_NO_SMOOTHPATH: c_int = 0x4000;
+const FPDF_RENDER_TOBECONTINUED: c_int = 1;
+const FPDF_RENDER_DONE: c_int = 2;
+
+/// A progressive render in flight.
+struct ProgressiveJob {
+    // Dropped in declaration order: the bitmap and page go before the
+    // scratch document.
+    bitmap: PdfBitmap<'static>,
+    scratch: ScratchPage<'static>,
+    page_index: u32,
+    scale: f32,
+    options: RenderOptions,
+    started: bool,
+    done: bool,
+}
+
+impl Drop for ProgressiveJob {
+    fn drop(&mut self) {
+        if self.started {
+            let bindings = self.scratch.page.bindings();
+            bindings.FPDF_RenderPage_Close(bindings.get_handle_from_page(&self.scratch.page));
+        }
+    }
+}
+
+/// The progressive renders of one document, by job id.
+#[derive(Default)]
+pub struct ProgressiveJobs {
+    jobs: HashMap<u64, ProgressiveJob>,
+    next_id: u64,
+}
+
+/// `IFSDK_PAUSE::NeedToPauseNow`: pause once the slice's deadline passed.
+unsafe extern "C" fn past_deadline(pause: *mut IFSDK_PAUSE) -> FPDF_BOOL {
+    // SAFETY: `user` points at the deadline owned by
+    // `continue_progressive_render`, which outlives the pdfium call that
+    // receives `pause`.
+    let deadline = unsafe { &*((*pause).user as *const Instant) };
+    (Instant::now() >= *deadline) as FPDF_BOOL
+}
+
+/// pdfium render flags for `options`. Form data is never drawn.
+fn render_flags(options: &RenderOptions) -> c_int {
+    let mut flags = 0;
+    if options.draws_annotations() {
+        flags |= FPDF_ANNOT;
+    }
+    if options.grayscale {
+        flags |= FPDF_GRAYSCALE;
+    }
+    if !options.antialias_text {
+        flags |= FPDF_RENDER_NO_SMOOTHTEXT;
+    }
+    if !options.antialias_images {
+        flags |= FPDF_RENDER_NO_SMOOTHIMAGE;
+    }
+    if !options.antialias_paths {
+        flags |= FPDF_RENDER_NO_SMOOTHPATH;
+    }
+    flags
+}
+
+impl CachedPdf {
+    /// Start a progressive render of a page at `scale`, returning a job id
+    /// for `continue_progressive_render`. The result has the size and look
+    /// of `render_page_to_rgba` with the same arguments, downscale included.
+    pub fn begin_progressive_render(
+        &mut self,
+        page_index: u32,
+        scale: f32,
+        options: &RenderOptions,
+    ) -> Result<u64, PdfError> {
+        if !scale.is_finite() || scale <= 0.0 {
+            return Err(PdfError::RenderError(format!("Invalid render scale: {}", scale)));
+        }
+        let scale = self.effective_render_scale(page_index, scale)?;
+        let scratch = self.scratch_page(page_index, options.layer)?;
+        let width = ((scratch.page.width().value * scale) as i32).max(1);
+        let height = ((scratch.page.height().value * scale) as i32).max(1);
+        let bitmap = PdfBitmap::empty(width, height, PdfBitmapFormat::BGRA, scratch.page.bindings())
+            .map_err(|e| PdfError::RenderError(e.to_string()))?;
+
+        // SAFETY: Same reasoning as for `CachedPdf::document`. The scratch
+        // document and bitmap borrow only the bindings inside `_pdfium`,
+        // which are heap-allocated and never modified, and `progressive` is
+        // declared before `_pdfium`, so every job is dropped first.
+        let bitmap = unsafe { std::mem::transmute::<PdfBitmap<'_>, PdfBitmap<'static>>(bitmap) };
+        let scratch = unsafe { std::mem::transmute::<ScratchPage<'_>, ScratchPage<'static>>(scratch) };
+
+        let jobs = &mut self.progressive;
+        jobs.next_id += 1;
+        let job = ProgressiveJob {
+            bitmap,
+            scratch,
+            page_index,
+            scale,
+            options: *options,
+            started: false,
+            done: false,
+        };
+        jobs.jobs.insert(jobs.next_id, job);
+        Ok(jobs.next_id)
+    }
+
+    /// Draw more of job `job_id` for about `budget`. Returns `true` once
+    /// the page is complete.
+    pub fn continue_progressive_render(&mut self, job_id: u64, budget: Duration) -> Result<bool, PdfError> {
+        let job = self
+            .progressive
+            .jobs
+            .get_mut(&job_id)
+            .ok_or_else(|| PdfError::RenderError(format!("No progressive render {}", job_id)))?;
+        if job.done {
+            return Ok(true);
+        }
+
+        let bindings = job.scratch.page.bindings();
+        let page = bindings.get_handle_from_page(&job.scratch.page);
+        let mut deadline = Instant::now() + budget;
+        let mut pause = IFSDK_PAUSE {
+            version: 1,
+            NeedToPauseNow: Some(past_deadline),
+            user: &mut deadline as *mut Instant as *mut c_void,
+        };
+
+        let status = if job.started {
+            bindings.FPDF_RenderPage_Continue(page, &mut pause)
+        } else {
+            job.started = true;
+            let bitmap = bindings.get_handle_from_bitmap(&job.bitmap);
+            let (width, height) = (job.bitmap.width() as c_int, job.bitmap.height() as c_int);
+            let [r, g, b, a] = job.options.clear_color();
+            let argb = u32::from_be_bytes([a, r, g, b]);
+            let _ = bindings.FPDFBitmap_FillRect(bitmap, 0, 0, width, height, argb as FPDF_DWORD);
+            bindings.FPDF_RenderPageBitmap_Start(
+                bitmap,
+                page,
+                0,
+                0,
+                width,
+                height,
+                0,
+                render_flags(&job.options),
+                &mut pause,
+            )
+        };
+
+        match status {
+            FPDF_RENDER_DONE => {
+                job.done = true;
+                Ok(true)
+            }
+            FPDF_RENDER_TOBECONTINUED => Ok(false),
+            _ => Err(PdfError::RenderError(format!(
+                "Progressive render {} failed",
+                job_id
+            ))),
+        }
+    }
+
+    /// The bitmap of job `job_id` as drawn so far: tightly packed RGBA with
+    /// the options' color mode and rotation applied.
+    pub fn progressive_frame(&self, job_id: u64) -> Result<(Vec<u8>, u32, u32), PdfError> {
+        let job = self
+            .progressive
+            .jobs
+            .get(&job_id)
+            .ok_or_else(|| PdfError::RenderError(format!("No progressive render {}", job_id)))?;
+        let rgba = job.bitmap.as_image().to_rgba8();
+        let (w, h) = rgba.dimensions();
+        let mut pixels = rgba.into_raw();
+        recolor(&job.scratch.page, &mut pixels, w, job.scale, (0, 0), &job.options.color);
+        Ok(job.options.rotation.rotate_pixels(pixels, w, h))
+    }
+
+    /// End the completed job `job_id` and return its final frame, the same
+    /// pixels `render_page_to_rgba` produces.
+    pub fn finish_progressive_render(&mut self, job_id: u64) -> Result<(Vec<u8>, u32, u32), PdfError> {
+        let (page_index, scale, options) = match self.progressive.jobs.get(&job_id) {
+            Some(job) if job.done => (job.page_index, job.scale, job.options),
+            _ => return Err(PdfError::RenderError(format!("Progressive render {} is not complete", job_id))),
+        };
+        let frame = if options.draws_forms() && self.page_has_form_fields(page_index) {
+            self.render_page_to_rgba(page_index, scale, &options)
+        } else {
+            self.progressive_frame(job_id)
+        };
+        self.end_progressive_render(job_id);
+        frame
+    }
+
+    /// Cancel job `job_id`, releasing its page copy and bitmap.
+    pub fn end_progressive_render(&mut self, job_id: u64) {
+        self.progressive.jobs.remove(&job_id);
+    }
+}
@@ -3,10 +3,18 @@
 //! Note: pdfium-render's Pdfium struct is not Send+Sync, so we create instances
 //! on-demand within each operation rather than storing in shared state.
 
+use super::color::{apply_color_mode, ColorMode, PixelRect};
+use super::progressive::ProgressiveJobs;
+use super::rotation::ViewRotation;
+use super::index::{page_chars, search_indexed, SearchIndex};
+use super::search::{search_document, search_page, CompiledQuery, SearchOptions};
 use pdfium_render::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
 use std::io::Cursor;
 use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
 use thiserror::Error;
 
 /// Errors that can occur during PDF operations.
@@ -36,6 +44,12 @@ pub enum PdfError {
 
     #[error("Incorrect password")]
     InvalidPassword,
+
+    #[error("Render of {width}x{height} pixels exceeds the limit of {max_pixels} pixels")]
+    RenderTooLarge { width: u64, height: u64, max_pixels: u64 },
+
+    #[error("Invalid search query: {0}")]
+    InvalidQuery(String),
 }
 
 impl Serialize for PdfError {
@@ -47,6 +61,57 @@ impl Serialize for PdfError {
     }
 }
 
+/// Default for `set_max_render_pixels`: 64 MP, 256 MiB of RGBA.
+pub const DEFAULT_MAX_RENDER_PIXELS: u64 = 64 * 1024 * 1024;
+
+static MAX_RENDER_PIXELS: AtomicU64 = AtomicU64::new(DEFAULT_MAX_RENDER_PIXELS);
+
+/// Set the largest bitmap, in pixels, any single render may allocate.
+///
+/// Full-page renders that would exceed it are rendered at a smaller scale
+/// instead (see `CachedPdf::effective_render_scale`); tiles are refused
+/// with `PdfError::RenderTooLarge`.
+pub fn set_max_render_pixels(max_pixels: u64) {
+    MAX_RENDER_PIXELS.store(max_pixels.max(1), Ordering::Relaxed);
+}
+
+pub fn max_render_pixels() -> u64 {
+    MAX_RENDER_PIXELS.load(Ordering::Relaxed)
+}
+
+/// Bitmap size of a `width` x `height` point page rendered at `scale`.
+/// Each side is at least one pixel, however thin the page.
+fn render_size(width: f32, height: f32, scale: f32) -> (u64, u64) {
+    (((width * scale) as u64).max(1), ((height * scale) as u64).max(1))
+}
+
+/// Pixel count of a `w` x `h` bitmap, saturating for huge scales.
+fn pixel_count((w, h): (u64, u64)) -> u64 {
+    w.saturating_mul(h)
+}
+
+/// The largest scale up to `scale` at which a `width` x `height` point page
+/// fits in `max_pixels`.
+fn fit_scale(width: f32, height: f32, scale: f32, max_pixels: u64) -> f32 {
+    if pixel_count(render_size(width, height, scale)) <= max_pixels {
+        return scale;
+    }
+    // Estimated from the page area rather than the (possibly saturated)
+    // pixel count at `scale`.
+    let area_scale = (max_pixels as f64 / (width as f64 * height as f64)).sqrt() as f32;
+    let mut fitted = scale.min(area_scale);
+    // A very elongated page is then less than a pixel thin; kept at one
+    // pixel, its long side alone must fit.
+    if width.min(height) * fitted < 1.0 {
+        fitted = fitted.min((max_pixels as f64 / width.max(height) as f64) as f32);
+    }
+    // Truncation to whole pixels makes the estimate close but not exact.
+    while fitted > 0.0 && pixel_count(render_size(width, height, fitted)) > max_pixels {
+        fitted *= 0.999;
+    }
+    fitted
+}
+
 /// Character bounding box with precise positioning for text selection.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CharRect {
@@ -75,6 +140,10 @@ pub struct PageInfo {
     pub height: f32,
     /// Page rotation in degrees (0, 90, 180, 270)
     pub rotation: i32,
+    /// View-only rotation in degrees (0, 90, 180, 270); when non-zero,
+    /// `width` and `height` describe the rotated view
+    #[serde(default)]
+    pub view_rotation: i32,
 }
 
 /// Document metadata.
@@ -95,12 +164,19 @@ pub struct DocumentInfo {
 pub struct SearchResult {
     /// Page index where match was found
     pub page: u32,
-    /// Start character index
+    /// Index of the first matched character, as in `CharRect::index`
     pub start_index: usize,
-    /// End character index
+    /// Index one past the last matched character
     pub end_index: usize,
     /// The matched text
     pub text: String,
+    /// Text just before and after the match, with line breaks and runs of
+    /// whitespace collapsed to single spaces, for a result list
+    pub context_before: String,
+    pub context_after: String,
+    /// How closely the match resembles the query: 1 for an exact match, less
+    /// for a `fuzzy` one
+    pub score: f32,
     /// Bounding rectangles for the match (may span multiple lines)
     pub rects: Vec<TextRect>,
 }
@@ -114,6 +190,272 @@ pub struct TextRect {
     pub height: f32,
 }
 
+/// A rendered sub-region (tile) of a page.
+#[derive(Debug, Clone)]
+pub struct RenderedRegion {
+    /// Tightly packed RGBA pixels (4 bytes per pixel, row-major, top-to-bottom)
+    pub pixels: Vec<u8>,
+    /// Pixel width of the tile
+    pub width: u32,
+    /// Pixel height of the tile
+    pub height: u32,
+    /// Left edge of the tile in PDF points (top-left origin)
+    pub x: f32,
+    /// Top edge of the tile in PDF points (top-left origin)
+    pub y: f32,
+}
+
+/// Which part of a page a render contains.
+///
+/// `Content` and `Annotations` split a `Full` render into two layers the
+/// viewer can composite itself, so annotations can be hidden, faded or
+/// re-fetched on their own. Drawing the annotation layer over the content
+/// layer gives the full render.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RenderLayer {
+    /// Page content with annotations, as selected by `RenderOptions`
+    #[default]
+    Full,
+    /// Page content and form fields, never annotations
+    Content,
+    /// Annotations alone on a transparent background; form field widgets
+    /// belong to the content layer
+    Annotations,
+}
+
+/// How a page is rasterized.
+///
+/// Every field has a default, so callers only send what they change, e.g.
+/// `{ "annotations": false }` for annotation editing or
+/// `{ "transparent": true, "grayscale": true }` for export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RenderOptions {
+    /// Draw annotations (highlights, notes, stamps)
+    pub annotations: bool,
+    /// Draw form field widgets and their values (full-page renders only)
+    pub forms: bool,
+    /// Anti-alias text
+    pub antialias_text: bool,
+    /// Anti-alias vector paths
+    pub antialias_paths: bool,
+    /// Smooth scaled raster images
+    pub antialias_images: bool,
+    /// Paper color (RGB) behind the page content
+    pub background: [u8; 3],
+    /// Leave the background transparent instead of painting `background`
+    pub transparent: bool,
+    /// Rasterize in grayscale
+    pub grayscale: bool,
+    /// Color mapping applied after rasterization
+    pub color: ColorMode,
+    /// Clockwise rotation of the output, without modifying the document
+    pub rotation: ViewRotation,
+    /// Part of the page to draw; overrides `annotations`, and the
+    /// annotation layer ignores `forms`, `background` and `transparent`
+    pub layer: RenderLayer,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            annotations: true,
+            forms: true,
+            antialias_text: true,
+            antialias_paths: true,
+            antialias_images: true,
+            background: [255, 255, 255],
+            transparent: false,
+            grayscale: false,
+            color: ColorMode::Normal,
+            rotation: ViewRotation::None,
+            layer: RenderLayer::Full,
+        }
+    }
+}
+
+impl RenderOptions {
+    pub fn is_default(&self) -> bool {
+        *self == RenderOptions::default()
+    }
+
+    /// Fingerprint for in-memory cache keys; 0 for the defaults.
+    ///
+    /// `DefaultHasher` output may change between Rust releases, so on-disk
+    /// caches name entries with `cache_tag` instead.
+    pub fn fingerprint(&self) -> u64 {
+        if self.is_default() {
+            return 0;
+        }
+        let mut hasher = DefaultHasher::new();
+        self.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Stable name for on-disk cache entries, listing the options that
+    /// differ from the defaults; `None` for the defaults.
+    pub fn cache_tag(&self) -> Option<String> {
+        let defaults = RenderOptions::default();
+        let mut parts = Vec::new();
+        if !self.annotations {
+            parts.push("noannots".to_string());
+        }
+        if !self.forms {
+            parts.push("noforms".to_string());
+        }
+        let antialias = [self.antialias_text, self.antialias_paths, self.antialias_images];
+        if antialias != [true; 3] {
+            let bits: String = antialias.iter().map(|&on| if on { '1' } else { '0' }).collect();
+            parts.push(format!("aa{}", bits));
+        }
+        if self.background != defaults.background {
+            let [r, g, b] = self.background;
+            parts.push(format!("bg{:02x}{:02x}{:02x}", r, g, b));
+        }
+        if self.transparent {
+            parts.push("transparent".to_string());
+        }
+        if self.grayscale {
+            parts.push("grayscale".to_string());
+        }
+        parts.extend(self.color.cache_tag());
+        if self.rotation != defaults.rotation {
+            parts.push(format!("rot{}", self.rotation.degrees()));
+        }
+        match self.layer {
+            RenderLayer::Full => {}
+            RenderLayer::Content => parts.push("content".to_string()),
+            RenderLayer::Annotations => parts.push("annotlayer".to_string()),
+        }
+        (!parts.is_empty()).then(|| parts.join("-"))
+    }
+
+    /// Whether annotations are drawn, after `layer`.
+    pub(super) fn draws_annotations(&self) -> bool {
+        match self.layer {
+            RenderLayer::Full => self.annotations,
+            RenderLayer::Content => false,
+            RenderLayer::Annotations => true,
+        }
+    }
+
+    /// Whether form fields are drawn, after `layer`.
+    pub(super) fn draws_forms(&self) -> bool {
+        self.forms && self.layer != RenderLayer::Annotations
+    }
+
+    /// RGBA color the bitmap is cleared to before drawing.
+    pub(super) fn clear_color(&self) -> [u8; 4] {
+        let [r, g, b] = self.background;
+        match self.layer {
+            RenderLayer::Annotations => [0, 0, 0, 0],
+            _ if self.transparent => [r, g, b, 0],
+            _ => [r, g, b, 255],
+        }
+    }
+
+    /// pdfium configuration for these options, before sizing.
+    fn pdf_render_config(&self) -> PdfRenderConfig {
+        let [r, g, b, a] = self.clear_color();
+        PdfRenderConfig::new()
+            .render_annotations(self.draws_annotations())
+            .render_form_data(self.draws_forms())
+            .set_text_smoothing(self.antialias_text)
+            .set_path_smoothing(self.antialias_paths)
+            .set_image_smoothing(self.antialias_images)
+            .set_clear_color(PdfColor::new(r, g, b, a))
+            .use_grayscale_rendering(self.grayscale)
+    }
+}
+
+/// A small page image for the thumbnail strip.
+#[derive(Debug, Clone)]
+pub struct PageThumbnail {
+    /// Tightly packed RGBA pixels (4 bytes per pixel, row-major, top-to-bottom)
+    pub pixels: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+    /// Taken from the page's embedded `/Thumb` image rather than rendered
+    pub embedded: bool,
+}
+
+/// Apply `color` to a bitmap rendered from `page` at `scale`, whose top-left
+/// pixel sits at `origin` in the full-page bitmap (non-zero for tiles).
+pub(super) fn recolor(
+    page: &PdfPage,
+    pixels: &mut [u8],
+    width: u32,
+    scale: f32,
+    origin: (i32, i32),
+    color: &ColorMode,
+) {
+    if color.is_normal() {
+        return;
+    }
+    let skip = if color.preserves_images() {
+        image_pixel_rects(page, scale, origin)
+    } else {
+        Vec::new()
+    };
+    apply_color_mode(pixels, width, color, &skip);
+}
+
+/// Bitmap-pixel bounds of the page's raster image objects.
+///
+/// Only top-level image objects are found; images nested inside form
+/// XObjects are recolored with the rest of the page. Object bounds are in
+/// the unrotated page space, so they are turned by the page's `/Rotate`
+/// like the rendered page.
+fn image_pixel_rects(page: &PdfPage, scale: f32, origin: (i32, i32)) -> Vec<PixelRect> {
+    let rotation = page_rotation(page);
+    // `width()` and `height()` are those of the rotated page.
+    let size = rotation.rotate_size(page.width().value, page.height().value);
+
+    page.objects()
+        .iter()
+        .filter(|object| object.object_type() == PdfPageObjectType::Image)
+        .filter_map(|object| object.bounds().ok())
+        .map(|bounds| {
+            let bounds = (bounds.left().value, bounds.bottom().value, bounds.right().value, bounds.top().value);
+            image_pixel_rect(bounds, size, rotation, scale, origin)
+        })
+        .filter(|rect| rect.x1 > rect.x0 && rect.y1 > rect.y0)
+        .collect()
+}
+
+/// Bitmap-pixel bounds of `(left, bottom, right, top)` in the PDF space of
+/// an unrotated `width` x `height` page shown turned by `rotation`, in a
+/// bitmap at `scale` whose first pixel sits at `origin`.
+fn image_pixel_rect(
+    (left, bottom, right, top): (f32, f32, f32, f32),
+    (width, height): (f32, f32),
+    rotation: ViewRotation,
+    scale: f32,
+    origin: (i32, i32),
+) -> PixelRect {
+    let (x, y, w, h) = rotation.rotate_rect((left, height - top, right - left, top - bottom), width, height);
+    let to_px = |points: f32, origin: i32, round: fn(f32) -> f32| {
+        (round(points * scale) as i32 - origin).max(0) as u32
+    };
+    PixelRect {
+        x0: to_px(x, origin.0, f32::floor),
+        y0: to_px(y, origin.1, f32::floor),
+        x1: to_px(x + w, origin.0, f32::ceil),
+        y1: to_px(y + h, origin.1, f32::ceil),
+    }
+}
+
+/// The page's own `/Rotate`, which pdfium applies to every render.
+fn page_rotation(page: &PdfPage) -> ViewRotation {
+    match page.rotation() {
+        Ok(PdfPageRenderRotation::Degrees90) => ViewRotation::Cw90,
+        Ok(PdfPageRenderRotation::Degrees180) => ViewRotation::Cw180,
+        Ok(PdfPageRenderRotation::Degrees270) => ViewRotation::Cw270,
+        _ => ViewRotation::None,
+    }
+}
+
 /// Bind to PDFium library and return a usable Pdfium instance.
 /// This is called on-demand for each operation since Pdfium is not Send+Sync.
 fn bind_pdfium() -> Result<Pdfium, PdfError> {
@@ -168,14 +510,11 @@ fn bind_pdfium() -> Result<Pdfium, PdfError> {
     }
     
     // Strategy 3: Try system library (for development)
-    match Pdfium::bind_to_system_library() {
-        Ok(bindings) => {
-            if !LOGGED_SUCCESS.swap(true, Ordering::Relaxed) {
-                eprintln!("[Kiosk PDF] Loaded system library");
-            }
-            return Ok(Pdfium::new(bindings));
+    if let Ok(bindings) = Pdfium::bind_to_system_library() {
+        if !LOGGED_SUCCESS.swap(true, Ordering::Relaxed) {
+            eprintln!("[Kiosk PDF] Loaded system library");
         }
-        Err(_) => {}
+        return Ok(Pdfium::new(bindings));
     }
     
     // Strategy 4: Try /usr/local/lib (common development location on macOS)
@@ -358,6 +697,7 @@ pub fn get_page_info(bytes: &[u8], page_index: u32) -> Result<PageInfo, PdfError
             },
             Err(_) => 0,
         },
+        view_rotation: 0,
     })
 }
 
@@ -486,75 +826,9 @@ pub fn search_text(
     let doc = pdfium
         .load_pdf_from_byte_slice(bytes, None)
         .map_err(|e| PdfError::LoadError(e.to_string()))?;
-    
-    let mut results = Vec::new();
-    let search_query = if case_sensitive {
-        query.to_string()
-    } else {
-        query.to_lowercase()
-    };
 
-    for page_index in 0..doc.pages().len() {
-        if results.len() >= max_results {
-            break;
-        }
-
-        if let Ok(page) = doc.pages().get(page_index as u16) {
-            if let Ok(text_page) = page.text() {
-                let page_text = text_page.all();
-                let page_height = page.height().value;
-                
-                let search_text = if case_sensitive {
-                    page_text.clone()
-                } else {
-                    page_text.to_lowercase()
-                };
-
-                let mut start = 0;
-                while let Some(pos) = search_text[start..].find(&search_query) {
-                    if results.len() >= max_results {
-                        break;
-                    }
-
-                    let match_start = start + pos;
-                    let match_end = match_start + query.len();
-
-                    // Get bounding rects for the match
-                    let mut match_rects = Vec::new();
-                    
-                    // Get the chars collection and keep it alive
-                    let chars_collection = text_page.chars();
-                    let chars: Vec<_> = chars_collection.iter().collect();
-                    
-                    for i in match_start..match_end.min(chars.len()) {
-                        if let Ok(rect) = chars[i].tight_bounds() {
-                            match_rects.push(TextRect {
-                                x: rect.left().value,
-                                y: page_height - rect.top().value,
-                                width: rect.width().value,
-                                height: rect.height().value,
-                            });
-                        }
-                    }
-
-                    // Merge adjacent rects on the same line
-                    let merged_rects = merge_text_rects(match_rects);
-
-                    results.push(SearchResult {
-                        page: page_index as u32,
-                        start_index: match_start,
-                        end_index: match_end,
-                        text: page_text.chars().skip(match_start).take(match_end - match_start).collect(),
-                        rects: merged_rects,
-                    });
-
-                    start = match_end;
-                }
-            }
-        }
-    }
-
-    Ok(results)
+    let options = SearchOptions { case_sensitive, ..SearchOptions::default() };
+    search_document(&doc, query, &options, max_results)
 }
 
 /// Get all page infos for the document.
@@ -588,6 +862,7 @@ pub fn get_all_page_infos(bytes: &[u8]) -> Result<Vec<PageInfo>, PdfError> {
                 },
                 Err(_) => 0,
             },
+            view_rotation: 0,
         });
     }
 
@@ -595,7 +870,7 @@ pub fn get_all_page_infos(bytes: &[u8]) -> Result<Vec<PageInfo>, PdfError> {
 }
 
 /// Merge adjacent text rectangles on the same line into single rectangles.
-fn merge_text_rects(rects: Vec<TextRect>) -> Vec<TextRect> {
+pub(super) fn merge_text_rects(rects: Vec<TextRect>) -> Vec<TextRect> {
     if rects.is_empty() {
         return rects;
     }
@@ -642,17 +917,27 @@ fn merge_text_rects(rects: Vec<TextRect>) -> Vec<TextRect> {
 ///   when the struct itself is moved, so all internal pointers remain valid.
 /// - Neither `_bytes` nor `_pdfium` are modified after document creation.
 pub struct CachedPdf {
-    // IMPORTANT: `document` MUST be the first field for correct drop order.
-    // Rust drops struct fields in declaration order. `document` borrows from
-    // `_pdfium` and `_bytes`, so it must be dropped before them.
+    // IMPORTANT: `progressive` and `document` MUST come first for correct
+    // drop order. Rust drops struct fields in declaration order. Both borrow
+    // from `_pdfium` (and `document` from `_bytes`), so they must be dropped
+    // before them.
+    pub(super) progressive: ProgressiveJobs,
     document: PdfDocument<'static>,
     _pdfium: Pdfium,
     _bytes: Vec<u8>,
 }
 
+/// A page copied into a document of its own; see `CachedPdf::scratch_page`.
+pub(super) struct ScratchPage<'a> {
+    // Dropped in declaration order: the page closes before its document.
+    pub(super) page: PdfPage<'a>,
+    _document: PdfDocument<'a>,
+}
+
 // SAFETY: CachedPdf is safe to send between threads because:
 // 1. `PdfDocument` wraps an FPDF_DOCUMENT handle (opaque pointer) and a
-//    reference to bindings (function pointers on the heap).
+//    reference to bindings (function pointers on the heap); the pages and
+//    bitmaps of progressive jobs likewise wrap pdfium handles.
 // 2. `Pdfium` wraps `Box<dyn PdfiumLibraryBindings>` — heap-allocated
 //    function pointers with no thread affinity.
 // 3. PDFium's C API is thread-safe when document handles are not used
@@ -704,12 +989,18 @@ impl CachedPdf {
         let document: PdfDocument<'static> = unsafe { std::mem::transmute(document) };
 
         Ok(CachedPdf {
+            progressive: ProgressiveJobs::default(),
             document,
             _pdfium: pdfium,
             _bytes: bytes,
         })
     }
 
+    /// Raw PDF bytes this document was parsed from.
+    pub fn bytes(&self) -> &[u8] {
+        &self._bytes
+    }
+
     /// Get document metadata from the cached document.
     pub fn get_document_info(&self) -> Result<DocumentInfo, PdfError> {
         let metadata = self.document.metadata();
@@ -750,6 +1041,7 @@ impl CachedPdf {
                 },
                 Err(_) => 0,
             },
+            view_rotation: 0,
         })
     }
 
@@ -785,6 +1077,87 @@ impl CachedPdf {
         Ok(png_bytes)
     }
 
+    /// Run `render` on the page to draw for `layer`.
+    ///
+    /// pdfium cannot draw annotations without the page content, so the
+    /// annotation layer is drawn from a scratch copy of the page with its
+    /// content objects and form widgets removed. The copy lives only for
+    /// this call; the cached document is never modified.
+    fn with_layer_page<T>(
+        &self,
+        page_index: u32,
+        layer: RenderLayer,
+        render: impl FnOnce(&PdfPage) -> Result<T, PdfError>,
+    ) -> Result<T, PdfError> {
+        if layer != RenderLayer::Annotations {
+            let page = self.document
+                .pages()
+                .get(page_index as u16)
+                .map_err(|_| PdfError::InvalidPage(page_index))?;
+            return render(&page);
+        }
+        let scratch = self.scratch_page(page_index, layer)?;
+        render(&scratch.page)
+    }
+
+    /// Copy page `page_index` into a new in-memory document, for renders
+    /// that must not touch (or hold on to) the cached document's page.
+    ///
+    /// For `RenderLayer::Annotations` the copy is stripped down to its
+    /// annotations: content objects and form widgets are removed.
+    pub(super) fn scratch_page(&self, page_index: u32, layer: RenderLayer) -> Result<ScratchPage<'_>, PdfError> {
+        if page_index >= self.document.pages().len() as u32 {
+            return Err(PdfError::InvalidPage(page_index));
+        }
+        let scratch_error = |e: PdfiumError| PdfError::RenderError(format!("Page copy: {}", e));
+        let mut document = self._pdfium.create_new_pdf().map_err(scratch_error)?;
+        document
+            .pages_mut()
+            .copy_page_from_document(&self.document, page_index as u16, 0)
+            .map_err(scratch_error)?;
+        let mut page = document.pages().get(0).map_err(scratch_error)?;
+
+        // The copy is rendered straight from memory, so its content stream
+        // never needs regenerating.
+        page.set_content_regeneration_strategy(PdfPageContentRegenerationStrategy::Manual);
+        if layer == RenderLayer::Annotations {
+            for index in (0..page.objects().len()).rev() {
+                page.objects_mut().remove_object_at_index(index).map_err(scratch_error)?;
+            }
+            let annotations = page.annotations_mut();
+            for index in (0..annotations.len()).rev() {
+                let Ok(annotation) = annotations.get(index) else {
+                    continue;
+                };
+                if matches!(
+                    annotation.annotation_type(),
+                    PdfPageAnnotationType::Widget | PdfPageAnnotationType::XfaWidget
+                ) {
+                    annotations.delete_annotation(annotation).map_err(scratch_error)?;
+                }
+            }
+        }
+
+        Ok(ScratchPage { page, _document: document })
+    }
+
+    /// Whether page `page_index` has form fields that a render with form
+    /// data enabled would draw.
+    pub fn page_has_form_fields(&self, page_index: u32) -> bool {
+        if self.document.form().is_none() {
+            return false;
+        }
+        self.document
+            .pages()
+            .get(page_index as u16)
+            .map(|page| {
+                page.annotations()
+                    .iter()
+                    .any(|a| a.annotation_type() == PdfPageAnnotationType::Widget)
+            })
+            .unwrap_or(false)
+    }
+
     /// Render a page to raw RGBA pixel buffer using the cached document.
     ///
     /// PHASE 4: Replaces PNG encoding on the hot path. Returns raw RGBA
@@ -792,37 +1165,205 @@ impl CachedPdf {
     /// per page at high DPR) and the corresponding browser PNG decode step.
     ///
     /// The returned buffer is tightly packed RGBA (4 bytes per pixel),
-    /// row-major, top-to-bottom.
+    /// row-major, top-to-bottom, rendered according to `options`.
+    ///
+    /// A page that would exceed `max_render_pixels` at `scale` is rendered
+    /// at `effective_render_scale` instead.
     pub fn render_page_to_rgba(
         &self,
         page_index: u32,
         scale: f32,
+        options: &RenderOptions,
     ) -> Result<(Vec<u8>, u32, u32), PdfError> {
+        self.with_layer_page(page_index, options.layer, |page| {
+            let scale = fit_scale(page.width().value, page.height().value, scale, max_render_pixels());
+            let (width, height) = render_size(page.width().value, page.height().value, scale);
+            let (width, height) = (width as i32, height as i32);
+
+            let config = options
+                .pdf_render_config()
+                .set_target_width(width)
+                .set_target_height(height);
+
+            let bitmap = page
+                .render_with_config(&config)
+                .map_err(|e| PdfError::RenderError(e.to_string()))?;
+
+            // Convert to RGBA without PNG encoding
+            let image = bitmap.as_image();
+            let rgba = image.to_rgba8();
+            let (w, h) = rgba.dimensions();
+            let mut raw_pixels = rgba.into_raw();
+            recolor(page, &mut raw_pixels, w, scale, (0, 0), &options.color);
+
+            Ok(options.rotation.rotate_pixels(raw_pixels, w, h))
+        })
+    }
+
+    /// The scale `render_page_to_rgba` renders `page_index` at when asked
+    /// for `scale`: `scale` itself, or the largest smaller scale at which
+    /// the page fits in `max_render_pixels`.
+    pub fn effective_render_scale(&self, page_index: u32, scale: f32) -> Result<f32, PdfError> {
+        let (width, height) = self.page_size(page_index)?;
+        Ok(fit_scale(width, height, scale, max_render_pixels()))
+    }
+
+    /// Fail with `PdfError::RenderTooLarge` unless `page_index` renders at
+    /// exactly `scale`, for callers that must not be downscaled.
+    pub fn check_render_size(&self, page_index: u32, scale: f32) -> Result<(), PdfError> {
+        let (width, height) = self.page_size(page_index)?;
+        let (w, h) = render_size(width, height, scale);
+        let max_pixels = max_render_pixels();
+        if pixel_count((w, h)) > max_pixels {
+            return Err(PdfError::RenderTooLarge { width: w, height: h, max_pixels });
+        }
+        Ok(())
+    }
+
+    /// Unrotated page size in points.
+    fn page_size(&self, page_index: u32) -> Result<(f32, f32), PdfError> {
         let page = self.document
             .pages()
             .get(page_index as u16)
             .map_err(|_| PdfError::InvalidPage(page_index))?;
+        Ok((page.width().value, page.height().value))
+    }
 
-        let width = (page.width().value * scale) as i32;
-        let height = (page.height().value * scale) as i32;
+    /// Produce a thumbnail whose longer side is at most `max_dim` pixels.
+    ///
+    /// With default `options` (apart from `rotation`) the page's embedded
+    /// `/Thumb` image is preferred when present (scaled down if it exceeds
+    /// `max_dim`, never up); otherwise the page is rendered at the scale that
+    /// fits `max_dim`.
+    pub fn render_thumbnail(
+        &self,
+        page_index: u32,
+        max_dim: u32,
+        options: &RenderOptions,
+    ) -> Result<PageThumbnail, PdfError> {
+        let page = self.document
+            .pages()
+            .get(page_index as u16)
+            .map_err(|_| PdfError::InvalidPage(page_index))?;
 
-        let config = PdfRenderConfig::new()
-            .set_target_width(width)
-            .set_target_height(height)
-            .render_form_data(true)
-            .render_annotations(true);
+        // An embedded thumbnail is a fixed image; it can't honor options
+        // other than a rotation of the finished pixels.
+        let unrotated = RenderOptions { rotation: ViewRotation::None, ..*options };
+        if unrotated.is_default() {
+            if let Ok(bitmap) = page.embedded_thumbnail() {
+                let mut image = bitmap.as_image();
+                if image.width() > max_dim || image.height() > max_dim {
+                    image = image.thumbnail(max_dim, max_dim);
+                }
+                let rgba = image.to_rgba8();
+                let (width, height) = rgba.dimensions();
+                let (pixels, width, height) = options.rotation.rotate_pixels(rgba.into_raw(), width, height);
+                return Ok(PageThumbnail { pixels, width, height, embedded: true });
+            }
+        }
 
-        let bitmap = page
-            .render_with_config(&config)
-            .map_err(|e| PdfError::RenderError(e.to_string()))?;
+        let longest = page.width().value.max(page.height().value);
+        if longest <= 0.0 {
+            return Err(PdfError::RenderError(format!("Page {} has no area", page_index)));
+        }
+        drop(page);
 
-        // Convert to RGBA without PNG encoding
-        let image = bitmap.as_image();
-        let rgba = image.to_rgba8();
-        let (w, h) = rgba.dimensions();
-        let raw_pixels = rgba.into_raw();
+        let (pixels, width, height) = self.render_page_to_rgba(page_index, max_dim as f32 / longest, options)?;
+        Ok(PageThumbnail { pixels, width, height, embedded: false })
+    }
 
-        Ok((raw_pixels, w, h))
+    /// Render only a rectangular region of a page to raw RGBA pixels.
+    ///
+    /// `x`, `y`, `width` and `height` are in PDF points with a top-left
+    /// origin (the same convention as `CharRect` and `TextRect`), measured
+    /// in the view rotated by `options.rotation`. The region is clamped to
+    /// the page and its edges are snapped outward to whole device pixels of
+    /// the full-page render, so adjacent tiles at the same scale line up
+    /// exactly. The snapped origin is returned with the pixels, rendered
+    /// according to `options`.
+    ///
+    /// Only the tile bitmap is allocated, so deep zoom costs memory
+    /// proportional to the visible area rather than the whole page.
+    ///
+    /// NOTE: pdfium cannot draw form field data through a custom transform
+    /// matrix, so tiles never contain form fields, whatever `options.forms`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render_page_region(
+        &self,
+        page_index: u32,
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+        scale: f32,
+        options: &RenderOptions,
+    ) -> Result<RenderedRegion, PdfError> {
+        self.with_layer_page(page_index, options.layer, |page| {
+            if !scale.is_finite() || scale <= 0.0 {
+                return Err(PdfError::RenderError(format!("Invalid render scale: {}", scale)));
+            }
+
+            // Snap the requested region to the device pixel grid of the full
+            // (rotated) page at this scale, clamped to the page bounds.
+            let (page_px_width, page_px_height) = render_size(page.width().value, page.height().value, scale);
+            let (view_px_width, view_px_height) =
+                options.rotation.rotate_size(page_px_width as f32, page_px_height as f32);
+            let left = ((x * scale).floor() as i32).clamp(0, view_px_width as i32);
+            let top = ((y * scale).floor() as i32).clamp(0, view_px_height as i32);
+            let right = (((x + width) * scale).ceil() as i32).clamp(0, view_px_width as i32);
+            let bottom = (((y + height) * scale).ceil() as i32).clamp(0, view_px_height as i32);
+
+            if right <= left || bottom <= top {
+                return Err(PdfError::RenderError(format!(
+                    "Region ({}, {}, {}x{}) lies outside page {}",
+                    x, y, width, height, page_index
+                )));
+            }
+
+            // The same tile in the unrotated page's pixel grid. Whole pixels map
+            // to whole pixels, so the casts below are exact.
+            let (base_left, base_top, base_width, base_height) = options.rotation.inverse().rotate_rect(
+                (left as f32, top as f32, (right - left) as f32, (bottom - top) as f32),
+                view_px_width,
+                view_px_height,
+            );
+            let (base_left, base_top) = (base_left as i32, base_top as i32);
+
+            let max_pixels = max_render_pixels();
+            if pixel_count((base_width as u64, base_height as u64)) > max_pixels {
+                return Err(PdfError::RenderTooLarge {
+                    width: base_width as u64,
+                    height: base_height as u64,
+                    max_pixels,
+                });
+            }
+
+            // Scale the page, then shift it so the tile's top-left corner lands
+            // on the bitmap origin.
+            let config = options
+                .pdf_render_config()
+                .set_fixed_size(base_width as i32, base_height as i32)
+                .transform(scale, 0.0, 0.0, scale, -(base_left as f32), -(base_top as f32))
+                .map_err(|e| PdfError::RenderError(e.to_string()))?;
+
+            let bitmap = page
+                .render_with_config(&config)
+                .map_err(|e| PdfError::RenderError(e.to_string()))?;
+
+            let rgba = bitmap.as_image().to_rgba8();
+            let (w, h) = rgba.dimensions();
+            let mut pixels = rgba.into_raw();
+            recolor(page, &mut pixels, w, scale, (base_left, base_top), &options.color);
+            let (pixels, w, h) = options.rotation.rotate_pixels(pixels, w, h);
+
+            Ok(RenderedRegion {
+                pixels,
+                width: w,
+                height: h,
+                x: left as f32 / scale,
+                y: top as f32 / scale,
+            })
+        })
     }
 
     /// Get character bounding boxes for text selection from the cached document.
@@ -879,80 +1420,50 @@ impl CachedPdf {
     }
 
     /// Search for text across all pages using the cached document.
+    ///
+    /// `start_index`/`end_index` of each result index the characters
+    /// `get_char_rects` returns. `options` selects the query syntax and the
+    /// pages to search (see `search`); an unusable query fails with
+    /// `PdfError::InvalidQuery`.
     pub fn search_text(
         &self,
         query: &str,
-        case_sensitive: bool,
+        options: &SearchOptions,
         max_results: usize,
     ) -> Result<Vec<SearchResult>, PdfError> {
-        let mut results = Vec::new();
-        let search_query = if case_sensitive {
-            query.to_string()
-        } else {
-            query.to_lowercase()
-        };
-
-        for page_index in 0..self.document.pages().len() {
-            if results.len() >= max_results {
-                break;
-            }
-
-            if let Ok(page) = self.document.pages().get(page_index as u16) {
-                if let Ok(text_page) = page.text() {
-                    let page_text = text_page.all();
-                    let page_height = page.height().value;
-
-                    let search_text_str = if case_sensitive {
-                        page_text.clone()
-                    } else {
-                        page_text.to_lowercase()
-                    };
-
-                    let mut start = 0;
-                    while let Some(pos) = search_text_str[start..].find(&search_query) {
-                        if results.len() >= max_results {
-                            break;
-                        }
+        search_document(&self.document, query, options, max_results)
+    }
 
-                        let match_start = start + pos;
-                        let match_end = match_start + query.len();
-
-                        let mut match_rects = Vec::new();
-                        let chars_collection = text_page.chars();
-                        let chars: Vec<_> = chars_collection.iter().collect();
-
-                        for i in match_start..match_end.min(chars.len()) {
-                            if let Ok(rect) = chars[i].tight_bounds() {
-                                match_rects.push(TextRect {
-                                    x: rect.left().value,
-                                    y: page_height - rect.top().value,
-                                    width: rect.width().value,
-                                    height: rect.height().value,
-                                });
-                            }
-                        }
+    /// `search_text` answered from a full-text index of this document,
+    /// with the same results.
+    pub fn search_text_indexed(
+        &self,
+        index: &SearchIndex,
+        query: &str,
+        options: &SearchOptions,
+        max_results: usize,
+    ) -> Result<Vec<SearchResult>, PdfError> {
+        search_indexed(&self.document, index, query, options, max_results)
+    }
 
-                        let merged_rects = merge_text_rects(match_rects);
-
-                        results.push(SearchResult {
-                            page: page_index as u32,
-                            start_index: match_start,
-                            end_index: match_end,
-                            text: page_text
-                                .chars()
-                                .skip(match_start)
-                                .take(match_end - match_start)
-                                .collect(),
-                            rects: merged_rects,
-                        });
-
-                        start = match_end;
-                    }
-                }
-            }
-        }
+    /// The text of each character of a page, for `SearchIndex::add_page`.
+    pub fn page_chars(&self, page_index: u32) -> Result<Vec<Option<String>>, PdfError> {
+        let page = self
+            .document
+            .pages()
+            .get(page_index as u16)
+            .map_err(|_| PdfError::InvalidPage(page_index))?;
+        page_chars(&page)
+    }
 
-        Ok(results)
+    /// All matches of a compiled query on one page, in page order.
+    pub fn search_page(&self, page_index: u32, query: &CompiledQuery) -> Result<Vec<SearchResult>, PdfError> {
+        let page = self
+            .document
+            .pages()
+            .get(page_index as u16)
+            .map_err(|_| PdfError::InvalidPage(page_index))?;
+        search_page(&page, page_index, query, usize::MAX)
     }
 
     /// Get all page infos for the document from the cached document.
@@ -979,6 +1490,7 @@ impl CachedPdf {
                     },
                     Err(_) => 0,
                 },
+                view_rotation: 0,
             });
         }
 
@@ -990,6 +1502,76 @@ impl CachedPdf {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_render_options_defaults() {
+        let options: RenderOptions = serde_json::from_str(r#"{ "annotations": false }"#).unwrap();
+        assert!(!options.annotations);
+        assert!(options.forms && options.antialias_text && !options.transparent);
+        assert_eq!(options.color, ColorMode::Normal);
+        assert_eq!(options.layer, RenderLayer::Full);
+
+        let layer: RenderOptions = serde_json::from_str(r#"{ "layer": "annotations" }"#).unwrap();
+        assert_eq!(layer.layer, RenderLayer::Annotations);
+        assert_ne!(layer.fingerprint(), 0);
+
+        assert_eq!(RenderOptions::default().fingerprint(), 0);
+        assert_ne!(options.fingerprint(), 0);
+        assert_eq!(RenderOptions::default().cache_tag(), None);
+        assert_eq!(options.cache_tag().as_deref(), Some("noannots"));
+        let styled = RenderOptions {
+            antialias_paths: false,
+            background: [244, 236, 216],
+            color: ColorMode::Inverted { preserve_images: true },
+            rotation: ViewRotation::Cw90,
+            ..layer
+        };
+        assert_eq!(styled.cache_tag().as_deref(), Some("aa101-bgf4ecd8-invert-keepimg-rot90-annotlayer"));
+        let parsed: RenderOptions = serde_json::from_str("{}").unwrap();
+        assert!(parsed.is_default());
+    }
+
+    #[test]
+    fn test_fit_scale() {
+        // A 200 x 24 inch banner at scale 4.
+        let (width, height) = (14400.0, 1728.0);
+        assert_eq!(fit_scale(612.0, 792.0, 2.0, DEFAULT_MAX_RENDER_PIXELS), 2.0);
+
+        let fitted = fit_scale(width, height, 4.0, DEFAULT_MAX_RENDER_PIXELS);
+        let (w, h) = render_size(width, height, fitted);
+        assert!(fitted < 4.0 && w * h <= DEFAULT_MAX_RENDER_PIXELS);
+        assert!(w * h > DEFAULT_MAX_RENDER_PIXELS * 99 / 100);
+        assert_eq!(fit_scale(width, height, fitted, DEFAULT_MAX_RENDER_PIXELS), fitted);
+
+        // Thin pages and tiny limits keep at least one pixel per side.
+        for (width, height, max_pixels) in [(14400.0, 1.0, 1000), (1.0, 14400.0, 1000), (612.0, 792.0, 1)] {
+            let fitted = fit_scale(width, height, 2.0, max_pixels);
+            let (w, h) = render_size(width, height, fitted);
+            assert!(fitted > 0.0 && w >= 1 && h >= 1 && w * h <= max_pixels);
+        }
+        assert_eq!(render_size(612.0, 792.0, 1e-6), (1, 1));
+
+        // Scales whose pixel count overflows u64 still fit.
+        for huge in [4.3e7, 1e30, f32::INFINITY] {
+            let fitted = fit_scale(612.0, 792.0, huge, DEFAULT_MAX_RENDER_PIXELS);
+            assert!(fitted.is_finite() && fitted > 0.0);
+            assert!(pixel_count(render_size(612.0, 792.0, fitted)) <= DEFAULT_MAX_RENDER_PIXELS);
+        }
+    }
+
+    #[test]
+    fn test_image_pixel_rect_on_rotated_page() {
+        // An image in the top-left corner of an unrotated 100 x 200 page.
+        let bounds = (10.0, 150.0, 30.0, 190.0);
+        let rect = image_pixel_rect(bounds, (100.0, 200.0), ViewRotation::None, 2.0, (0, 0));
+        assert_eq!(rect, PixelRect { x0: 20, y0: 20, x1: 60, y1: 100 });
+
+        // With `/Rotate 90` the corner is shown top right of a 200 x 100 page.
+        let rect = image_pixel_rect(bounds, (100.0, 200.0), ViewRotation::Cw90, 1.0, (0, 0));
+        assert_eq!(rect, PixelRect { x0: 150, y0: 10, x1: 190, y1: 30 });
+        let rect = image_pixel_rect(bounds, (100.0, 200.0), ViewRotation::Cw90, 1.0, (160, 0));
+        assert_eq!(rect, PixelRect { x0: 0, y0: 10, x1: 30, y1: 30 });
+    }
+
     #[test]
     fn test_merge_text_rects() {
         let rects = vec![
@@ -0,0 +1,192 @@
+//! View-only page rotation.
+//!
+//! A sideways scan can be viewed upright without touching the file: the
+//! document keeps a `ViewRotations` table (a global rotation plus per-page
+//! overrides), renders are rotated to match, and every geometry the viewer
+//! consumes (`PageInfo` sizes, `CharRect`s, search `TextRect`s) is mapped
+//! into the rotated view.
+//!
+//! All geometry here uses the renderer's top-left-origin page space. A
+//! rotation maps a rect in a `width` x `height` source space into the
+//! rotated space; `inverse()` maps back.
+
+use super::{CharRect, PageInfo, TextRect};
+use image::{imageops, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Clockwise rotation in quarter turns, serialized as degrees.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "i32", into = "i32")]
+pub enum ViewRotation {
+    #[default]
+    None,
+    Cw90,
+    Cw180,
+    Cw270,
+}
+
+impl TryFrom<i32> for ViewRotation {
+    type Error = String;
+
+    /// Accepts any multiple of 90, including negative (counter-clockwise).
+    fn try_from(degrees: i32) -> Result<Self, Self::Error> {
+        if degrees % 90 != 0 {
+            return Err(format!("Rotation must be a multiple of 90 degrees: {}", degrees));
+        }
+        Ok(match degrees.rem_euclid(360) {
+            0 => ViewRotation::None,
+            90 => ViewRotation::Cw90,
+            180 => ViewRotation::Cw180,
+            _ => ViewRotation::Cw270,
+        })
+    }
+}
+
+impl From<ViewRotation> for i32 {
+    fn from(rotation: ViewRotation) -> i32 {
+        rotation.degrees()
+    }
+}
+
+impl ViewRotation {
+    pub fn degrees(self) -> i32 {
+        match self {
+            ViewRotation::None => 0,
+            ViewRotation::Cw90 => 90,
+            ViewRotation::Cw180 => 180,
+            ViewRotation::Cw270 => 270,
+        }
+    }
+
+    /// This rotation followed by `other`.
+    pub fn then(self, other: ViewRotation) -> ViewRotation {
+        ViewRotation::try_from(self.degrees() + other.degrees()).unwrap_or_default()
+    }
+
+    pub fn inverse(self) -> ViewRotation {
+        ViewRotation::try_from(-self.degrees()).unwrap_or_default()
+    }
+
+    /// Whether width and height trade places.
+    pub fn swaps_axes(self) -> bool {
+        matches!(self, ViewRotation::Cw90 | ViewRotation::Cw270)
+    }
+
+    /// Size of a `width` x `height` space after rotation.
+    pub fn rotate_size(self, width: f32, height: f32) -> (f32, f32) {
+        if self.swaps_axes() {
+            (height, width)
+        } else {
+            (width, height)
+        }
+    }
+
+    /// Map the rect `(x, y, w, h)` in a `width` x `height` source space
+    /// into the rotated space.
+    pub fn rotate_rect(
+        self,
+        (x, y, w, h): (f32, f32, f32, f32),
+        width: f32,
+        height: f32,
+    ) -> (f32, f32, f32, f32) {
+        match self {
+            ViewRotation::None => (x, y, w, h),
+            ViewRotation::Cw90 => (height - y - h, x, h, w),
+            ViewRotation::Cw180 => (width - x - w, height - y - h, w, h),
+            ViewRotation::Cw270 => (y, width - x - w, h, w),
+        }
+    }
+
+    /// Rotate tightly packed RGBA pixels, returning the new buffer and size.
+    pub fn rotate_pixels(self, pixels: Vec<u8>, width: u32, height: u32) -> (Vec<u8>, u32, u32) {
+        if self == ViewRotation::None {
+            return (pixels, width, height);
+        }
+        let Some(image) = RgbaImage::from_raw(width, height, pixels) else {
+            // Callers always pass width * height * 4 bytes.
+            return (Vec::new(), 0, 0);
+        };
+        let rotated = match self {
+            ViewRotation::Cw90 => imageops::rotate90(&image),
+            ViewRotation::Cw180 => imageops::rotate180(&image),
+            _ => imageops::rotate270(&image),
+        };
+        let (w, h) = rotated.dimensions();
+        (rotated.into_raw(), w, h)
+    }
+
+    /// Map page info into the rotated view: size swapped, rotation noted.
+    pub fn rotate_page_info(self, info: &mut PageInfo) {
+        (info.width, info.height) = self.rotate_size(info.width, info.height);
+        info.view_rotation = self.degrees();
+    }
+
+    /// Map character boxes of a `width` x `height` page into the view.
+    pub fn rotate_char_rects(self, rects: &mut [CharRect], width: f32, height: f32) {
+        if self == ViewRotation::None {
+            return;
+        }
+        for rect in rects {
+            (rect.x, rect.y, rect.width, rect.height) =
+                self.rotate_rect((rect.x, rect.y, rect.width, rect.height), width, height);
+        }
+    }
+
+    /// Map text rects of a `width` x `height` page into the view.
+    pub fn rotate_text_rects(self, rects: &mut [TextRect], width: f32, height: f32) {
+        if self == ViewRotation::None {
+            return;
+        }
+        for rect in rects {
+            (rect.x, rect.y, rect.width, rect.height) =
+                self.rotate_rect((rect.x, rect.y, rect.width, rect.height), width, height);
+        }
+    }
+}
+
+/// A document's view rotations: one for all pages, plus per-page overrides.
+#[derive(Debug, Clone, Default)]
+pub struct ViewRotations {
+    pub global: ViewRotation,
+    pub pages: HashMap<u32, ViewRotation>,
+}
+
+impl ViewRotations {
+    pub fn for_page(&self, page_index: u32) -> ViewRotation {
+        self.pages.get(&page_index).copied().unwrap_or(self.global)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotate_rect_roundtrip() {
+        // A 10x20 box at (1, 2) on a 100x200 page.
+        let rect = (1.0, 2.0, 10.0, 20.0);
+        assert_eq!(ViewRotation::Cw90.rotate_rect(rect, 100.0, 200.0), (178.0, 1.0, 20.0, 10.0));
+        assert_eq!(ViewRotation::Cw180.rotate_rect(rect, 100.0, 200.0), (89.0, 178.0, 10.0, 20.0));
+        assert_eq!(ViewRotation::Cw270.rotate_rect(rect, 100.0, 200.0), (2.0, 89.0, 20.0, 10.0));
+
+        for rotation in [ViewRotation::Cw90, ViewRotation::Cw180, ViewRotation::Cw270] {
+            let (vw, vh) = rotation.rotate_size(100.0, 200.0);
+            let view = rotation.rotate_rect(rect, 100.0, 200.0);
+            assert_eq!(rotation.inverse().rotate_rect(view, vw, vh), rect, "{:?}", rotation);
+        }
+
+        assert_eq!(ViewRotation::try_from(-90), Ok(ViewRotation::Cw270));
+        assert!(ViewRotation::try_from(45).is_err());
+        assert_eq!(ViewRotation::Cw270.then(ViewRotation::Cw180), ViewRotation::Cw90);
+    }
+
+    #[test]
+    fn test_rotate_pixels() {
+        // 2x1: red, green -> rotated 90 cw is 1x2: red on top, green below.
+        let pixels = vec![255, 0, 0, 255, 0, 255, 0, 255];
+        let (rotated, w, h) = ViewRotation::Cw90.rotate_pixels(pixels, 2, 1);
+        assert_eq!((w, h), (1, 2));
+        assert_eq!(rotated, vec![255, 0, 0, 255, 0, 255, 0, 255]);
+    }
+}
@@ -0,0 +1,924 @@
+//! Text search over pdfium text pages.
+//!
+//! Matches are found in a searchable copy of the page text built from
+//! pdfium's character list (`PageText`), which remembers where every
+//! character starts. A match is therefore always reported as a range of the
+//! character indices `get_char_rects` returns, whatever the text contains:
+//! multi-byte UTF-8, characters whose case folding has a different length
+//! (`ß` folds to `ss`), or characters without a Unicode value.
+//!
+//! Case-insensitive search uses full Unicode case folding. Each character
+//! can further be normalized (`SearchNormalization`) so that `résumé`,
+//! `ﬁle` and full-width `ＰＤＦ` are found by `resume`, `file` and `PDF`; the
+//! query is folded the same way as the page text. With `join_lines` the
+//! page text reads as running prose: a line break matches a space, and a
+//! word hyphenated at the end of a line matches the whole word, while the
+//! match still covers the characters on both lines.
+//!
+//! Queries are read according to `SearchMode`:
+//!
+//! ```text
+//! text   indemnif                 every occurrence of the string
+//! regex  \d+\.\d+\(a\)            every match of a regular expression
+//! query  "force majeure" OR act   terms separated by spaces must all occur
+//!                                 on a page; OR separates alternatives,
+//!                                 quoted phrases are single terms
+//! fuzzy  indemnification          approximate matches, allowing a few
+//!                                 characters to be missing, extra or wrong
+//! ```
+//!
+//! In `query` mode every occurrence of the terms of a satisfied alternative
+//! is reported. Regular expressions run on the folded page text.
+//!
+//! `fuzzy` mode is meant for OCR text layers. A match may differ from the
+//! query by up to `max_edits` inserted, deleted or substituted characters
+//! (after folding), but always by fewer than half of the query's characters,
+//! so short queries don't match every letter of the page.
+//! `SearchResult::score` rates a match from 1 (exact) down; results of a
+//! document search come best first instead of in page order.
+//! Other modes score every match 1. With
+//! `whole_word` a match must not begin or end inside a word.
+
+use super::renderer::{merge_text_rects, PdfError, SearchResult, TextRect};
+use pdfium_render::prelude::*;
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BinaryHeap};
+use std::ops::Range;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+/// How a search query is interpreted (see module docs).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchMode {
+    /// The query is a literal string
+    #[default]
+    Text,
+    /// The query is a regular expression (`regex` crate syntax)
+    Regex,
+    /// Terms and quoted phrases, combined with implicit AND and with OR
+    Query,
+    /// Approximate matches of the query string (see module docs)
+    Fuzzy,
+}
+
+/// Differences between query and page text that a search ignores.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchNormalization {
+    /// Compatibility normalization (NFKC): full-width forms, ligatures,
+    /// superscripts and similar variants match their plain form
+    pub compatibility: bool,
+    /// Accents and other combining marks are ignored
+    pub ignore_diacritics: bool,
+    /// Typographic ligatures (`ﬁ`, `ﬂ`, `ﬀ`, ...) match their letters;
+    /// implied by `compatibility`
+    pub expand_ligatures: bool,
+    /// Line breaks match a single space, and words hyphenated across a line
+    /// break (`inter-` / `national`) match without the hyphen
+    pub join_lines: bool,
+}
+
+/// Characters of context on each side of a match in
+/// `SearchResult::context_before`/`context_after`.
+const CONTEXT_CHARS: usize = 40;
+
+/// Options of a text search.
+#[derive(Debug, Clone, Default)]
+pub struct SearchOptions {
+    pub case_sensitive: bool,
+    pub mode: SearchMode,
+    pub normalization: SearchNormalization,
+    /// Matches must start and end at word boundaries
+    pub whole_word: bool,
+    /// Most edits a `fuzzy` match may differ from the query by; by default
+    /// a quarter of the query's length. Capped below half of its length
+    pub max_edits: Option<u32>,
+    /// 0-based indices of the pages to search; all pages when `None`
+    pub pages: Option<Vec<u32>>,
+}
+
+impl SearchOptions {
+    /// The 0-based pages to search, in ascending order and without
+    /// duplicates.
+    pub fn page_indices(&self, page_count: u32) -> Vec<u32> {
+        match &self.pages {
+            Some(pages) => {
+                let mut pages = pages.clone();
+                pages.sort_unstable();
+                pages.dedup();
+                pages.retain(|&p| p < page_count);
+                pages
+            }
+            None => (0..page_count).collect(),
+        }
+    }
+}
+
+/// Letters of the ligatures in the Alphabetic Presentation Forms block.
+fn expand_ligature(c: char) -> Option<&'static str> {
+    Some(match c {
+        '\u{FB00}' => "ff",
+        '\u{FB01}' => "fi",
+        '\u{FB02}' => "fl",
+        '\u{FB03}' => "ffi",
+        '\u{FB04}' => "ffl",
+        '\u{FB05}' | '\u{FB06}' => "st",
+        _ => return None,
+    })
+}
+
+/// Hyphens that can end a line inside a hyphenated word. U+0002 is how
+/// pdfium reports some line-end hyphens.
+const LINE_END_HYPHENS: &[&str] = &["-", "\u{AD}", "\u{2010}", "\u{2}"];
+
+/// How a character reads in the searchable text with `join_lines`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Join {
+    Keep,
+    /// The first character of a line break
+    Space,
+    /// The rest of a line break, or a hyphen joining a word across it
+    Drop,
+}
+
+/// Whether `c` continues a word, for `whole_word`.
+pub(super) fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || is_combining_mark(c)
+}
+
+/// How query and page text are folded before they are compared.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Folding {
+    case_sensitive: bool,
+    normalization: SearchNormalization,
+}
+
+impl Folding {
+    pub(super) fn new(options: &SearchOptions) -> Self {
+        Folding { case_sensitive: options.case_sensitive, normalization: options.normalization }
+    }
+
+    /// The folding that ignores the most: whatever text matches a query
+    /// under any options also matches it under this one.
+    pub(super) fn loosest() -> Self {
+        Folding {
+            case_sensitive: false,
+            normalization: SearchNormalization {
+                compatibility: true,
+                ignore_diacritics: true,
+                expand_ligatures: true,
+                join_lines: true,
+            },
+        }
+    }
+
+    /// Fold `text` the way page text is folded for a search.
+    pub(super) fn fold(&self, text: &str) -> String {
+        let SearchNormalization { compatibility, ignore_diacritics, expand_ligatures, .. } = self.normalization;
+        let mut folded = if expand_ligatures && !compatibility {
+            text.chars()
+                .map(|c| expand_ligature(c).map_or_else(|| c.to_string(), str::to_string))
+                .collect()
+        } else {
+            text.to_string()
+        };
+        if !self.case_sensitive {
+            folded = caseless::default_case_fold_str(&folded);
+        }
+        // Decomposed forms, so that a base letter and its combining mark
+        // match whether the PDF stores them as one character or two.
+        if compatibility {
+            folded = folded.nfkd().collect();
+        }
+        if ignore_diacritics {
+            folded = folded.nfd().filter(|&c| !is_combining_mark(c)).collect();
+        }
+        folded
+    }
+}
+
+/// One page's text in searchable form.
+pub(super) struct PageText {
+    /// Text of the page's characters, concatenated
+    original: String,
+    /// Byte offset of each character in `original`, plus its length
+    original_offsets: Vec<usize>,
+    /// Each character passed through `Folding::fold`, concatenated; with
+    /// `join_lines`, line breaks and the hyphens they join are replaced
+    /// first (see `line_joins`)
+    folded: String,
+    /// Byte offset of each character in `folded`, plus its length
+    folded_offsets: Vec<usize>,
+}
+
+impl PageText {
+    /// Build from the Unicode value of each character, in page order.
+    pub(super) fn new<S: AsRef<str>>(chars: impl IntoIterator<Item = Option<S>>, folding: Folding) -> Self {
+        let mut text = PageText {
+            original: String::new(),
+            original_offsets: Vec::new(),
+            folded: String::new(),
+            folded_offsets: Vec::new(),
+        };
+        for c in chars {
+            text.original_offsets.push(text.original.len());
+            if let Some(c) = c {
+                text.original.push_str(c.as_ref());
+            }
+        }
+        text.original_offsets.push(text.original.len());
+
+        let joins = folding.normalization.join_lines.then(|| text.line_joins());
+        let mut folded = String::with_capacity(text.original.len());
+        let mut folded_offsets = Vec::with_capacity(text.original_offsets.len());
+        for i in 0..text.len() {
+            folded_offsets.push(folded.len());
+            let c = match joins.as_ref().map_or(Join::Keep, |joins| joins[i]) {
+                Join::Keep => text.text(i..i + 1),
+                Join::Space => " ",
+                Join::Drop => "",
+            };
+            folded.push_str(&folding.fold(c));
+        }
+        folded_offsets.push(folded.len());
+        text.folded = folded;
+        text.folded_offsets = folded_offsets;
+        text
+    }
+
+    /// How each character reads with `join_lines`. A line break is a run of
+    /// whitespace (or characters without text) containing `\r` or `\n`; it
+    /// reads as one space, or as nothing after a hyphen. A hyphen between a
+    /// letter and a line break is dropped too when the next line starts
+    /// with a lowercase letter, as it then most likely splits one word.
+    /// Soft hyphens never read as anything.
+    fn line_joins(&self) -> Vec<Join> {
+        let len = self.len();
+        let char_at = |i: usize| self.text(i..i + 1);
+        let is_blank = |i: usize| char_at(i).chars().all(char::is_whitespace);
+        let mut joins = vec![Join::Keep; len];
+        let mut i = 0;
+        while i < len {
+            if char_at(i) == "\u{AD}" {
+                joins[i] = Join::Drop;
+            }
+            if !is_blank(i) {
+                i += 1;
+                continue;
+            }
+            let start = i;
+            while i < len && is_blank(i) {
+                i += 1;
+            }
+            if !self.text(start..i).contains(['\r', '\n']) {
+                continue;
+            }
+            joins[start..i].fill(Join::Drop);
+            let hyphen = start.checked_sub(1).filter(|&h| LINE_END_HYPHENS.contains(&char_at(h)));
+            let after_word = hyphen
+                .and_then(|h| h.checked_sub(1))
+                .and_then(|w| char_at(w).chars().last())
+                .is_some_and(is_word_char);
+            let before_lowercase = i < len && char_at(i).chars().next().is_some_and(char::is_lowercase);
+            match hyphen {
+                Some(h) if after_word && before_lowercase => joins[h] = Join::Drop,
+                Some(_) if after_word => {}
+                _ => joins[start] = Join::Space,
+            }
+        }
+        joins
+    }
+
+    /// The searchable text, see `folded`.
+    pub(super) fn folded(&self) -> &str {
+        &self.folded
+    }
+
+    /// Number of characters.
+    pub(super) fn len(&self) -> usize {
+        self.original_offsets.len() - 1
+    }
+
+    /// Original text of the characters in `chars`.
+    pub(super) fn text(&self, chars: Range<usize>) -> &str {
+        &self.original[self.original_offsets[chars.start]..self.original_offsets[chars.end]]
+    }
+
+    /// The characters a byte range of `folded` was produced from.
+    fn char_range(&self, bytes: Range<usize>) -> Range<usize> {
+        let start = self.folded_offsets.partition_point(|&o| o <= bytes.start) - 1;
+        let end = self.folded_offsets.partition_point(|&o| o < bytes.end);
+        start..end
+    }
+
+    /// Character ranges of the non-overlapping matches of `query`, which
+    /// must have been folded like the page text.
+    pub(super) fn find<'a>(&'a self, query: &'a str) -> impl Iterator<Item = Range<usize>> + 'a {
+        self.folded
+            .match_indices(query)
+            .take_while(move |_| !query.is_empty())
+            .map(move |(at, m)| self.char_range(at..at + m.len()))
+    }
+
+    /// Character ranges of the non-empty matches of `regex` in the folded
+    /// text.
+    pub(super) fn find_regex<'a>(&'a self, regex: &'a Regex) -> impl Iterator<Item = Range<usize>> + 'a {
+        regex
+            .find_iter(&self.folded)
+            .filter(|m| !m.is_empty())
+            .map(move |m| self.char_range(m.range()))
+    }
+
+    /// Approximate matches of the folded `query` with at most `max_edits`
+    /// edits, as character ranges and scores in page order. Where candidate
+    /// matches overlap the one with the fewest edits wins.
+    pub(super) fn find_fuzzy(&self, query: &[char], max_edits: u32) -> Vec<(Range<usize>, f32)> {
+        if query.is_empty() {
+            return Vec::new();
+        }
+        // Approximate substring matching (Sellers): one column of edit
+        // distances per text position, where a match may start anywhere.
+        // `start[i]` tracks where the best alignment of `query[..i]` began.
+        // Fewer than half of the query's characters may be edited, which
+        // also keeps a match at least half as long as the query.
+        let max_edits = max_edits.min((query.len() as u32 - 1) / 2);
+        let text: Vec<(usize, char)> = self.folded.char_indices().collect();
+        let mut cost: Vec<u32> = (0..=query.len() as u32).collect();
+        let mut start = vec![0; query.len() + 1];
+        let mut candidates = Vec::new();
+        for (j, &(_, c)) in text.iter().enumerate() {
+            let (mut diagonal, mut diagonal_start) = (cost[0], start[0]);
+            cost[0] = 0;
+            start[0] = j + 1;
+            for i in 1..=query.len() {
+                let substitute = (diagonal + (query[i - 1] != c) as u32, diagonal_start);
+                let skip_text = (cost[i] + 1, start[i]);
+                let skip_query = (cost[i - 1] + 1, start[i - 1]);
+                (diagonal, diagonal_start) = (cost[i], start[i]);
+                (cost[i], start[i]) = substitute.min(skip_text).min(skip_query);
+            }
+            let edits = cost[query.len()];
+            if edits <= max_edits && start[query.len()] <= j {
+                candidates.push((edits, start[query.len()]..j + 1));
+            }
+        }
+
+        candidates.sort_by_key(|(edits, range)| (*edits, range.start, range.len()));
+        let mut taken = vec![false; text.len()];
+        let mut found = Vec::new();
+        for (edits, range) in candidates {
+            if taken[range.clone()].iter().any(|&t| t) {
+                continue;
+            }
+            taken[range.clone()].fill(true);
+            let score = 1.0 - edits as f32 / query.len().max(range.len()) as f32;
+            let bytes = text[range.start].0..text[range.end - 1].0 + text[range.end - 1].1.len_utf8();
+            found.push((self.char_range(bytes), score));
+        }
+        found.sort_by_key(|(range, _)| range.start);
+        found
+    }
+
+    /// Up to `CONTEXT_CHARS` characters before and after `chars`, with
+    /// whitespace collapsed.
+    pub(super) fn context(&self, chars: &Range<usize>) -> (String, String) {
+        let collapse = |text: &str| text.split_whitespace().collect::<Vec<_>>().join(" ");
+        let before = self.text(chars.start.saturating_sub(CONTEXT_CHARS)..chars.start);
+        let after = self.text(chars.end..(chars.end + CONTEXT_CHARS).min(self.len()));
+        let mut context_before = collapse(before);
+        let mut context_after = collapse(after);
+        if before.ends_with(char::is_whitespace) && !context_before.is_empty() {
+            context_before.push(' ');
+        }
+        if after.starts_with(char::is_whitespace) && !context_after.is_empty() {
+            context_after.insert(0, ' ');
+        }
+        (context_before, context_after)
+    }
+
+    /// Whether `chars` neither begins nor ends inside a word.
+    pub(super) fn is_whole_word(&self, chars: &Range<usize>) -> bool {
+        let first = self.text(chars.start..chars.start + 1).chars().next();
+        let last = self.text(chars.end - 1..chars.end).chars().last();
+        let before = chars
+            .start
+            .checked_sub(1)
+            .and_then(|i| self.text(i..i + 1).chars().last());
+        let after = (chars.end < self.len())
+            .then(|| self.text(chars.end..chars.end + 1).chars().next())
+            .flatten();
+        let boundary =
+            |a: Option<char>, b: Option<char>| !(a.is_some_and(is_word_char) && b.is_some_and(is_word_char));
+        boundary(before, first) && boundary(last, after)
+    }
+}
+
+/// A query compiled for `SearchOptions`, ready to run on pages.
+pub struct CompiledQuery {
+    pub(super) folding: Folding,
+    whole_word: bool,
+    pattern: Pattern,
+}
+
+enum Pattern {
+    /// A folded literal
+    Text(String),
+    Regex(Regex),
+    /// Alternatives, each a list of folded terms that must all occur
+    Terms(Vec<Vec<String>>),
+    /// The characters of a folded query, and the edits a match may need
+    Fuzzy(Vec<char>, u32),
+}
+
+impl CompiledQuery {
+    /// Fails with `PdfError::InvalidQuery` when `query` is unusable in the
+    /// selected mode.
+    pub fn new(query: &str, options: &SearchOptions) -> Result<Self, PdfError> {
+        let folding = Folding::new(options);
+        let pattern = match options.mode {
+            SearchMode::Text => Pattern::Text(folding.fold(query)),
+            SearchMode::Regex => Pattern::Regex(
+                RegexBuilder::new(query)
+                    .case_insensitive(!options.case_sensitive)
+                    .build()
+                    .map_err(|e| PdfError::InvalidQuery(e.to_string()))?,
+            ),
+            SearchMode::Query => Pattern::Terms(
+                parse_terms(query)?
+                    .into_iter()
+                    .map(|terms| terms.iter().map(|t| folding.fold(t)).collect())
+                    .collect(),
+            ),
+            SearchMode::Fuzzy => {
+                let query: Vec<char> = folding.fold(query).chars().collect();
+                let max_edits = options.max_edits.unwrap_or(query.len() as u32 / 4);
+                Pattern::Fuzzy(query, max_edits)
+            }
+        };
+        Ok(CompiledQuery { folding, whole_word: options.whole_word, pattern })
+    }
+
+    /// Character ranges of the matches on a page with their scores, in
+    /// page order and not overlapping.
+    pub(super) fn matches(&self, text: &PageText) -> Vec<(Range<usize>, f32)> {
+        let keep = |range: &Range<usize>| !self.whole_word || text.is_whole_word(range);
+        let exact = |range: Range<usize>| (range, 1.0);
+        match &self.pattern {
+            Pattern::Text(query) => text.find(query).filter(keep).map(exact).collect(),
+            Pattern::Regex(regex) => text.find_regex(regex).filter(keep).map(exact).collect(),
+            Pattern::Fuzzy(query, max_edits) => text
+                .find_fuzzy(query, *max_edits)
+                .into_iter()
+                .filter(|(range, _)| keep(range))
+                .collect(),
+            Pattern::Terms(alternatives) => {
+                let mut found = Vec::new();
+                for terms in alternatives {
+                    let matches: Vec<Vec<Range<usize>>> =
+                        terms.iter().map(|term| text.find(term).filter(keep).collect()).collect();
+                    if matches.iter().all(|m| !m.is_empty()) {
+                        found.extend(matches.into_iter().flatten());
+                    }
+                }
+                found.sort_by_key(|r| (r.start, std::cmp::Reverse(r.end)));
+                let mut end = 0;
+                found.retain(|r| {
+                    let keep = r.start >= end;
+                    if keep {
+                        end = r.end;
+                    }
+                    keep
+                });
+                found.into_iter().map(exact).collect()
+            }
+        }
+    }
+}
+
+/// Split a `query` mode query into alternatives of terms: words separated
+/// by whitespace, `"quoted phrases"` as single terms, `OR` between
+/// alternatives and an optional `AND` between terms.
+pub(super) fn parse_terms(query: &str) -> Result<Vec<Vec<String>>, PdfError> {
+    let mut alternatives = vec![Vec::new()];
+    let mut chars = query.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        if c == '"' {
+            chars.next();
+            let phrase: String = chars.by_ref().take_while(|&c| c != '"').collect();
+            if !phrase.trim().is_empty() {
+                alternatives.last_mut().unwrap().push(phrase);
+            }
+            continue;
+        }
+        let mut word = String::new();
+        while let Some(&c) = chars.peek() {
+            if c.is_whitespace() || c == '"' {
+                break;
+            }
+            word.push(c);
+            chars.next();
+        }
+        match word.as_str() {
+            "OR" => alternatives.push(Vec::new()),
+            "AND" => {}
+            _ => alternatives.last_mut().unwrap().push(word),
+        }
+    }
+    alternatives.retain(|terms| !terms.is_empty());
+    if alternatives.is_empty() {
+        return Err(PdfError::InvalidQuery(format!("No search terms in {:?}", query)));
+    }
+    Ok(alternatives)
+}
+
+/// Find up to `limit` matches of `query` on `page`.
+pub(super) fn search_page(
+    page: &PdfPage,
+    page_index: u32,
+    query: &CompiledQuery,
+    limit: usize,
+) -> Result<Vec<SearchResult>, PdfError> {
+    let text_page = page
+        .text()
+        .map_err(|e| PdfError::RenderError(e.to_string()))?;
+    let chars = text_page.chars();
+    let text = PageText::new(chars.iter().map(|c| c.unicode_string()), query.folding);
+    let mut matches = query.matches(&text);
+    matches.truncate(limit);
+    Ok(page_results(page, page_index, &chars, &text, matches))
+}
+
+/// Results for `matches`, character ranges of `text` with their scores,
+/// the text of `chars` on `page`.
+pub(super) fn page_results(
+    page: &PdfPage,
+    page_index: u32,
+    chars: &PdfPageTextChars,
+    text: &PageText,
+    matches: Vec<(Range<usize>, f32)>,
+) -> Vec<SearchResult> {
+    let page_height = page.height().value;
+    matches
+        .into_iter()
+        .map(|(range, score)| {
+            // Line breaks have no glyphs, and their boxes would add stray
+            // rects to a match spanning lines.
+            let rects = range
+                .clone()
+                .filter(|&i| !text.text(i..i + 1).contains(['\r', '\n']))
+                .filter_map(|i| chars.get(i).ok()?.tight_bounds().ok())
+                .map(|rect| TextRect {
+                    x: rect.left().value,
+                    y: page_height - rect.top().value,
+                    width: rect.width().value,
+                    height: rect.height().value,
+                })
+                .collect();
+            let (context_before, context_after) = text.context(&range);
+            SearchResult {
+                page: page_index,
+                start_index: range.start,
+                end_index: range.end,
+                text: text.text(range).to_string(),
+                context_before,
+                context_after,
+                score,
+                rects: merge_text_rects(rects),
+            }
+        })
+        .collect()
+}
+
+/// Whether results are ranked by score, which takes searching every page
+/// before `max_results` applies.
+pub(super) fn is_ranked(options: &SearchOptions) -> bool {
+    options.mode == SearchMode::Fuzzy
+}
+
+/// A match of a ranked search. Orders worse matches greater: lower score,
+/// then later in the document.
+struct RankedMatch {
+    score: f32,
+    page_index: u32,
+    range: Range<usize>,
+}
+
+impl RankedMatch {
+    fn key(&self) -> (u32, usize) {
+        (self.page_index, self.range.start)
+    }
+}
+
+impl Ord for RankedMatch {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.score.total_cmp(&self.score).then_with(|| self.key().cmp(&other.key()))
+    }
+}
+
+impl PartialOrd for RankedMatch {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for RankedMatch {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for RankedMatch {}
+
+/// Character ranges of matches on one page, with their scores.
+type PageMatches = Vec<(Range<usize>, f32)>;
+
+/// The best `max_results` matches of a ranked search, kept while pages are
+/// searched, so that rects and context are built only for the matches that
+/// are returned.
+pub(super) struct TopMatches {
+    max_results: usize,
+    /// Worst kept match on top
+    heap: BinaryHeap<RankedMatch>,
+}
+
+impl TopMatches {
+    pub(super) fn new(max_results: usize) -> Self {
+        TopMatches { max_results, heap: BinaryHeap::new() }
+    }
+
+    pub(super) fn add(&mut self, page_index: u32, matches: PageMatches) {
+        for (range, score) in matches {
+            self.heap.push(RankedMatch { score, page_index, range });
+            if self.heap.len() > self.max_results {
+                self.heap.pop();
+            }
+        }
+    }
+
+    /// The kept matches of each page, pages and matches in page order.
+    pub(super) fn into_pages(self) -> Vec<(u32, PageMatches)> {
+        let mut pages: BTreeMap<u32, PageMatches> = BTreeMap::new();
+        for m in self.heap.into_sorted_vec() {
+            pages.entry(m.page_index).or_default().push((m.range, m.score));
+        }
+        for matches in pages.values_mut() {
+            matches.sort_by_key(|(range, _)| range.start);
+        }
+        pages.into_iter().collect()
+    }
+}
+
+/// Put ranked results best first, then in page order, and keep the first
+/// `max_results`. Unranked results are left as they are.
+pub(super) fn rank_results(
+    mut results: Vec<SearchResult>,
+    options: &SearchOptions,
+    max_results: usize,
+) -> Vec<SearchResult> {
+    if is_ranked(options) {
+        results.sort_by(|a, b| b.score.total_cmp(&a.score));
+        results.truncate(max_results);
+    }
+    results
+}
+
+/// Search the pages of `document` selected by `options`, in page order,
+/// stopping after `max_results` matches; ranked results are sorted by score
+/// instead. Pages whose text can't be read are skipped.
+pub(super) fn search_document(
+    document: &PdfDocument,
+    query: &str,
+    options: &SearchOptions,
+    max_results: usize,
+) -> Result<Vec<SearchResult>, PdfError> {
+    let query = CompiledQuery::new(query, options)?;
+    let page_indices = options.page_indices(document.pages().len() as u32);
+    if is_ranked(options) {
+        return Ok(search_document_ranked(document, &query, page_indices, options, max_results));
+    }
+    let mut results = Vec::new();
+    for page_index in page_indices {
+        if results.len() >= max_results {
+            break;
+        }
+        let Ok(page) = document.pages().get(page_index as u16) else {
+            continue;
+        };
+        if let Ok(found) = search_page(&page, page_index, &query, max_results - results.len()) {
+            results.extend(found);
+        }
+    }
+    Ok(results)
+}
+
+/// `search_document` for ranked results: every page is matched, then only
+/// the pages holding the best `max_results` matches are read again to
+/// build their results.
+fn search_document_ranked(
+    document: &PdfDocument,
+    query: &CompiledQuery,
+    page_indices: Vec<u32>,
+    options: &SearchOptions,
+    max_results: usize,
+) -> Vec<SearchResult> {
+    let mut top = TopMatches::new(max_results);
+    for page_index in page_indices {
+        let Ok(page) = document.pages().get(page_index as u16) else {
+            continue;
+        };
+        let Ok(text_page) = page.text() else {
+            continue;
+        };
+        let text = PageText::new(text_page.chars().iter().map(|c| c.unicode_string()), query.folding);
+        top.add(page_index, query.matches(&text));
+    }
+
+    let mut results = Vec::new();
+    for (page_index, matches) in top.into_pages() {
+        let Ok(page) = document.pages().get(page_index as u16) else {
+            continue;
+        };
+        let Ok(text_page) = page.text() else {
+            continue;
+        };
+        let chars = text_page.chars();
+        let text = PageText::new(chars.iter().map(|c| c.unicode_string()), query.folding);
+        results.extend(page_results(&page, page_index, &chars, &text, matches));
+    }
+    rank_results(results, options, max_results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn folding(case_sensitive: bool, normalization: SearchNormalization) -> Folding {
+        Folding::new(&SearchOptions { case_sensitive, normalization, ..SearchOptions::default() })
+    }
+
+    fn page_text(chars: &[&str], folding: Folding) -> PageText {
+        PageText::new(chars.iter().map(|c| (!c.is_empty()).then(|| c.to_string())), folding)
+    }
+
+    #[test]
+    fn test_matches_are_char_ranges() {
+        let fold = |text: &str, case_sensitive| folding(case_sensitive, SearchNormalization::default()).fold(text);
+        let text = page_text(
+            &["Ä", "", "S", "T", "R", "A", "ß", "E", " ", "日", "本"],
+            folding(false, SearchNormalization::default()),
+        );
+
+        let query = fold("straße", false);
+        let found: Vec<_> = text.find(&query).collect();
+        assert_eq!(found, vec![2..8]);
+        assert_eq!(text.text(2..8), "STRAßE");
+
+        assert_eq!(text.find(&fold("ss", false)).collect::<Vec<_>>(), vec![6..7]);
+        assert_eq!(text.find(&fold("äs", false)).collect::<Vec<_>>(), vec![0..3]);
+        assert_eq!(text.find("本").collect::<Vec<_>>(), vec![10..11]);
+        assert_eq!(text.find("").count(), 0);
+
+        let exact = page_text(&["S", "t", "r", "a", "ß", "e"], folding(true, SearchNormalization::default()));
+        assert_eq!(exact.find(&fold("strasse", true)).count(), 0);
+        assert_eq!(exact.find(&fold("ß", true)).collect::<Vec<_>>(), vec![4..5]);
+
+        let lines: Vec<String> = "first line\r\nthe  match\r\nlast".chars().map(String::from).collect();
+        let text = PageText::new(lines.into_iter().map(Some), folding(false, SearchNormalization::default()));
+        assert_eq!(text.context(&(17..22)), ("first line the ".to_string(), " last".to_string()));
+        assert_eq!(text.context(&(0..5)), (String::new(), " line the match last".to_string()));
+    }
+
+    #[test]
+    fn test_normalization() {
+        let all = SearchNormalization {
+            compatibility: true,
+            ignore_diacritics: true,
+            expand_ligatures: true,
+            join_lines: false,
+        };
+        let normalized = folding(false, all);
+        let chars = ["R", "é", "s", "u", "m", "e\u{301}", " ", "\u{FB01}", "l", "e", " ", "Ｐ", "Ｄ", "Ｆ"];
+        let text = page_text(&chars, normalized);
+        let find = |query: &str| text.find(&normalized.fold(query)).collect::<Vec<_>>();
+
+        assert_eq!(find("resume"), vec![0..6]);
+        assert_eq!(find("RÉSUMÉ"), vec![0..6]);
+        assert_eq!(find("file"), vec![7..10]);
+        assert_eq!(find("ile"), vec![7..10]);
+        assert_eq!(find("pdf"), vec![11..14]);
+        assert_eq!(text.text(7..10), "\u{FB01}le");
+
+        let ligatures = SearchNormalization { expand_ligatures: true, ..SearchNormalization::default() };
+        assert_eq!(folding(true, ligatures).fold("\u{FB03}x Ｐé"), "ffix Ｐé");
+        let plain = folding(false, SearchNormalization::default());
+        assert_eq!(page_text(&chars, plain).find(&plain.fold("resume")).count(), 0);
+    }
+
+    #[test]
+    fn test_join_lines() {
+        let join = SearchNormalization { join_lines: true, ..SearchNormalization::default() };
+        let chars: Vec<String> = "An inter-\r\nnational force \r\nmajeure; Jean-\r\nPaul, long\u{AD}\r\nterm"
+            .chars()
+            .map(String::from)
+            .collect();
+        let text = PageText::new(chars.iter().map(Some), folding(false, join));
+        let find = |query: &str| text.find(query).collect::<Vec<_>>();
+
+        assert_eq!(text.folded(), "an international force majeure; jean-paul, longterm");
+        assert_eq!(find("international"), vec![3..19]);
+        assert_eq!(text.text(3..19), "inter-\r\nnational");
+        assert_eq!(find("force majeure"), vec![20..35]);
+        assert_eq!(find("jean-paul"), vec![37..48]);
+        assert_eq!(find("longterm"), vec![50..61]);
+
+        let plain = PageText::new(chars.iter().map(Some), folding(false, SearchNormalization::default()));
+        assert_eq!(plain.find("international").count(), 0);
+        assert_eq!(plain.find("force majeure").count(), 0);
+    }
+
+    #[test]
+    fn test_fuzzy() {
+        let chars: Vec<String> = "The indemnlficatlon clause; indemnification. Indemnity"
+            .chars()
+            .map(String::from)
+            .collect();
+        let text = PageText::new(chars.into_iter().map(Some), folding(false, SearchNormalization::default()));
+        let run = |query: &str, max_edits: Option<u32>| {
+            let options = SearchOptions { mode: SearchMode::Fuzzy, max_edits, ..SearchOptions::default() };
+            CompiledQuery::new(query, &options).unwrap().matches(&text)
+        };
+
+        let found = run("Indemnification", None);
+        let ranges: Vec<_> = found.iter().map(|(range, _)| range.clone()).collect();
+        assert_eq!(ranges, vec![4..19, 28..43]);
+        assert!(found[1].1 == 1.0 && (found[0].1 - (1.0 - 2.0 / 15.0)).abs() < 1e-6);
+        assert_eq!(run("indemnification", Some(1)).len(), 1);
+        assert_eq!(run("indemnify", Some(2)).len(), 3);
+        assert!(run("", None).is_empty());
+        assert_eq!(run("x", Some(3)), Vec::new());
+        // Short queries can't match single letters.
+        assert_eq!(run("th", Some(1)), vec![(0..2, 1.0)]);
+        assert_eq!(run("thx", None), Vec::new());
+
+        let mut top = TopMatches::new(3);
+        top.add(0, vec![(0..3, 0.5), (5..8, 1.0)]);
+        top.add(1, vec![(0..3, 0.8), (4..7, 0.5)]);
+        top.add(2, vec![(0..3, 0.2)]);
+        assert_eq!(top.into_pages(), vec![(0, vec![(0..3, 0.5), (5..8, 1.0)]), (1, vec![(0..3, 0.8)])]);
+    }
+
+    #[test]
+    fn test_whole_word() {
+        let chars: Vec<String> = "cat concat cat's cats".chars().map(String::from).collect();
+        let text = PageText::new(chars.into_iter().map(Some), folding(false, SearchNormalization::default()));
+        let run = |query: &str, mode: SearchMode, whole_word: bool| {
+            let options = SearchOptions { mode, whole_word, ..SearchOptions::default() };
+            let matches = CompiledQuery::new(query, &options).unwrap().matches(&text);
+            matches.into_iter().map(|(range, _)| range).collect::<Vec<_>>()
+        };
+
+        assert_eq!(run("cat", SearchMode::Text, false).len(), 4);
+        assert_eq!(run("cat", SearchMode::Text, true), vec![0..3, 11..14]);
+        assert_eq!(run("cat conc", SearchMode::Text, true), Vec::<Range<usize>>::new());
+        assert_eq!(run("c.t", SearchMode::Regex, true), vec![0..3, 11..14]);
+        assert_eq!(run("cats OR concat", SearchMode::Query, true), vec![4..10, 17..21]);
+        assert_eq!(run("cats concat", SearchMode::Query, true), vec![4..10, 17..21]);
+        assert_eq!(run("cats conca", SearchMode::Query, true), Vec::<Range<usize>>::new());
+    }
+
+    #[test]
+    fn test_query_modes() {
+        let chars: Vec<String> = "See 12.3(a) and 4.5(b); Force Majeure applies."
+            .chars()
+            .map(String::from)
+            .collect();
+        let text = PageText::new(chars.into_iter().map(Some), folding(false, SearchNormalization::default()));
+        let run = |query: &str, mode: SearchMode| {
+            let options = SearchOptions { mode, ..SearchOptions::default() };
+            let matches = CompiledQuery::new(query, &options).unwrap().matches(&text);
+            matches.into_iter().map(|(range, _)| range).collect::<Vec<_>>()
+        };
+
+        assert_eq!(run(r"\d+\.\d+\(a\)", SearchMode::Regex), vec![4..11]);
+        assert_eq!(run(r"\d+\.\d+\([a-z]\)", SearchMode::Regex).len(), 2);
+        assert_eq!(run(r"\bFORCE\b", SearchMode::Regex), vec![24..29]);
+        assert_eq!(run("\"force majeure\" applies", SearchMode::Query), vec![24..37, 38..45]);
+        assert_eq!(run("force AND lapses", SearchMode::Query), Vec::<Range<usize>>::new());
+        assert_eq!(run("lapses OR see", SearchMode::Query), vec![0..3]);
+
+        let invalid = SearchOptions { mode: SearchMode::Regex, ..SearchOptions::default() };
+        assert!(matches!(CompiledQuery::new("(", &invalid), Err(PdfError::InvalidQuery(_))));
+        assert_eq!(
+            parse_terms(r#"a "b c" OR d AND"#).unwrap(),
+            vec![vec!["a".to_string(), "b c".to_string()], vec!["d".to_string()]]
+        );
+        assert!(parse_terms(" OR \"\" ").is_err());
+    }
+}
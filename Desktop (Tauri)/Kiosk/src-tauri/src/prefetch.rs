@@ -0,0 +1,256 @@
+//! Background prefetch of pages around the viewport.
+//!
+//! The frontend reports what it is showing (visible pages, scroll direction
+//! and render scale) through `set_viewport_hint`. A single worker thread then
+//! renders the neighbouring pages at that scale into the document's
+//! `RenderCache`, so the next `render_page` for them is a cache hit.
+//!
+//! Prefetching must never delay what the user is looking at. Foreground
+//! renders mark themselves on the document's `ForegroundRenders` counter; the
+//! worker waits while that counter is non-zero and only ever `try_lock`s the
+//! `CachedPdf`, backing off instead of queueing on the mutex. A foreground
+//! render can therefore wait for at most the one prefetch page already inside
+//! pdfium. A newer hint supersedes the current job between pages.
+
+use crate::commands::{prefetch_page_cached, AppState};
+use crate::pdf::RenderOptions;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Manager, Runtime};
+
+/// Pages prefetched on each side of the visible range by default.
+pub const DEFAULT_PREFETCH_PAGES: u32 = 2;
+
+/// Upper bound on pages prefetched per side, whatever the frontend asks for.
+pub const MAX_PREFETCH_PAGES: u32 = 8;
+
+/// How long the worker sleeps before retrying when the document is busy.
+pub const PREFETCH_BACKOFF: Duration = Duration::from_millis(10);
+
+/// Scroll direction reported with a viewport hint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScrollDirection {
+    /// Towards higher page indices
+    Forward,
+    /// Towards lower page indices
+    Backward,
+    /// Not scrolling (zoom, jump, idle)
+    None,
+}
+
+/// What the frontend is currently showing for one document.
+#[derive(Debug, Clone)]
+pub struct ViewportHint {
+    pub doc_id: String,
+    /// 0-based indices of the visible pages
+    pub visible_pages: Vec<u32>,
+    pub direction: ScrollDirection,
+    /// Render scale the viewer will request for these pages
+    pub scale: f32,
+    /// Render options the viewer will request for these pages
+    pub options: RenderOptions,
+    /// Pages to prefetch on each side of the visible range
+    pub pages_ahead: u32,
+    /// Page count of the document, used to clamp the prefetch range
+    pub page_count: u32,
+}
+
+/// Count of foreground renders in flight for one document.
+///
+/// Foreground render paths hold a `ForegroundGuard` while they render; the
+/// prefetch worker does not start a page while any guard is alive.
+#[derive(Debug, Default)]
+pub struct ForegroundRenders(AtomicUsize);
+
+impl ForegroundRenders {
+    pub fn begin(&self) -> ForegroundGuard<'_> {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        ForegroundGuard(self)
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.0.load(Ordering::SeqCst) > 0
+    }
+}
+
+/// Marks a foreground render as in flight until dropped.
+pub struct ForegroundGuard<'a>(&'a ForegroundRenders);
+
+impl Drop for ForegroundGuard<'_> {
+    fn drop(&mut self) {
+        self.0 .0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Latest viewport hint plus the wake-up signal for the worker thread.
+pub struct Prefetcher {
+    pending: Mutex<Option<ViewportHint>>,
+    wake: Condvar,
+    /// Bumped on every hint; a job is superseded once this moves past it
+    epoch: AtomicU64,
+}
+
+impl Prefetcher {
+    pub fn new() -> Self {
+        Self {
+            pending: Mutex::new(None),
+            wake: Condvar::new(),
+            epoch: AtomicU64::new(0),
+        }
+    }
+
+    /// Replace any pending or running prefetch job with one for `hint`.
+    pub fn submit(&self, hint: ViewportHint) {
+        let mut pending = self.pending.lock().unwrap();
+        *pending = Some(hint);
+        self.epoch.fetch_add(1, Ordering::SeqCst);
+        self.wake.notify_one();
+    }
+
+    /// Block until a hint is available, returning it with its epoch.
+    fn next_job(&self) -> (ViewportHint, u64) {
+        let mut pending = self.pending.lock().unwrap();
+        loop {
+            if let Some(hint) = pending.take() {
+                return (hint, self.epoch.load(Ordering::SeqCst));
+            }
+            pending = self.wake.wait(pending).unwrap();
+        }
+    }
+
+    fn is_superseded(&self, epoch: u64) -> bool {
+        self.epoch.load(Ordering::SeqCst) != epoch
+    }
+}
+
+impl Default for Prefetcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Order in which to prefetch the pages around `visible_pages`.
+///
+/// Visible pages themselves are left to the foreground. Pages in the scroll
+/// direction come first, nearest first; without a direction both sides are
+/// interleaved by distance.
+pub fn prefetch_order(
+    visible_pages: &[u32],
+    direction: ScrollDirection,
+    page_count: u32,
+    pages_ahead: u32,
+) -> Vec<u32> {
+    let visible: Vec<u32> = visible_pages
+        .iter()
+        .copied()
+        .filter(|&p| p < page_count)
+        .collect();
+    let (first, last) = match (visible.iter().min(), visible.iter().max()) {
+        (Some(&first), Some(&last)) => (first, last),
+        _ => return Vec::new(),
+    };
+
+    let after: Vec<u32> = (1..=pages_ahead)
+        .map(|d| last + d)
+        .take_while(|&p| p < page_count)
+        .collect();
+    let before: Vec<u32> = (1..=pages_ahead.min(first))
+        .map(|d| first - d)
+        .collect();
+
+    match direction {
+        ScrollDirection::Forward => after.into_iter().chain(before).collect(),
+        ScrollDirection::Backward => before.into_iter().chain(after).collect(),
+        ScrollDirection::None => {
+            let mut order = Vec::with_capacity(after.len() + before.len());
+            for i in 0..after.len().max(before.len()) {
+                order.extend(after.get(i));
+                order.extend(before.get(i));
+            }
+            order
+        }
+    }
+}
+
+/// Start the prefetch worker thread. Called once from app setup.
+pub fn spawn_worker<R: Runtime>(app: AppHandle<R>) {
+    let spawned = std::thread::Builder::new()
+        .name("kiosk-prefetch".into())
+        .spawn(move || {
+            let state = app.state::<AppState>();
+            loop {
+                let (hint, epoch) = state.prefetcher.next_job();
+                run_job(&state, &hint, epoch);
+            }
+        });
+
+    if let Err(e) = spawned {
+        eprintln!("[Kiosk Prefetch] Failed to start worker: {}", e);
+    }
+}
+
+/// Prefetch the pages for one hint until done or superseded.
+fn run_job(state: &AppState, hint: &ViewportHint, epoch: u64) {
+    let superseded = || state.prefetcher.is_superseded(epoch);
+    let order = prefetch_order(
+        &hint.visible_pages,
+        hint.direction,
+        hint.page_count,
+        hint.pages_ahead,
+    );
+
+    for page_index in order {
+        if superseded() {
+            return;
+        }
+        match prefetch_page_cached(
+            state,
+            &hint.doc_id,
+            page_index,
+            hint.scale,
+            &hint.options,
+            &superseded,
+        ) {
+            Ok(true) => {
+                eprintln!(
+                    "[Kiosk Prefetch] {} page={} scale={:.2}",
+                    hint.doc_id, page_index, hint.scale
+                );
+            }
+            Ok(false) => {}
+            Err(e) => {
+                // Typically the document was closed; drop the rest of the job.
+                eprintln!("[Kiosk Prefetch] {} page={}: {}", hint.doc_id, page_index, e);
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prefetch_order() {
+        // Scrolling forward: pages ahead first, then behind.
+        assert_eq!(
+            prefetch_order(&[4, 5], ScrollDirection::Forward, 10, 2),
+            vec![6, 7, 3, 2]
+        );
+        // Scrolling backward near the start: clamped at page 0.
+        assert_eq!(
+            prefetch_order(&[1], ScrollDirection::Backward, 10, 2),
+            vec![0, 2, 3]
+        );
+        // Idle near the end: interleaved and clamped at page_count.
+        assert_eq!(
+            prefetch_order(&[8], ScrollDirection::None, 10, 2),
+            vec![9, 7, 6]
+        );
+        assert!(prefetch_order(&[], ScrollDirection::None, 10, 2).is_empty());
+    }
+}
@@ -0,0 +1,203 @@
+//! Progressive rendering of heavy pages.
+//!
+//! `start_progressive_render` (see `commands`) draws a page in time slices
+//! on the blocking thread pool (see `CachedPdf::begin_progressive_render`)
+//! instead of in one pdfium call, so a page that takes seconds to rasterize
+//! shows up piece by piece:
+//!
+//! - every `PROGRESSIVE_FRAME_INTERVAL` the bitmap drawn so far is published
+//!   as the render's current frame and a `progressive-render` event
+//!   (`ProgressiveUpdate`) with status `rendering` is emitted;
+//! - the frontend fetches the frame with `get_progressive_frame`, either on
+//!   the event or by polling;
+//! - the final frame is the full-quality render, identical to what
+//!   `render_page` returns, and is inserted into the render cache.
+//!
+//! A render can be paused between slices (the document stays free for other
+//! renders meanwhile), resumed and cancelled. Closing the document cancels
+//! its renders.
+
+use crate::render_cache::CachedRender;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+/// Event emitted when a progressive render publishes a frame or ends.
+pub const PROGRESSIVE_RENDER_EVENT: &str = "progressive-render";
+
+/// Time pdfium draws for between checks for pause and cancellation.
+pub const PROGRESSIVE_SLICE: Duration = Duration::from_millis(100);
+
+/// Minimum time between two published intermediate frames.
+pub const PROGRESSIVE_FRAME_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Stage of a progressive render, as reported in `ProgressiveUpdate`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProgressiveStatus {
+    /// An intermediate frame is available
+    Rendering,
+    /// Paused; the frame drawn so far is available
+    Paused,
+    /// The final, full-quality frame is available
+    Complete,
+    Cancelled,
+    /// See `ProgressiveUpdate.error`
+    Failed,
+}
+
+/// Payload of `PROGRESSIVE_RENDER_EVENT`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProgressiveUpdate {
+    pub render_id: u64,
+    pub doc_id: String,
+    pub page_index: u32,
+    /// Size of the current frame; 0 when none was published
+    pub width: u32,
+    pub height: u32,
+    pub status: ProgressiveStatus,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RunState {
+    Running,
+    Paused,
+    Cancelled,
+}
+
+/// One progressive render: its run state and latest frame.
+pub struct ProgressiveRender {
+    pub doc_id: String,
+    pub page_index: u32,
+    state: Mutex<RunState>,
+    resumed: Condvar,
+    /// Latest published frame, and whether it is the final one
+    frame: Mutex<Option<(CachedRender, bool)>>,
+}
+
+impl ProgressiveRender {
+    fn new(doc_id: &str, page_index: u32) -> Self {
+        Self {
+            doc_id: doc_id.to_string(),
+            page_index,
+            state: Mutex::new(RunState::Running),
+            resumed: Condvar::new(),
+            frame: Mutex::new(None),
+        }
+    }
+
+    /// Stop after the current slice. No effect once cancelled.
+    pub fn pause(&self) {
+        let mut state = self.state.lock().unwrap();
+        if *state == RunState::Running {
+            *state = RunState::Paused;
+        }
+    }
+
+    pub fn resume(&self) {
+        let mut state = self.state.lock().unwrap();
+        if *state == RunState::Paused {
+            *state = RunState::Running;
+            self.resumed.notify_all();
+        }
+    }
+
+    pub fn cancel(&self) {
+        *self.state.lock().unwrap() = RunState::Cancelled;
+        self.resumed.notify_all();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        *self.state.lock().unwrap() == RunState::Paused
+    }
+
+    /// Block while paused. Returns `false` if the render was cancelled.
+    pub fn wait_while_paused(&self) -> bool {
+        let state = self
+            .resumed
+            .wait_while(self.state.lock().unwrap(), |s| *s == RunState::Paused)
+            .unwrap();
+        *state != RunState::Cancelled
+    }
+
+    pub fn set_frame(&self, frame: CachedRender, complete: bool) {
+        *self.frame.lock().unwrap() = Some((frame, complete));
+    }
+
+    /// Latest published frame, and whether it is the final one.
+    pub fn frame(&self) -> Option<(CachedRender, bool)> {
+        self.frame.lock().unwrap().clone()
+    }
+}
+
+/// Progressive renders in flight or awaiting retrieval of their final frame.
+#[derive(Default)]
+pub struct ProgressiveRenders {
+    renders: Mutex<HashMap<u64, Arc<ProgressiveRender>>>,
+    next_id: AtomicU64,
+}
+
+impl ProgressiveRenders {
+    /// Register a new render of `page_index` and return its id.
+    pub fn start(&self, doc_id: &str, page_index: u32) -> (u64, Arc<ProgressiveRender>) {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let render = Arc::new(ProgressiveRender::new(doc_id, page_index));
+        self.renders.lock().unwrap().insert(id, Arc::clone(&render));
+        (id, render)
+    }
+
+    pub fn get(&self, id: u64) -> Option<Arc<ProgressiveRender>> {
+        self.renders.lock().unwrap().get(&id).cloned()
+    }
+
+    pub fn remove(&self, id: u64) -> Option<Arc<ProgressiveRender>> {
+        self.renders.lock().unwrap().remove(&id)
+    }
+
+    /// Cancel and forget every render of `doc_id`.
+    pub fn cancel_document(&self, doc_id: &str) {
+        self.renders.lock().unwrap().retain(|_, render| {
+            if render.doc_id == doc_id {
+                render.cancel();
+            }
+            render.doc_id != doc_id
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pause_resume_cancel() {
+        let renders = ProgressiveRenders::default();
+        let (id, render) = renders.start("doc-1", 0);
+        assert!(render.wait_while_paused());
+
+        render.pause();
+        assert!(render.is_paused());
+        let waiter = {
+            let render = Arc::clone(&render);
+            std::thread::spawn(move || render.wait_while_paused())
+        };
+        render.resume();
+        assert!(waiter.join().unwrap());
+
+        render.pause();
+        let waiter = {
+            let render = Arc::clone(&render);
+            std::thread::spawn(move || render.wait_while_paused())
+        };
+        renders.cancel_document("doc-1");
+        assert!(!waiter.join().unwrap());
+        assert!(renders.get(id).is_none());
+
+        render.resume();
+        assert!(!render.is_paused());
+        assert!(!render.wait_while_paused());
+    }
+}
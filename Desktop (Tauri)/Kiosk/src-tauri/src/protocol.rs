@@ -0,0 +1,287 @@
+//! `kiosk://` custom URI scheme serving rendered page images.
+//!
+//! URL shape:
+//!
+//! ```text
+//! kiosk://localhost/render/{doc_id}/{page}?scale=2&format=webp
+//! kiosk://render/{doc_id}/{page}?scale=2&format=png
+//! kiosk://render/{doc_id}/{page}?color=inverted&preserve_images=1
+//! kiosk://render/{doc_id}/{page}?color=custom&paper=f4ecd8&ink=3b2f2f
+//! kiosk://render/{doc_id}/{page}?annotations=0&transparent=1&format=png
+//! kiosk://render/{doc_id}/{page}?rotation=90
+//! kiosk://render/{doc_id}/{page}?layer=annotations&format=png
+//! ```
+//!
+//! (On Windows the WebView reaches the same handler as
+//! `http://kiosk.localhost/render/...`.)
+//!
+//! The document is resolved through `AppState.documents` exactly like the IPC
+//! commands, and rendered through the same render cache as `render_page`
+//! (backed by `CachedPdf::render_page_to_rgba`). Serving pages
+//! over a protocol lets the WebView use plain `<img>`/`fetch` with HTTP
+//! revalidation and skips IPC serialization entirely.
+//!
+//! Responses carry the image size in `X-Kiosk-Width`/`X-Kiosk-Height` and
+//! the scale actually rendered in `X-Kiosk-Scale`, which is below `scale`
+//! when the page was downscaled to fit the pixel limit.
+//!
+//! Supported formats: `rgba` (the raw frame used by `render_page_binary`),
+//! `png` (default), `webp` (lossless), `jpeg` and `tiff`.
+//!
+//! Color modes (see `ColorMode`): `normal` (default), `inverted` (with
+//! optional `preserve_images=1`), `sepia`, and `custom` with `paper` and
+//! `ink` given as hex RGB.
+//!
+//! The other `RenderOptions` map to `annotations`, `forms`, `aa_text`,
+//! `aa_paths`, `aa_images`, `transparent` and `grayscale` (`0`/`1`),
+//! `background` (hex RGB), `rotation` (degrees clockwise, added to the
+//! document's view rotation) and `layer` (`full`, `content` or
+//! `annotations`; see `RenderLayer`).
+
+use crate::commands::{render_page_cached, AppState, RenderPriority};
+use crate::pdf::{
+    encode_rgba, ColorMode, PageImageFormat, RenderLayer, RenderOptions, ViewRotation,
+};
+use std::time::Instant;
+use tauri::http::{header, Request, Response, StatusCode};
+use tauri::{Manager, Runtime, UriSchemeContext, UriSchemeResponder};
+
+/// URI scheme name registered with the WebView.
+pub const SCHEME: &str = "kiosk";
+
+/// A parsed `kiosk://render/...` request.
+struct RenderRequest {
+    doc_id: String,
+    page_index: u32,
+    scale: f32,
+    format: PageImageFormat,
+    options: RenderOptions,
+}
+
+/// Entry point registered via `register_asynchronous_uri_scheme_protocol`.
+///
+/// Rendering can take a while, so the work runs on the blocking thread pool
+/// and the WebView is answered through `responder` when it finishes.
+pub fn handle_request<R: Runtime>(
+    ctx: UriSchemeContext<'_, R>,
+    request: Request<Vec<u8>>,
+    responder: UriSchemeResponder,
+) {
+    let app = ctx.app_handle().clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        responder.respond(serve(&app, &request));
+    });
+}
+
+/// Resolve and render a single request into an HTTP response.
+fn serve<R: Runtime>(app: &tauri::AppHandle<R>, request: &Request<Vec<u8>>) -> Response<Vec<u8>> {
+    let parsed = match parse_request(request) {
+        Ok(p) => p,
+        Err(message) => return error_response(StatusCode::BAD_REQUEST, &message),
+    };
+
+    let state = app.state::<AppState>();
+    let generation = {
+        let docs = state.documents.lock().unwrap();
+        match docs.get(&parsed.doc_id) {
+            Some(doc_state) => doc_state.render_cache.lock().unwrap().generation(),
+            None => return error_response(StatusCode::NOT_FOUND, "Document not found"),
+        }
+    };
+
+    // Within one render cache generation a document's pixels never change,
+    // so the request parameters plus the generation identify the body.
+    let etag = format!(
+        "\"{}-{}-{}-{}-{:?}-{:x}\"",
+        parsed.doc_id,
+        generation,
+        parsed.page_index,
+        parsed.scale,
+        parsed.format,
+        parsed.options.fingerprint()
+    );
+    let not_modified = request
+        .headers()
+        .get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v == etag)
+        .unwrap_or(false);
+
+    if not_modified {
+        return base_response(StatusCode::NOT_MODIFIED, &etag)
+            .body(Vec::new())
+            .unwrap();
+    }
+
+    let render_start = Instant::now();
+    let rendered = render_page_cached(
+        &parsed.doc_id,
+        parsed.page_index,
+        parsed.scale,
+        &parsed.options,
+        RenderPriority::Normal,
+        None,
+        &state,
+    );
+    let (pixels, width, height, scale) = match rendered {
+        Ok(r) => (r.pixels, r.width, r.height, r.scale),
+        Err(e) => return error_response(StatusCode::INTERNAL_SERVER_ERROR, &e),
+    };
+
+    let body = match encode_rgba(&pixels, width, height, parsed.format) {
+        Ok(b) => b,
+        Err(e) => return error_response(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+    };
+
+    eprintln!(
+        "[Kiosk Protocol] {} page={} {}x{} {:?} {}B in {:?}",
+        parsed.doc_id,
+        parsed.page_index,
+        width,
+        height,
+        parsed.format,
+        body.len(),
+        render_start.elapsed()
+    );
+
+    base_response(StatusCode::OK, &etag)
+        .header(header::CONTENT_TYPE, parsed.format.mime_type())
+        .header("X-Kiosk-Width", width)
+        .header("X-Kiosk-Height", height)
+        .header("X-Kiosk-Scale", scale.to_string())
+        .body(body)
+        .unwrap()
+}
+
+/// Parse `render/{doc_id}/{page}` plus the `scale`/`format`/options query.
+///
+/// The `render` segment may arrive either as the URI host
+/// (`kiosk://render/...`) or as the first path segment
+/// (`kiosk://localhost/render/...`, `http://kiosk.localhost/render/...`).
+fn parse_request(request: &Request<Vec<u8>>) -> Result<RenderRequest, String> {
+    let uri = request.uri();
+
+    let mut segments: Vec<String> = Vec::new();
+    if let Some(host) = uri.host() {
+        if host != "localhost" && !host.ends_with(".localhost") {
+            segments.push(host.to_string());
+        }
+    }
+    for segment in uri.path().split('/').filter(|s| !s.is_empty()) {
+        let decoded = urlencoding::decode(segment)
+            .map_err(|e| format!("Invalid path segment: {}", e))?;
+        segments.push(decoded.into_owned());
+    }
+
+    let (doc_id, page) = match segments.as_slice() {
+        [route, doc_id, page] if route == "render" => (doc_id.clone(), page),
+        _ => return Err(format!("Unknown route: {}", uri.path())),
+    };
+    let page_index = page
+        .parse::<u32>()
+        .map_err(|_| format!("Invalid page index: {}", page))?;
+
+    let mut scale = 1.0f32;
+    let mut format = PageImageFormat::Png;
+    let mut options = RenderOptions::default();
+    let mut color_name = String::from("normal");
+    let mut preserve_images = false;
+    let mut paper = [255, 255, 255];
+    let mut ink = [0, 0, 0];
+    for (key, value) in url::form_urlencoded::parse(uri.query().unwrap_or("").as_bytes()) {
+        match key.as_ref() {
+            "scale" => {
+                scale = value
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|s| s.is_finite() && *s > 0.0)
+                    .ok_or_else(|| format!("Invalid scale: {}", value))?;
+            }
+            "format" => {
+                format = PageImageFormat::from_name(&value)
+                    .ok_or_else(|| format!("Unsupported format: {}", value))?;
+            }
+            "color" => color_name = value.into_owned(),
+            "preserve_images" => preserve_images = parse_flag(&key, &value)?,
+            "paper" => paper = parse_hex_rgb(&value)?,
+            "ink" => ink = parse_hex_rgb(&value)?,
+            "annotations" => options.annotations = parse_flag(&key, &value)?,
+            "forms" => options.forms = parse_flag(&key, &value)?,
+            "aa_text" => options.antialias_text = parse_flag(&key, &value)?,
+            "aa_paths" => options.antialias_paths = parse_flag(&key, &value)?,
+            "aa_images" => options.antialias_images = parse_flag(&key, &value)?,
+            "transparent" => options.transparent = parse_flag(&key, &value)?,
+            "grayscale" => options.grayscale = parse_flag(&key, &value)?,
+            "background" => options.background = parse_hex_rgb(&value)?,
+            "rotation" => {
+                options.rotation = value
+                    .parse::<i32>()
+                    .map_err(|_| format!("Invalid rotation: {}", value))
+                    .and_then(ViewRotation::try_from)?;
+            }
+            "layer" => {
+                options.layer = match value.as_ref() {
+                    "full" => RenderLayer::Full,
+                    "content" => RenderLayer::Content,
+                    "annotations" => RenderLayer::Annotations,
+                    other => return Err(format!("Unsupported layer: {}", other)),
+                };
+            }
+            _ => {}
+        }
+    }
+
+    options.color = match color_name.as_str() {
+        "normal" => ColorMode::Normal,
+        "inverted" => ColorMode::Inverted { preserve_images },
+        "sepia" => ColorMode::Sepia,
+        "custom" => ColorMode::Custom { paper, ink },
+        other => return Err(format!("Unsupported color mode: {}", other)),
+    };
+
+    Ok(RenderRequest { doc_id, page_index, scale, format, options })
+}
+
+/// Parse a `0`/`1` (or `false`/`true`) query flag.
+fn parse_flag(key: &str, value: &str) -> Result<bool, String> {
+    match value {
+        "1" | "true" => Ok(true),
+        "0" | "false" => Ok(false),
+        _ => Err(format!("Invalid {}: {}", key, value)),
+    }
+}
+
+/// Parse `RRGGBB` (optionally `#`-prefixed) into RGB bytes.
+fn parse_hex_rgb(value: &str) -> Result<[u8; 3], String> {
+    let hex = value.trim_start_matches('#');
+    let channel = |i: usize| {
+        hex.get(i..i + 2)
+            .and_then(|h| u8::from_str_radix(h, 16).ok())
+            .ok_or_else(|| format!("Invalid color: {}", value))
+    };
+    if hex.len() != 6 {
+        return Err(format!("Invalid color: {}", value));
+    }
+    Ok([channel(0)?, channel(2)?, channel(4)?])
+}
+
+/// Response builder with the headers shared by every successful reply.
+fn base_response(status: StatusCode, etag: &str) -> tauri::http::response::Builder {
+    Response::builder()
+        .status(status)
+        .header(header::ETAG, etag)
+        .header(header::CACHE_CONTROL, "no-cache")
+        .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+        .header(header::ACCESS_CONTROL_EXPOSE_HEADERS, "ETag, X-Kiosk-Width, X-Kiosk-Height, X-Kiosk-Scale")
+}
+
+/// Plain-text error response.
+fn error_response(status: StatusCode, message: &str) -> Response<Vec<u8>> {
+    eprintln!("[Kiosk Protocol] {} {}", status, message);
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "text/plain; charset=utf-8")
+        .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+        .body(message.as_bytes().to_vec())
+        .unwrap()
+}
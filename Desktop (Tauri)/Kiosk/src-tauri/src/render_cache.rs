@@ -0,0 +1,298 @@
+//! LRU cache of rendered page bitmaps.
+//!
+//! Each open document owns a `RenderCache` (stored in its `DocumentState`).
+//! All caches draw from one `RenderCacheBudget`, a global byte budget shared
+//! across documents, so scrolling back to a page reuses its bitmap instead of
+//! going through pdfium again while total memory stays bounded.
+//!
+//! Recency is tracked with a global logical clock rather than per-cache
+//! ordering, so the least recently used entry can be found across documents
+//! when the global budget is exceeded (see `AppState::enforce_render_budget`).
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Default global budget for rendered bitmaps across all documents (256 MiB).
+pub const DEFAULT_RENDER_CACHE_BUDGET: usize = 256 * 1024 * 1024;
+
+/// Render scales are quantized to 1/100 so that tiny floating-point
+/// differences (e.g. zoom * devicePixelRatio) still hit the same entry.
+const SCALE_QUANTUM: f32 = 100.0;
+
+/// Cache key: page, quantized scale and a fingerprint of the render options.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RenderCacheKey {
+    pub page_index: u32,
+    scale_key: u32,
+    /// Fingerprint of the render options (0 = default full-page render)
+    pub options_key: u64,
+}
+
+impl RenderCacheKey {
+    pub fn new(page_index: u32, scale: f32, options_key: u64) -> Self {
+        Self {
+            page_index,
+            scale_key: (scale * SCALE_QUANTUM).round().max(1.0) as u32,
+            options_key,
+        }
+    }
+
+    /// The quantized scale this entry is rendered at. Callers must render at
+    /// this scale (not the requested one) so the cached bitmap matches the key.
+    pub fn scale(&self) -> f32 {
+        self.scale_key as f32 / SCALE_QUANTUM
+    }
+}
+
+/// A cached bitmap. Pixels are shared so cache hits never copy the buffer.
+#[derive(Debug, Clone)]
+pub struct CachedRender {
+    /// Tightly packed RGBA pixels (4 bytes per pixel, row-major, top-to-bottom)
+    pub pixels: Arc<Vec<u8>>,
+    pub width: u32,
+    pub height: u32,
+    /// Scale the page was rendered at; below the requested scale when the
+    /// page was downscaled to fit `max_render_pixels`
+    pub scale: f32,
+}
+
+/// Hit/miss and occupancy statistics for one document or all documents.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RenderCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    /// Number of cached bitmaps
+    pub entries: usize,
+    /// Bytes held by cached bitmaps
+    pub bytes: usize,
+    /// Global byte budget shared by all documents
+    pub budget_bytes: usize,
+}
+
+/// Global byte budget and recency clock shared by every `RenderCache`.
+pub struct RenderCacheBudget {
+    max_bytes: AtomicUsize,
+    used_bytes: AtomicUsize,
+    clock: AtomicU64,
+}
+
+impl RenderCacheBudget {
+    pub fn new(max_bytes: usize) -> Self {
+        Self {
+            max_bytes: AtomicUsize::new(max_bytes),
+            used_bytes: AtomicUsize::new(0),
+            clock: AtomicU64::new(0),
+        }
+    }
+
+    pub fn max_bytes(&self) -> usize {
+        self.max_bytes.load(Ordering::Relaxed)
+    }
+
+    pub fn set_max_bytes(&self, max_bytes: usize) {
+        self.max_bytes.store(max_bytes, Ordering::Relaxed);
+    }
+
+    pub fn used_bytes(&self) -> usize {
+        self.used_bytes.load(Ordering::Relaxed)
+    }
+
+    pub fn is_over_budget(&self) -> bool {
+        self.used_bytes() > self.max_bytes()
+    }
+
+    fn tick(&self) -> u64 {
+        self.clock.fetch_add(1, Ordering::Relaxed) + 1
+    }
+}
+
+struct Entry {
+    render: CachedRender,
+    last_used: u64,
+}
+
+/// Per-document LRU of rendered bitmaps.
+pub struct RenderCache {
+    entries: HashMap<RenderCacheKey, Entry>,
+    bytes: usize,
+    hits: u64,
+    misses: u64,
+    /// Bumped whenever the document is modified and the cache invalidated
+    generation: u64,
+    budget: Arc<RenderCacheBudget>,
+}
+
+impl RenderCache {
+    pub fn new(budget: Arc<RenderCacheBudget>) -> Self {
+        Self {
+            entries: HashMap::new(),
+            bytes: 0,
+            hits: 0,
+            misses: 0,
+            generation: 0,
+            budget,
+        }
+    }
+
+    /// Look up a bitmap, marking it most recently used. Counts a hit or miss.
+    pub fn get(&mut self, key: &RenderCacheKey) -> Option<CachedRender> {
+        match self.entries.get_mut(key) {
+            Some(entry) => {
+                entry.last_used = self.budget.tick();
+                self.hits += 1;
+                Some(entry.render.clone())
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// Whether a bitmap is cached, without touching recency or statistics.
+    pub fn contains(&self, key: &RenderCacheKey) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    /// Insert a bitmap rendered from the document as of `generation` (read
+    /// before rendering). Bitmaps from before an invalidation are stale and
+    /// are dropped, as are bitmaps larger than the whole budget.
+    ///
+    /// This only accounts the bytes against the global budget; the caller is
+    /// responsible for trimming afterwards, since eviction may need to touch
+    /// other documents' caches.
+    pub fn insert(&mut self, generation: u64, key: RenderCacheKey, render: CachedRender) {
+        let size = render.pixels.len();
+        if generation != self.generation || size > self.budget.max_bytes() {
+            return;
+        }
+
+        let entry = Entry { render, last_used: self.budget.tick() };
+        if let Some(old) = self.entries.insert(key, entry) {
+            self.release(old.render.pixels.len());
+        }
+        self.bytes += size;
+        self.budget.used_bytes.fetch_add(size, Ordering::Relaxed);
+    }
+
+    /// Recency tick of the least recently used entry, if any.
+    pub fn oldest_tick(&self) -> Option<u64> {
+        self.entries.values().map(|e| e.last_used).min()
+    }
+
+    /// Evict the least recently used entry. Returns false if the cache is empty.
+    pub fn evict_oldest(&mut self) -> bool {
+        let oldest = self
+            .entries
+            .iter()
+            .min_by_key(|(_, e)| e.last_used)
+            .map(|(k, _)| *k);
+
+        match oldest.and_then(|k| self.entries.remove(&k)) {
+            Some(entry) => {
+                self.release(entry.render.pixels.len());
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Drop every entry, returning their bytes to the global budget.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        let bytes = self.bytes;
+        self.release(bytes);
+    }
+
+    /// Drop every entry and bump the generation after the document (or how
+    /// it is rendered) changed.
+    pub fn invalidate(&mut self) {
+        self.clear();
+        self.generation += 1;
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn stats(&self) -> RenderCacheStats {
+        RenderCacheStats {
+            hits: self.hits,
+            misses: self.misses,
+            entries: self.entries.len(),
+            bytes: self.bytes,
+            budget_bytes: self.budget.max_bytes(),
+        }
+    }
+
+    fn release(&mut self, size: usize) {
+        self.bytes -= size;
+        self.budget.used_bytes.fetch_sub(size, Ordering::Relaxed);
+    }
+}
+
+impl Drop for RenderCache {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bitmap(bytes: usize) -> CachedRender {
+        CachedRender { pixels: Arc::new(vec![0; bytes]), width: 1, height: 1, scale: 1.0 }
+    }
+
+    #[test]
+    fn test_lru_eviction_and_accounting() {
+        let budget = Arc::new(RenderCacheBudget::new(1000));
+        let mut cache = RenderCache::new(Arc::clone(&budget));
+
+        let a = RenderCacheKey::new(0, 1.0, 0);
+        let b = RenderCacheKey::new(1, 1.0, 0);
+        cache.insert(0, a, bitmap(400));
+        cache.insert(0, b, bitmap(400));
+        assert_eq!(budget.used_bytes(), 800);
+
+        // Touch `a` so `b` becomes least recently used.
+        assert!(cache.get(&a).is_some());
+        assert!(cache.evict_oldest());
+        assert!(cache.get(&b).is_none());
+        assert!(cache.get(&a).is_some());
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses), (2, 1));
+        assert_eq!(budget.used_bytes(), 400);
+
+        drop(cache);
+        assert_eq!(budget.used_bytes(), 0);
+    }
+
+    #[test]
+    fn test_stale_insert_is_dropped() {
+        let budget = Arc::new(RenderCacheBudget::new(1000));
+        let mut cache = RenderCache::new(Arc::clone(&budget));
+        let key = RenderCacheKey::new(0, 1.0, 0);
+
+        // A render that started before the invalidation finishes after it.
+        let generation = cache.generation();
+        cache.invalidate();
+        cache.insert(generation, key, bitmap(400));
+        assert!(!cache.contains(&key));
+        assert_eq!(budget.used_bytes(), 0);
+
+        cache.insert(cache.generation(), key, bitmap(400));
+        assert!(cache.contains(&key));
+    }
+
+    #[test]
+    fn test_scale_quantization() {
+        let key = RenderCacheKey::new(0, 1.333_333, 0);
+        assert_eq!(key, RenderCacheKey::new(0, 1.331, 0));
+        assert_eq!(key.scale(), 1.33);
+    }
+}
@@ -0,0 +1,566 @@
+//! Optional pool of out-of-process render workers.
+//!
+//! In-process rendering serializes every page of a document on its
+//! `Mutex<CachedPdf>`, and a malformed PDF that crashes pdfium takes the
+//! whole app down with it. When enabled (`set_render_workers`), full-page
+//! renders are instead sent to N helper processes. Each helper is this same
+//! executable started with `WORKER_ARG`; it binds pdfium via `bind_pdfium`
+//! (through `CachedPdf::new`), opens documents on demand, and renders pages
+//! for the main process over its stdin/stdout pipes.
+//!
+//! If a helper dies mid-request, or hangs without answering within
+//! `WORKER_CALL_TIMEOUT`, it is killed and replaced with a fresh process and
+//! the page is reported as `PdfError::RenderError`; Kiosk keeps running.
+//!
+//! Pipe protocol: every message is a frame of `[len: u32 LE][payload]`.
+//! Requests and responses are JSON frames; `Open` is followed by a frame
+//! with the document bytes and `Rendered` by a frame with the RGBA pixels.
+//! Each request gets exactly one response.
+//!
+//! Password-protected documents are never sent to workers, since reopening
+//! them after a restart would require keeping the password around.
+//!
+//! Every registration of a document's bytes gets a new version. A worker
+//! holding an older version of a document (e.g. from before a layer toggle
+//! replaced its bytes) closes it and opens the current one on next use.
+
+use crate::pdf::{max_render_pixels, set_max_render_pixels, CachedPdf, PdfError, RenderOptions};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+/// Command line argument that starts the executable as a render worker.
+pub const WORKER_ARG: &str = "--kiosk-render-worker";
+
+/// Upper bound on the number of worker processes.
+pub const MAX_RENDER_WORKERS: usize = 16;
+
+/// How long a worker may take to answer one request before it is treated
+/// as hung, killed and replaced.
+const WORKER_CALL_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Serialize, Deserialize)]
+enum WorkerRequest {
+    /// Followed by a frame with the document bytes
+    Open { doc_id: String },
+    Render {
+        doc_id: String,
+        page_index: u32,
+        scale: f32,
+        options: RenderOptions,
+        /// The main process's `max_render_pixels`
+        max_pixels: u64,
+    },
+    Close { doc_id: String },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+enum WorkerResponse {
+    Ok,
+    /// Followed by a frame with tightly packed RGBA pixels
+    Rendered { width: u32, height: u32 },
+    Error { message: String },
+}
+
+fn write_frame(w: &mut impl Write, payload: &[u8]) -> io::Result<()> {
+    let len = u32::try_from(payload.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "frame too large"))?;
+    w.write_all(&len.to_le_bytes())?;
+    w.write_all(payload)
+}
+
+fn read_frame(r: &mut impl Read) -> io::Result<Vec<u8>> {
+    let mut len = [0u8; 4];
+    r.read_exact(&mut len)?;
+    let mut payload = vec![0u8; u32::from_le_bytes(len) as usize];
+    r.read_exact(&mut payload)?;
+    Ok(payload)
+}
+
+fn write_message<T: Serialize>(w: &mut impl Write, message: &T) -> io::Result<()> {
+    let json = serde_json::to_vec(message).map_err(io::Error::other)?;
+    write_frame(w, &json)
+}
+
+fn read_message<T: for<'de> Deserialize<'de>>(r: &mut impl Read) -> io::Result<T> {
+    let frame = read_frame(r)?;
+    serde_json::from_slice(&frame).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// A response and, for `Rendered`, the pixel frame that follows it.
+type Reply = (WorkerResponse, Option<Vec<u8>>);
+
+fn read_reply(r: &mut impl Read) -> io::Result<Reply> {
+    let response = read_message(r)?;
+    let data = match response {
+        WorkerResponse::Rendered { .. } => Some(read_frame(r)?),
+        _ => None,
+    };
+    Ok((response, data))
+}
+
+// ============================================================================
+// Worker side
+// ============================================================================
+
+/// Whether this process was started as a render worker.
+pub fn is_worker_invocation() -> bool {
+    std::env::args().nth(1).as_deref() == Some(WORKER_ARG)
+}
+
+/// Serve render requests on stdin/stdout until the main process goes away.
+pub fn run_worker() {
+    let mut input = BufReader::new(io::stdin().lock());
+    let mut output = BufWriter::new(io::stdout().lock());
+    let mut documents: HashMap<String, CachedPdf> = HashMap::new();
+
+    eprintln!("[Kiosk Worker {}] started", std::process::id());
+
+    // A read error means the pipe closed: the main process exited or
+    // dropped this worker.
+    while let Ok(request) = read_message::<WorkerRequest>(&mut input) {
+        let written = match request {
+            WorkerRequest::Open { doc_id } => {
+                let bytes = match read_frame(&mut input) {
+                    Ok(b) => b,
+                    Err(_) => break,
+                };
+                match CachedPdf::new(bytes, None) {
+                    Ok(pdf) => {
+                        documents.insert(doc_id, pdf);
+                        write_message(&mut output, &WorkerResponse::Ok)
+                    }
+                    Err(e) => write_message(
+                        &mut output,
+                        &WorkerResponse::Error { message: e.to_string() },
+                    ),
+                }
+            }
+            WorkerRequest::Render { doc_id, page_index, scale, options, max_pixels } => {
+                set_max_render_pixels(max_pixels);
+                let rendered = documents
+                    .get(&doc_id)
+                    .ok_or(PdfError::NoDocument)
+                    .and_then(|pdf| pdf.render_page_to_rgba(page_index, scale, &options));
+                match rendered {
+                    Ok((pixels, width, height)) => {
+                        write_message(&mut output, &WorkerResponse::Rendered { width, height })
+                            .and_then(|_| write_frame(&mut output, &pixels))
+                    }
+                    Err(e) => write_message(
+                        &mut output,
+                        &WorkerResponse::Error { message: e.to_string() },
+                    ),
+                }
+            }
+            WorkerRequest::Close { doc_id } => {
+                documents.remove(&doc_id);
+                write_message(&mut output, &WorkerResponse::Ok)
+            }
+        };
+
+        if written.and_then(|_| output.flush()).is_err() {
+            break;
+        }
+    }
+}
+
+// ============================================================================
+// Main process side
+// ============================================================================
+
+/// Why a request to a worker failed.
+enum WorkerFailure {
+    /// The worker answered with an error; it is still healthy.
+    Pdf(PdfError),
+    /// The pipe broke: the worker crashed or hung up.
+    Crashed(io::Error),
+}
+
+impl From<io::Error> for WorkerFailure {
+    fn from(e: io::Error) -> Self {
+        WorkerFailure::Crashed(e)
+    }
+}
+
+/// Bytes of a document registered with the pool.
+#[derive(Clone)]
+struct RegisteredDocument {
+    /// Distinct for every registration, so replaced bytes are reopened
+    version: u64,
+    bytes: Arc<Vec<u8>>,
+}
+
+/// Documents a worker has open, by doc id, that are no longer registered
+/// or are registered with other bytes.
+fn stale_documents(opened: &HashMap<String, u64>, registered: &HashMap<String, RegisteredDocument>) -> Vec<String> {
+    opened
+        .iter()
+        .filter(|(id, version)| registered.get(*id).map(|doc| doc.version) != Some(**version))
+        .map(|(id, _)| id.clone())
+        .collect()
+}
+
+/// Wait up to `timeout` for the next reply read from a worker.
+fn recv_reply(replies: &Receiver<io::Result<Reply>>, timeout: Duration) -> io::Result<Reply> {
+    match replies.recv_timeout(timeout) {
+        Ok(reply) => reply,
+        Err(RecvTimeoutError::Timeout) => Err(io::Error::new(io::ErrorKind::TimedOut, "worker timed out")),
+        Err(RecvTimeoutError::Disconnected) => Err(io::Error::new(io::ErrorKind::BrokenPipe, "worker exited")),
+    }
+}
+
+/// Read replies from a worker's stdout on a thread of their own, so a
+/// worker stuck in pdfium can't block the caller past its deadline. The
+/// thread ends when the pipe closes, e.g. once the worker is killed.
+fn spawn_reply_reader(stdout: ChildStdout) -> Receiver<io::Result<Reply>> {
+    let (sender, replies) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut stdout = BufReader::new(stdout);
+        loop {
+            let reply = read_reply(&mut stdout);
+            let failed = reply.is_err();
+            if sender.send(reply).is_err() || failed {
+                break;
+            }
+        }
+    });
+    replies
+}
+
+/// One helper process and the documents it currently has open.
+struct WorkerProcess {
+    child: Child,
+    stdin: BufWriter<ChildStdin>,
+    replies: Receiver<io::Result<Reply>>,
+    /// Version of each open document
+    opened: HashMap<String, u64>,
+}
+
+impl WorkerProcess {
+    fn spawn() -> io::Result<Self> {
+        let exe = std::env::current_exe()?;
+        let mut child = Command::new(exe)
+            .arg(WORKER_ARG)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()?;
+
+        let stdin = child.stdin.take().ok_or_else(|| io::Error::other("worker stdin missing"))?;
+        let stdout = child.stdout.take().ok_or_else(|| io::Error::other("worker stdout missing"))?;
+
+        Ok(WorkerProcess {
+            child,
+            stdin: BufWriter::new(stdin),
+            replies: spawn_reply_reader(stdout),
+            opened: HashMap::new(),
+        })
+    }
+
+    /// Send one request (plus an optional data frame) and wait for the
+    /// reply, for at most `WORKER_CALL_TIMEOUT`.
+    fn call(&mut self, request: &WorkerRequest, data: Option<&[u8]>) -> Result<Reply, WorkerFailure> {
+        write_message(&mut self.stdin, request)?;
+        if let Some(data) = data {
+            write_frame(&mut self.stdin, data)?;
+        }
+        self.stdin.flush()?;
+        Ok(recv_reply(&self.replies, WORKER_CALL_TIMEOUT)?)
+    }
+
+    /// Close documents that are no longer registered with the pool, or
+    /// whose registered bytes have changed.
+    fn close_stale(&mut self, registered: &HashMap<String, RegisteredDocument>) -> Result<(), WorkerFailure> {
+        for doc_id in stale_documents(&self.opened, registered) {
+            self.opened.remove(&doc_id);
+            self.call(&WorkerRequest::Close { doc_id }, None)?;
+        }
+        Ok(())
+    }
+
+    fn render(
+        &mut self,
+        doc_id: &str,
+        document: &RegisteredDocument,
+        page_index: u32,
+        scale: f32,
+        options: RenderOptions,
+    ) -> Result<(Vec<u8>, u32, u32), WorkerFailure> {
+        if self.opened.get(doc_id) != Some(&document.version) {
+            let open = WorkerRequest::Open { doc_id: doc_id.to_string() };
+            match self.call(&open, Some(&document.bytes))?.0 {
+                WorkerResponse::Ok => {
+                    self.opened.insert(doc_id.to_string(), document.version);
+                }
+                WorkerResponse::Error { message } => {
+                    return Err(WorkerFailure::Pdf(PdfError::LoadError(message)))
+                }
+                other => return Err(unexpected(&other)),
+            }
+        }
+
+        let request = WorkerRequest::Render {
+            doc_id: doc_id.to_string(),
+            page_index,
+            scale,
+            options,
+            max_pixels: max_render_pixels(),
+        };
+        match self.call(&request, None)? {
+            (WorkerResponse::Rendered { width, height }, Some(pixels)) => Ok((pixels, width, height)),
+            (WorkerResponse::Error { message }, _) => {
+                Err(WorkerFailure::Pdf(PdfError::RenderError(message)))
+            }
+            (other, _) => Err(unexpected(&other)),
+        }
+    }
+}
+
+/// A response out of protocol means the pipe is out of sync; treat the
+/// worker as crashed so it gets replaced.
+fn unexpected(response: &WorkerResponse) -> WorkerFailure {
+    WorkerFailure::Crashed(io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("unexpected worker response: {:?}", response),
+    ))
+}
+
+impl Drop for WorkerProcess {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+struct PoolState {
+    idle: Vec<WorkerProcess>,
+    /// Workers alive, idle or busy
+    live: usize,
+}
+
+/// Fixed-size pool of render worker processes.
+pub struct RenderWorkerPool {
+    state: Mutex<PoolState>,
+    available: Condvar,
+    size: usize,
+    /// Bytes of every document the workers may open, keyed by doc id
+    documents: Mutex<HashMap<String, RegisteredDocument>>,
+    /// Version given to the next registration
+    next_version: AtomicU64,
+}
+
+impl RenderWorkerPool {
+    /// Start `size` worker processes.
+    pub fn new(size: usize) -> Result<Self, PdfError> {
+        let idle = (0..size)
+            .map(|_| WorkerProcess::spawn())
+            .collect::<io::Result<Vec<_>>>()
+            .map_err(|e| PdfError::InitError(format!("Failed to start render worker: {}", e)))?;
+
+        Ok(Self {
+            state: Mutex::new(PoolState { idle, live: size }),
+            available: Condvar::new(),
+            size,
+            documents: Mutex::new(HashMap::new()),
+            next_version: AtomicU64::new(1),
+        })
+    }
+
+    /// Number of worker processes, i.e. how many pages can render at once.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn has_document(&self, doc_id: &str) -> bool {
+        self.documents.lock().unwrap().contains_key(doc_id)
+    }
+
+    fn registration(&self, bytes: Vec<u8>) -> RegisteredDocument {
+        RegisteredDocument {
+            version: self.next_version.fetch_add(1, Ordering::Relaxed),
+            bytes: Arc::new(bytes),
+        }
+    }
+
+    /// Make a document's bytes available to the workers, replacing any
+    /// bytes registered before. Workers reopen the document on next use.
+    pub fn register_document(&self, doc_id: &str, bytes: Vec<u8>) {
+        let document = self.registration(bytes);
+        self.documents.lock().unwrap().insert(doc_id.to_string(), document);
+    }
+
+    /// Register a document's bytes unless some are registered already, so
+    /// a render that read the bytes before they were replaced can't undo
+    /// `register_document`.
+    pub fn register_document_if_absent(&self, doc_id: &str, bytes: Vec<u8>) {
+        let document = self.registration(bytes);
+        self.documents
+            .lock()
+            .unwrap()
+            .entry(doc_id.to_string())
+            .or_insert(document);
+    }
+
+    /// Forget a closed document. Workers close their copy the next time
+    /// they are used.
+    pub fn forget_document(&self, doc_id: &str) {
+        self.documents.lock().unwrap().remove(doc_id);
+    }
+
+    /// Render a page in a worker process.
+    ///
+    /// Blocks until a worker is idle. If the worker crashes or times out, it
+    /// is replaced and the page is reported as `PdfError::RenderError`.
+    pub fn render_page(
+        &self,
+        doc_id: &str,
+        page_index: u32,
+        scale: f32,
+        options: RenderOptions,
+    ) -> Result<(Vec<u8>, u32, u32), PdfError> {
+        let document = self
+            .documents
+            .lock()
+            .unwrap()
+            .get(doc_id)
+            .cloned()
+            .ok_or(PdfError::NoDocument)?;
+
+        let mut worker = self.checkout()?;
+        let result = {
+            let registered = self.documents.lock().unwrap().clone();
+            worker
+                .close_stale(&registered)
+                .and_then(|_| worker.render(doc_id, &document, page_index, scale, options))
+        };
+
+        match result {
+            Ok(rendered) => {
+                self.checkin(worker);
+                Ok(rendered)
+            }
+            Err(WorkerFailure::Pdf(e)) => {
+                self.checkin(worker);
+                Err(e)
+            }
+            Err(WorkerFailure::Crashed(e)) => {
+                let pid = worker.child.id();
+                // Kills the worker, which also ends its reply reader.
+                drop(worker);
+                eprintln!(
+                    "[Kiosk Worker {}] crashed rendering {} page {}: {}",
+                    pid, doc_id, page_index, e
+                );
+                self.replace_crashed();
+                let failure = match e.kind() {
+                    io::ErrorKind::TimedOut => "timed out",
+                    _ => "crashed",
+                };
+                Err(PdfError::RenderError(format!(
+                    "Render worker {} on page {}",
+                    failure, page_index
+                )))
+            }
+        }
+    }
+
+    fn checkout(&self) -> Result<WorkerProcess, PdfError> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some(worker) = state.idle.pop() {
+                return Ok(worker);
+            }
+            if state.live == 0 {
+                return Err(PdfError::RenderError("No render workers available".into()));
+            }
+            state = self.available.wait(state).unwrap();
+        }
+    }
+
+    fn checkin(&self, worker: WorkerProcess) {
+        self.state.lock().unwrap().idle.push(worker);
+        self.available.notify_one();
+    }
+
+    /// Start a replacement for a crashed worker. If that fails the pool
+    /// shrinks; waiters are woken either way.
+    fn replace_crashed(&self) {
+        let replacement = WorkerProcess::spawn();
+        let mut state = self.state.lock().unwrap();
+        match replacement {
+            Ok(worker) => state.idle.push(worker),
+            Err(e) => {
+                eprintln!("[Kiosk Worker] Failed to restart render worker: {}", e);
+                state.live -= 1;
+            }
+        }
+        self.available.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_roundtrip() {
+        let mut pipe = Vec::new();
+        write_message(&mut pipe, &WorkerResponse::Rendered { width: 2, height: 1 }).unwrap();
+        write_frame(&mut pipe, &[1, 2, 3, 4, 5, 6, 7, 8]).unwrap();
+
+        let mut reader = pipe.as_slice();
+        match read_message::<WorkerResponse>(&mut reader).unwrap() {
+            WorkerResponse::Rendered { width, height } => assert_eq!((width, height), (2, 1)),
+            other => panic!("unexpected response: {:?}", other),
+        }
+        assert_eq!(read_frame(&mut reader).unwrap(), vec![1, 2, 3, 4, 5, 6, 7, 8]);
+        // A truncated pipe reads as an error, which the pool treats as a crash.
+        assert!(read_frame(&mut reader).is_err());
+    }
+
+    #[test]
+    fn test_reply_timeout() {
+        let (sender, replies) = mpsc::channel();
+        let timeout = Duration::from_millis(10);
+        // A hung worker sends nothing while its pipe stays open.
+        let hung = recv_reply(&replies, timeout).unwrap_err();
+        assert_eq!(hung.kind(), io::ErrorKind::TimedOut);
+
+        sender.send(Ok((WorkerResponse::Ok, None))).unwrap();
+        assert!(matches!(recv_reply(&replies, timeout).unwrap().0, WorkerResponse::Ok));
+        drop(sender);
+        assert_eq!(recv_reply(&replies, timeout).unwrap_err().kind(), io::ErrorKind::BrokenPipe);
+    }
+
+    #[test]
+    fn test_replaced_document_is_reopened() {
+        // A pool without processes still tracks registrations.
+        let pool = RenderWorkerPool::new(0).unwrap();
+        pool.register_document_if_absent("doc_1", b"original".to_vec());
+        let opened: HashMap<String, u64> = pool
+            .documents
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(id, doc)| (id.clone(), doc.version))
+            .collect();
+        assert!(stale_documents(&opened, &pool.documents.lock().unwrap()).is_empty());
+
+        // Toggling a layer replaces the bytes under the same doc id; a render
+        // still holding the old bytes must not register them back.
+        pool.register_document("doc_1", b"layers off".to_vec());
+        pool.register_document_if_absent("doc_1", b"original".to_vec());
+        let registered = pool.documents.lock().unwrap().clone();
+        assert_eq!(stale_documents(&opened, &registered), vec!["doc_1".to_string()]);
+        assert_eq!(registered["doc_1"].bytes.as_slice(), b"layers off");
+
+        pool.forget_document("doc_1");
+        assert_eq!(stale_documents(&opened, &pool.documents.lock().unwrap()), vec!["doc_1".to_string()]);
+    }
+}
@@ -0,0 +1,71 @@
+//! On-disk cache of full-text search indexes.
+//!
+//! A document's `SearchIndex` is built in the background when it is opened
+//! (see `commands::build_search_index`) and stored under the app data
+//! directory, keyed like the thumbnail cache by the SHA-256 of the document
+//! bytes, so reopening the file loads the index instead of rebuilding it and
+//! an edited file gets a fresh one:
+//!
+//! ```text
+//! {app_data_dir}/search_index/{content_hash}.idx
+//! ```
+//!
+//! Indexes of password-protected documents are kept in memory only, so their
+//! text never reaches the disk unencrypted.
+//!
+//! The cache is trimmed to `SEARCH_INDEX_CACHE_MAX_BYTES` at startup, least
+//! recently used indexes first (see `disk_cache`). Files of an older index
+//! format are deleted when read, or age out like any unused index.
+
+use crate::disk_cache;
+use crate::pdf::SearchIndex;
+use std::io;
+use std::path::PathBuf;
+
+/// Directory of the cache under the app data directory.
+pub const SEARCH_INDEX_CACHE_DIR: &str = "search_index";
+
+/// Disk space the search index cache may keep across documents (512 MiB).
+pub const SEARCH_INDEX_CACHE_MAX_BYTES: u64 = 512 * 1024 * 1024;
+
+/// Search index directory for one cache root.
+pub struct SearchIndexCache {
+    dir: PathBuf,
+}
+
+impl SearchIndexCache {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    fn path(&self, content_hash: &str) -> PathBuf {
+        self.dir.join(format!("{}.idx", content_hash))
+    }
+
+    /// Read a stored index, marking it used. Missing, outdated or corrupt
+    /// files are a miss; the latter two are deleted.
+    pub fn get(&self, content_hash: &str) -> Option<SearchIndex> {
+        let path = self.path(content_hash);
+        let index = SearchIndex::from_bytes(&std::fs::read(&path).ok()?);
+        match index {
+            Some(_) => disk_cache::touch(&path),
+            None => {
+                let _ = std::fs::remove_file(&path);
+            }
+        }
+        index
+    }
+
+    /// Store an index. Written to a temporary file and renamed into place,
+    /// so concurrent readers never see a partial file.
+    pub fn put(&self, content_hash: &str, index: &SearchIndex) -> io::Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        disk_cache::write_atomic(&self.path(content_hash), &index.to_bytes())
+    }
+
+    /// Delete the least recently used indexes until the cache fits
+    /// `SEARCH_INDEX_CACHE_MAX_BYTES`. Returns the bytes freed.
+    pub fn trim(&self) -> io::Result<u64> {
+        disk_cache::trim(&self.dir, SEARCH_INDEX_CACHE_MAX_BYTES)
+    }
+}
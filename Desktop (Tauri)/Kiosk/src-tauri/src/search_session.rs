@@ -0,0 +1,246 @@
+//! Streaming, cancellable text search.
+//!
+//! `search_text` answers in one call and stops at `max_results`, holding the
+//! document for the whole walk. A search session (`start_search`, see
+//! `commands`) instead searches page by page on the blocking thread pool,
+//! taking the document lock for one page at a time, so renders interleave
+//! with a long search:
+//!
+//! - each page with matches is reported as a `search-progress` event
+//!   (`SearchUpdate`) carrying that page's results; pages without matches
+//!   are folded into a progress event at most every `SEARCH_PROGRESS_INTERVAL`;
+//! - every result is kept in the session, and `get_search_results` pages
+//!   through them by offset while the search is still running;
+//! - the last event has status `complete` or `cancelled`; pages whose text
+//!   can't be read are skipped, as in `search_text`.
+//!
+//! Results stay in page order in every mode; `fuzzy` results are ranked by
+//! their `score` on the frontend, as only a finished search has them all.
+//!
+//! A session is kept until it is cancelled (which also releases a finished
+//! one) or its document is closed.
+
+use crate::pdf::SearchResult;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Event emitted with each page batch and progress report of a search.
+pub const SEARCH_PROGRESS_EVENT: &str = "search-progress";
+
+/// Minimum time between two progress events for pages without matches.
+pub const SEARCH_PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Results a session keeps when `start_search` is given no limit.
+pub const DEFAULT_SESSION_MAX_RESULTS: usize = 10_000;
+
+/// Stage of a search session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchStatus {
+    Searching,
+    Complete,
+    Cancelled,
+}
+
+/// Payload of `SEARCH_PROGRESS_EVENT`.
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchUpdate {
+    pub search_id: u64,
+    pub doc_id: String,
+    pub status: SearchStatus,
+    /// Results found since the previous event, in page order
+    pub results: Vec<SearchResult>,
+    /// Pages searched so far, out of `page_count`
+    pub pages_searched: u32,
+    pub page_count: u32,
+    /// Results found so far
+    pub total_results: usize,
+    /// The result limit was reached and the search stopped early
+    pub truncated: bool,
+}
+
+/// One slice of a session's results, from `get_search_results`.
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchResultsPage {
+    /// Index of the first result in `results`
+    pub offset: usize,
+    pub results: Vec<SearchResult>,
+    pub total_results: usize,
+    pub pages_searched: u32,
+    pub page_count: u32,
+    pub status: SearchStatus,
+    pub truncated: bool,
+}
+
+struct Progress {
+    results: Vec<SearchResult>,
+    pages_searched: u32,
+    status: SearchStatus,
+    truncated: bool,
+}
+
+/// One search: its results so far and whether it should stop.
+pub struct SearchSession {
+    pub doc_id: String,
+    /// Number of pages to search
+    pub page_count: u32,
+    /// Results kept before the search stops
+    pub max_results: usize,
+    cancelled: AtomicBool,
+    progress: Mutex<Progress>,
+}
+
+impl SearchSession {
+    fn new(doc_id: &str, page_count: u32, max_results: usize) -> Self {
+        Self {
+            doc_id: doc_id.to_string(),
+            page_count,
+            max_results,
+            cancelled: AtomicBool::new(false),
+            progress: Mutex::new(Progress {
+                results: Vec::new(),
+                pages_searched: 0,
+                status: SearchStatus::Searching,
+                truncated: false,
+            }),
+        }
+    }
+
+    /// Stop before the next page.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Record a searched page and its results, dropping those beyond
+    /// `max_results`. Returns `false` once the limit is reached.
+    pub fn add_page(&self, mut results: Vec<SearchResult>) -> bool {
+        let mut progress = self.progress.lock().unwrap();
+        progress.pages_searched += 1;
+        let room = self.max_results - progress.results.len();
+        if results.len() >= room {
+            progress.truncated = results.len() > room || progress.pages_searched < self.page_count;
+            results.truncate(room);
+        }
+        progress.results.extend(results);
+        progress.results.len() < self.max_results
+    }
+
+    pub fn finish(&self, status: SearchStatus) {
+        self.progress.lock().unwrap().status = status;
+    }
+
+    /// An update carrying the results from index `from` on.
+    pub fn update(&self, search_id: u64, from: usize) -> SearchUpdate {
+        let progress = self.progress.lock().unwrap();
+        SearchUpdate {
+            search_id,
+            doc_id: self.doc_id.clone(),
+            status: progress.status,
+            results: progress.results[from.min(progress.results.len())..].to_vec(),
+            pages_searched: progress.pages_searched,
+            page_count: self.page_count,
+            total_results: progress.results.len(),
+            truncated: progress.truncated,
+        }
+    }
+
+    /// Up to `limit` results starting at `offset`.
+    pub fn results(&self, offset: usize, limit: usize) -> SearchResultsPage {
+        let progress = self.progress.lock().unwrap();
+        let start = offset.min(progress.results.len());
+        let end = start.saturating_add(limit).min(progress.results.len());
+        SearchResultsPage {
+            offset: start,
+            results: progress.results[start..end].to_vec(),
+            total_results: progress.results.len(),
+            pages_searched: progress.pages_searched,
+            page_count: self.page_count,
+            status: progress.status,
+            truncated: progress.truncated,
+        }
+    }
+}
+
+/// Search sessions, running or finished, by search id.
+#[derive(Default)]
+pub struct SearchSessions {
+    sessions: Mutex<HashMap<u64, Arc<SearchSession>>>,
+    next_id: AtomicU64,
+}
+
+impl SearchSessions {
+    /// Register a new search over `page_count` pages and return its id.
+    pub fn start(&self, doc_id: &str, page_count: u32, max_results: usize) -> (u64, Arc<SearchSession>) {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let session = Arc::new(SearchSession::new(doc_id, page_count, max_results));
+        self.sessions.lock().unwrap().insert(id, Arc::clone(&session));
+        (id, session)
+    }
+
+    pub fn get(&self, id: u64) -> Option<Arc<SearchSession>> {
+        self.sessions.lock().unwrap().get(&id).cloned()
+    }
+
+    pub fn remove(&self, id: u64) -> Option<Arc<SearchSession>> {
+        self.sessions.lock().unwrap().remove(&id)
+    }
+
+    /// Cancel and forget every search of `doc_id`.
+    pub fn cancel_document(&self, doc_id: &str) {
+        self.sessions.lock().unwrap().retain(|_, session| {
+            if session.doc_id == doc_id {
+                session.cancel();
+            }
+            session.doc_id != doc_id
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(page: u32) -> SearchResult {
+        SearchResult {
+            page,
+            start_index: 0,
+            end_index: 1,
+            text: "a".to_string(),
+            context_before: String::new(),
+            context_after: String::new(),
+            score: 1.0,
+            rects: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_session_paging_and_limit() {
+        let sessions = SearchSessions::default();
+        let (id, session) = sessions.start("doc-1", 3, 4);
+        assert!(session.add_page(vec![result(0), result(0)]));
+        assert!(session.add_page(Vec::new()));
+
+        let page = session.results(1, 10);
+        assert_eq!((page.offset, page.results.len(), page.total_results), (1, 1, 2));
+        assert_eq!(page.status, SearchStatus::Searching);
+        assert_eq!(session.update(id, 1).results.len(), 1);
+
+        assert!(!session.add_page(vec![result(2), result(2), result(2)]));
+        session.finish(SearchStatus::Complete);
+        let page = session.results(3, 10);
+        assert_eq!((page.results.len(), page.total_results, page.truncated), (1, 4, true));
+        assert_eq!(page.pages_searched, 3);
+        assert!(session.results(9, 10).results.is_empty());
+
+        sessions.cancel_document("doc-1");
+        assert!(session.is_cancelled());
+        assert!(sessions.get(id).is_none());
+    }
+}
@@ -0,0 +1,177 @@
+//! On-disk cache of encoded page thumbnails.
+//!
+//! Thumbnails are stored under the app data directory, keyed by the SHA-256
+//! of the document bytes rather than its path, so a file that is moved or
+//! reopened from a download still hits, and an edited file never shows
+//! stale thumbnails:
+//!
+//! ```text
+//! {app_data_dir}/thumbnails/{content_hash}/{page}_{max_dim}.{ext}
+//! {app_data_dir}/thumbnails/{content_hash}/{page}_{max_dim}_{variant}.{ext}
+//! ```
+//!
+//! The second form holds thumbnails rendered with non-default render
+//! options or an accessibility filter; `variant` names that combination.
+//!
+//! Each file holds a small header (`[width u32][height u32][embedded u8]`,
+//! little-endian) followed by the encoded image.
+//!
+//! The cache is trimmed to `THUMBNAIL_CACHE_MAX_BYTES` at startup, dropping
+//! the thumbnails of the least recently viewed documents first (see
+//! `disk_cache`).
+
+use crate::disk_cache;
+use crate::pdf::{AccessibilityFilter, PageImageFormat, RenderOptions};
+use sha2::{Digest, Sha256};
+use std::io;
+use std::path::PathBuf;
+
+/// Directory of the cache under the app data directory.
+pub const THUMBNAIL_CACHE_DIR: &str = "thumbnails";
+
+/// Disk space the thumbnail cache may keep across documents (256 MiB).
+pub const THUMBNAIL_CACHE_MAX_BYTES: u64 = 256 * 1024 * 1024;
+
+/// Size of the header in front of each cached image.
+const HEADER_LEN: usize = 9;
+
+/// An encoded thumbnail, as stored on disk.
+#[derive(Debug, Clone)]
+pub struct StoredThumbnail {
+    pub width: u32,
+    pub height: u32,
+    /// Taken from the page's embedded `/Thumb` image
+    pub embedded: bool,
+    /// Encoded image bytes
+    pub data: Vec<u8>,
+}
+
+/// Hex SHA-256 of a document's bytes, used as its on-disk cache key.
+pub fn content_hash(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Cache variant name for thumbnails rendered with `options` and `filter`;
+/// `None` for the defaults.
+pub fn thumbnail_variant(options: &RenderOptions, filter: &AccessibilityFilter) -> Option<String> {
+    match (options.cache_tag(), filter.cache_tag()) {
+        (None, None) => None,
+        (Some(tag), None) | (None, Some(tag)) => Some(tag),
+        (Some(options_tag), Some(filter_tag)) => Some(format!("{}-{}", options_tag, filter_tag)),
+    }
+}
+
+/// Thumbnail directory for one cache root.
+pub struct ThumbnailCache {
+    dir: PathBuf,
+}
+
+impl ThumbnailCache {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    fn path(
+        &self,
+        content_hash: &str,
+        page_index: u32,
+        max_dim: u32,
+        format: PageImageFormat,
+        variant: Option<&str>,
+    ) -> PathBuf {
+        let name = match variant {
+            Some(tag) => format!("{}_{}_{}.{}", page_index, max_dim, tag, format.extension()),
+            None => format!("{}_{}.{}", page_index, max_dim, format.extension()),
+        };
+        self.dir.join(content_hash).join(name)
+    }
+
+    /// Read a cached thumbnail, marking it used. Missing or corrupt files
+    /// are a miss.
+    pub fn get(
+        &self,
+        content_hash: &str,
+        page_index: u32,
+        max_dim: u32,
+        format: PageImageFormat,
+        variant: Option<&str>,
+    ) -> Option<StoredThumbnail> {
+        let path = self.path(content_hash, page_index, max_dim, format, variant);
+        let file = std::fs::read(&path).ok()?;
+        if file.len() <= HEADER_LEN {
+            return None;
+        }
+        disk_cache::touch(&path);
+        Some(StoredThumbnail {
+            width: u32::from_le_bytes(file[0..4].try_into().ok()?),
+            height: u32::from_le_bytes(file[4..8].try_into().ok()?),
+            embedded: file[8] != 0,
+            data: file[HEADER_LEN..].to_vec(),
+        })
+    }
+
+    /// Store a thumbnail. Written to a temporary file and renamed into
+    /// place, so concurrent readers never see a partial file.
+    pub fn put(
+        &self,
+        content_hash: &str,
+        page_index: u32,
+        max_dim: u32,
+        format: PageImageFormat,
+        variant: Option<&str>,
+        thumbnail: &StoredThumbnail,
+    ) -> io::Result<()> {
+        let path = self.path(content_hash, page_index, max_dim, format, variant);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let mut file = Vec::with_capacity(HEADER_LEN + thumbnail.data.len());
+        file.extend_from_slice(&thumbnail.width.to_le_bytes());
+        file.extend_from_slice(&thumbnail.height.to_le_bytes());
+        file.push(thumbnail.embedded as u8);
+        file.extend_from_slice(&thumbnail.data);
+        disk_cache::write_atomic(&path, &file)
+    }
+
+    /// Delete the thumbnails of the least recently used documents until the
+    /// cache fits `THUMBNAIL_CACHE_MAX_BYTES`. Returns the bytes freed.
+    pub fn trim(&self) -> io::Result<u64> {
+        disk_cache::trim(&self.dir, THUMBNAIL_CACHE_MAX_BYTES)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdf::ColorMode;
+
+    #[test]
+    fn test_thumbnail_roundtrip() {
+        let dir = std::env::temp_dir().join(format!("kiosk-thumbs-{}", std::process::id()));
+        let cache = ThumbnailCache::new(dir.clone());
+        let hash = content_hash(b"%PDF-1.7");
+        let thumbnail = StoredThumbnail { width: 3, height: 4, embedded: true, data: vec![1, 2, 3] };
+
+        assert!(cache.get(&hash, 0, 128, PageImageFormat::Jpeg, None).is_none());
+        cache.put(&hash, 0, 128, PageImageFormat::Jpeg, None, &thumbnail).unwrap();
+
+        let read = cache.get(&hash, 0, 128, PageImageFormat::Jpeg, None).unwrap();
+        assert_eq!((read.width, read.height, read.embedded), (3, 4, true));
+        assert_eq!(read.data, vec![1, 2, 3]);
+        // Other sizes, formats and variants are separate entries.
+        assert!(cache.get(&hash, 0, 256, PageImageFormat::Jpeg, None).is_none());
+        assert!(cache.get(&hash, 0, 128, PageImageFormat::Webp, None).is_none());
+        let gray = thumbnail_variant(&RenderOptions::default(), &AccessibilityFilter::Grayscale);
+        assert_eq!(gray.as_deref(), Some("gray"));
+        let sepia = RenderOptions { color: ColorMode::Sepia, ..RenderOptions::default() };
+        let sepia_gray = thumbnail_variant(&sepia, &AccessibilityFilter::Grayscale);
+        assert_eq!(sepia_gray.as_deref(), Some("sepia-gray"));
+        assert!(cache.get(&hash, 0, 128, PageImageFormat::Jpeg, gray.as_deref()).is_none());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
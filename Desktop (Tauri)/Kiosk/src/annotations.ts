@@ -164,6 +164,15 @@ export async function initAnnotations(docId: string, pageInfos: PageInfo[]): Pro
   notifyStateChange();
 }
 
+/**
+ * Replace the page infos used for coordinate mapping, e.g. after the view
+ * was rotated. Annotations themselves stay in PDF coordinates.
+ */
+export function updateAnnotationPageInfos(pageInfos: PageInfo[]): void {
+  state.pageInfos = pageInfos;
+  notifyStateChange();
+}
+
 /**
  * Reset annotation state (when closing document).
  */
@@ -688,9 +697,21 @@ function isPointInRect(point: PdfPoint, rect: PdfRect): boolean {
 // Coordinate Mapping
 // ============================================================================
 
+/**
+ * Size of the unrotated page. `pageInfo` describes the rotated view, so
+ * width and height are swapped back for quarter turns.
+ */
+function unrotatedSize(pageInfo: PageInfo): { width: number; height: number } {
+  const rotation = pageInfo.view_rotation ?? 0;
+  return rotation === 90 || rotation === 270
+    ? { width: pageInfo.height, height: pageInfo.width }
+    : { width: pageInfo.width, height: pageInfo.height };
+}
+
 /**
  * Convert screen coordinates to PDF coordinates.
- * PDF coordinates have origin at bottom-left.
+ * PDF coordinates have origin at bottom-left of the unrotated page, so
+ * annotations are saved the same way whatever the view rotation.
  */
 function screenToPdf(
   screenX: number,
@@ -698,11 +719,21 @@ function screenToPdf(
   pageInfo: PageInfo,
   scale: number
 ): PdfPoint {
+  const { width, height } = unrotatedSize(pageInfo);
+  const viewX = screenX / scale;
+  const viewY = screenY / scale;
+
+  // Undo the clockwise view rotation (top-left origin)
+  let x = viewX;
+  let y = viewY;
+  switch (pageInfo.view_rotation ?? 0) {
+    case 90: x = viewY; y = height - viewX; break;
+    case 180: x = width - viewX; y = height - viewY; break;
+    case 270: x = width - viewY; y = viewX; break;
+  }
+
   // Screen Y is top-down, PDF Y is bottom-up
-  const pdfX = screenX / scale;
-  const pdfY = pageInfo.height - (screenY / scale);
-  
-  return { x: pdfX, y: pdfY };
+  return { x, y: height - y };
 }
 
 /**
@@ -714,11 +745,21 @@ export function pdfToScreen(
   pageInfo: PageInfo,
   scale: number
 ): { x: number; y: number } {
+  const { width, height } = unrotatedSize(pageInfo);
   // PDF Y is bottom-up, screen Y is top-down
-  const screenX = pdfX * scale;
-  const screenY = (pageInfo.height - pdfY) * scale;
-  
-  return { x: screenX, y: screenY };
+  const x = pdfX;
+  const y = height - pdfY;
+
+  // Apply the clockwise view rotation (top-left origin)
+  let viewX = x;
+  let viewY = y;
+  switch (pageInfo.view_rotation ?? 0) {
+    case 90: viewX = height - y; viewY = x; break;
+    case 180: viewX = width - x; viewY = height - y; break;
+    case 270: viewX = y; viewY = width - x; break;
+  }
+
+  return { x: viewX * scale, y: viewY * scale };
 }
 
 /**
@@ -729,14 +770,15 @@ export function pdfRectToScreen(
   pageInfo: PageInfo,
   scale: number
 ): { x: number; y: number; width: number; height: number } {
-  const topLeft = pdfToScreen(rect.x1, rect.y2, pageInfo, scale);
-  const bottomRight = pdfToScreen(rect.x2, rect.y1, pageInfo, scale);
+  // Opposite corners; under rotation either may end up top-left
+  const a = pdfToScreen(rect.x1, rect.y2, pageInfo, scale);
+  const b = pdfToScreen(rect.x2, rect.y1, pageInfo, scale);
   
   return {
-    x: topLeft.x,
-    y: topLeft.y,
-    width: bottomRight.x - topLeft.x,
-    height: bottomRight.y - topLeft.y,
+    x: Math.min(a.x, b.x),
+    y: Math.min(a.y, b.y),
+    width: Math.abs(b.x - a.x),
+    height: Math.abs(b.y - a.y),
   };
 }
 
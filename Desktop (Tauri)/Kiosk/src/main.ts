@@ -12,6 +12,8 @@ import {
   toggleSidebar, 
   setDisplayMode, 
   getDisplayMode,
+  setAccessibilityFilter,
+  getAccessibilityFilter,
   setAnnotationTool,
   saveAllAnnotations,
   hasUnsavedChanges,
@@ -20,7 +22,7 @@ import {
   type AnnotationTool
 } from './pdf-viewer';
 import { setAnnotationColor, onAnnotationStateChange, undoAnnotation, redoAnnotation, canUndo, canRedo } from './annotations';
-import { fileToBytes } from './pdf-api';
+import { fileToBytes, type AccessibilityFilter } from './pdf-api';
 
 // ============================================================================
 // Screen State Controller (Part 1)
@@ -266,7 +268,7 @@ function setupDisplayModeDropdown(): void {
   });
   
   // Handle mode selection
-  dropdown.querySelectorAll('.display-mode-option').forEach((option) => {
+  dropdown.querySelectorAll('.display-mode-option[data-mode]').forEach((option) => {
     option.addEventListener('click', () => {
       const mode = (option as HTMLElement).dataset.mode as DisplayMode;
       if (mode) {
@@ -274,7 +276,7 @@ function setupDisplayModeDropdown(): void {
         dropdown.classList.remove('open');
         
         // Update active state
-        dropdown.querySelectorAll('.display-mode-option').forEach(opt => {
+        dropdown.querySelectorAll('.display-mode-option[data-mode]').forEach(opt => {
           opt.classList.toggle('active', (opt as HTMLElement).dataset.mode === mode);
         });
       }
@@ -286,11 +288,60 @@ function setupDisplayModeDropdown(): void {
     dropdown.classList.remove('open');
   });
   
+  // Handle accessibility filter selection (applies to the open document)
+  const filterOptions = dropdown.querySelectorAll<HTMLElement>('.filter-option');
+  const markActiveFilter = () => {
+    const current = getAccessibilityFilter();
+    const currentCorrect = 'correct' in current && current.correct === true;
+    filterOptions.forEach(opt => {
+      opt.classList.toggle(
+        'active',
+        opt.dataset.filter === current.filter && (opt.dataset.correct === 'true') === currentCorrect
+      );
+    });
+  };
+  filterOptions.forEach((option) => {
+    option.addEventListener('click', async () => {
+      const filter = filterFromOption(option);
+      dropdown.classList.remove('open');
+      try {
+        await setAccessibilityFilter(filter);
+      } catch (err) {
+        console.error('[Kiosk] Failed to set accessibility filter:', err);
+      }
+      markActiveFilter();
+    });
+  });
+  // Filters are per document; refresh the highlight whenever the menu opens
+  btn.addEventListener('click', markActiveFilter);
+  
   // Set initial active state
   const currentMode = getDisplayMode();
-  dropdown.querySelectorAll('.display-mode-option').forEach(opt => {
+  dropdown.querySelectorAll('.display-mode-option[data-mode]').forEach(opt => {
     opt.classList.toggle('active', (opt as HTMLElement).dataset.mode === currentMode);
   });
+  markActiveFilter();
+}
+
+/**
+ * Build the AccessibilityFilter described by a filter menu option.
+ */
+function filterFromOption(option: HTMLElement): AccessibilityFilter {
+  const correct = option.dataset.correct === 'true';
+  switch (option.dataset.filter) {
+    case 'high_contrast':
+      return { filter: 'high_contrast' };
+    case 'grayscale':
+      return { filter: 'grayscale' };
+    case 'deuteranopia':
+      return { filter: 'deuteranopia', correct };
+    case 'protanopia':
+      return { filter: 'protanopia', correct };
+    case 'tritanopia':
+      return { filter: 'tritanopia', correct };
+    default:
+      return { filter: 'none' };
+  }
 }
 
 async function handleOpenFile(): Promise<void> {
@@ -4,7 +4,8 @@
  * TypeScript types and API for communicating with the Rust PDF renderer.
  */
 
-import { invoke } from '@tauri-apps/api/core';
+import { convertFileSrc, invoke } from '@tauri-apps/api/core';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
 
 // ============================================================================
 // Types
@@ -23,9 +24,13 @@ export interface CharRect {
 /** Page metadata */
 export interface PageInfo {
   index: number;
+  /** Width of the page as viewed, i.e. after `view_rotation` */
   width: number;
+  /** Height of the page as viewed, i.e. after `view_rotation` */
   height: number;
   rotation: number;
+  /** View-only clockwise rotation in degrees (0, 90, 180, 270) */
+  view_rotation: number;
 }
 
 /** Document metadata */
@@ -63,9 +68,16 @@ export interface TextRect {
 /** Search result */
 export interface SearchResult {
   page: number;
+  /** First matched character, as CharRect.index */
   start_index: number;
+  /** One past the last matched character */
   end_index: number;
   text: string;
+  /** Text around the match, whitespace collapsed */
+  context_before: string;
+  context_after: string;
+  /** 1 for an exact match; lower for a less similar fuzzy match */
+  score: number;
   rects: TextRect[];
 }
 
@@ -77,6 +89,59 @@ export interface RenderResult {
   pixels: string;
   width: number;
   height: number;
+  /**
+   * Scale actually rendered; below the requested scale when the page was
+   * downscaled to fit the pixel limit (see setMaxRenderPixels)
+   */
+  scale: number;
+}
+
+/**
+ * A page render decoded from a binary IPC response.
+ * `pixels` is a view into the response buffer (no copy) when rows are
+ * tightly packed.
+ */
+export interface RgbaRender {
+  pixels: Uint8ClampedArray;
+  width: number;
+  height: number;
+}
+
+/** Image formats served by the kiosk:// protocol. */
+export type PageImageFormat = 'rgba' | 'png' | 'webp' | 'jpeg' | 'tiff';
+
+/**
+ * Result of a region (tile) render. `x`/`y` are the pixel-snapped tile
+ * origin in PDF points (top-left origin), which may differ slightly from
+ * the requested region.
+ */
+export interface RegionRenderResult extends RenderResult {
+  x: number;
+  y: number;
+}
+
+/** An encoded page thumbnail from renderThumbnails */
+export interface ThumbnailResult {
+  page_index: number;
+  width: number;
+  height: number;
+  format: PageImageFormat;
+  /** Base64-encoded image bytes */
+  data: string;
+  /** Taken from the page's embedded /Thumb image rather than rendered */
+  embedded: boolean;
+}
+
+/** Render cache statistics for one document or all documents */
+export interface RenderCacheStats {
+  hits: number;
+  misses: number;
+  /** Number of cached bitmaps */
+  entries: number;
+  /** Bytes held by cached bitmaps */
+  bytes: number;
+  /** Global byte budget shared by all documents */
+  budget_bytes: number;
 }
 
 // ============================================================================
@@ -128,23 +193,584 @@ export async function getAllPageInfos(docId: string): Promise<PageInfo[]> {
   return invoke<PageInfo[]>('get_all_page_infos', { docId });
 }
 
+/** Scheduling priority of a render request; higher runs first */
+export type RenderPriority = 'low' | 'normal' | 'high';
+
+/**
+ * Color mapping applied to rendered pages by the backend.
+ * - inverted: lightness inverted with hue preserved; preserve_images
+ *   leaves raster images as authored
+ * - sepia: sepia tone
+ * - custom: white becomes paper and black becomes ink (RGB 0-255)
+ */
+export type ColorMode =
+  | { mode: 'normal' }
+  | { mode: 'inverted'; preserve_images?: boolean }
+  | { mode: 'sepia' }
+  | { mode: 'custom'; paper: [number, number, number]; ink: [number, number, number] };
+
+/**
+ * How the backend rasterizes a page. Every field is optional; omitted
+ * fields keep their defaults (annotations, forms and anti-aliasing on,
+ * white opaque background, full color, normal color mode, no extra
+ * rotation).
+ */
+export interface RenderOptions {
+  annotations?: boolean;
+  /** Form field widgets and values (never drawn in region tiles) */
+  forms?: boolean;
+  antialias_text?: boolean;
+  antialias_paths?: boolean;
+  antialias_images?: boolean;
+  /** Paper color as RGB 0-255 */
+  background?: [number, number, number];
+  transparent?: boolean;
+  grayscale?: boolean;
+  color?: ColorMode;
+  /** Extra clockwise rotation in degrees, on top of the view rotation */
+  rotation?: ViewRotation;
+  /** Part of the page to draw (overrides annotations) */
+  layer?: RenderLayer;
+}
+
+/**
+ * Part of a page a render contains.
+ * - full: content plus annotations (per the annotations option)
+ * - content: content and form fields, without annotations
+ * - annotations: annotations only, on a transparent background; draw it
+ *   over the content layer to get the full page
+ */
+export type RenderLayer = 'full' | 'content' | 'annotations';
+
+/** Clockwise view rotation in degrees. */
+export type ViewRotation = 0 | 90 | 180 | 270;
+
+/**
+ * Accessibility filter applied by the backend to every page, tile and
+ * thumbnail render of a document. For the color vision filters, correct
+ * selects daltonization instead of simulation.
+ */
+export type AccessibilityFilter =
+  | { filter: 'none' }
+  | { filter: 'high_contrast'; threshold?: number }
+  | { filter: 'grayscale' }
+  | { filter: 'deuteranopia'; correct?: boolean }
+  | { filter: 'protanopia'; correct?: boolean }
+  | { filter: 'tritanopia'; correct?: boolean };
+
+/**
+ * Select the accessibility filter for a document. Cached renders are
+ * dropped; re-render visible pages and thumbnails afterwards.
+ */
+export async function setAccessibilityFilter(
+  docId: string,
+  filter: AccessibilityFilter
+): Promise<void> {
+  return invoke('set_accessibility_filter', { docId, filter });
+}
+
+/**
+ * Rotate the view of one page (pageIndex) or of every page (omitted, which
+ * also clears per-page rotations), without modifying the file. Page infos,
+ * char rects and search results describe the rotated view afterwards, so
+ * refetch them and re-render.
+ */
+export async function setViewRotation(
+  docId: string,
+  rotation: ViewRotation,
+  pageIndex?: number
+): Promise<void> {
+  return invoke('set_view_rotation', { docId, rotation, pageIndex });
+}
+
+/** An optional content group (layer), identified by its object id. */
+export interface OptionalContentGroup {
+  id: string;
+  name: string;
+  default_visible: boolean;
+  visible: boolean;
+  /** The author asks viewers not to toggle this layer */
+  locked: boolean;
+}
+
+/**
+ * An entry of the layers panel tree: a layer (group) or a heading (label)
+ * over its children.
+ */
+export interface LayerNode {
+  group: string | null;
+  label: string | null;
+  children: LayerNode[];
+}
+
+/** A document's layers; both lists are empty when it has none. */
+export interface OptionalContent {
+  groups: OptionalContentGroup[];
+  order: LayerNode[];
+}
+
+/**
+ * List the optional content groups (layers) of a document with their
+ * visibility, and the layers panel tree.
+ */
+export async function getOptionalContent(docId: string): Promise<OptionalContent> {
+  return invoke<OptionalContent>('get_optional_content', { docId });
+}
+
+/**
+ * Show or hide layers by group id; layers not listed keep their state.
+ * Cached renders are dropped, so re-render visible pages and thumbnails
+ * afterwards. Not supported for password-protected documents.
+ */
+export async function setLayerVisibility(
+  docId: string,
+  layers: Record<string, boolean>
+): Promise<OptionalContent> {
+  return invoke<OptionalContent>('set_layer_visibility', { docId, layers });
+}
+
+/**
+ * Scheduling options for render calls.
+ *
+ * Renders tagged with a `cycleId` are dropped by the backend while still
+ * queued once a newer cycle is seen for the document; they reject with
+ * an error for which `isRenderCancelled` returns true.
+ */
+export interface RenderScheduling {
+  priority?: RenderPriority;
+  cycleId?: number;
+}
+
+/** Error message the backend uses for renders dropped before rendering. */
+const RENDER_CANCELLED = 'Render cancelled';
+
+/** Whether a render call failed only because it was superseded. */
+export function isRenderCancelled(err: unknown): boolean {
+  return err === RENDER_CANCELLED;
+}
+
+/**
+ * Drop every queued render of a document whose cycle is older than
+ * cycleId. Renders already in progress still complete.
+ */
+export async function cancelRenders(docId: string, cycleId: number): Promise<void> {
+  return invoke('cancel_renders', { docId, cycleId });
+}
+
 /**
  * Render a page to raw RGBA pixels.
  *
  * PHASE 4: Returns a RenderResult with base64-encoded RGBA pixel data
  * and pixel dimensions. Replaces the PNG-based render path.
+ * NOTE: Retained for JSON callers. The viewer hot path uses renderPageBinary.
  *
  * @param docId - Document ID from loadPdf
  * @param pageIndex - 0-based page index
  * @param scale - Render scale (1.0 = 72 DPI, 2.0 = 144 DPI)
+ * @param options - Render options (annotations, forms, background, color...)
  * @returns RenderResult with base64-encoded RGBA pixels, width, height
  */
 export async function renderPage(
   docId: string,
   pageIndex: number,
-  scale: number
+  scale: number,
+  options: RenderOptions = {},
+  scheduling: RenderScheduling = {}
 ): Promise<RenderResult> {
-  return invoke<RenderResult>('render_page', { docId, pageIndex, scale });
+  return invoke<RenderResult>('render_page', {
+    docId,
+    pageIndex,
+    scale,
+    options,
+    priority: scheduling.priority ?? null,
+    cycleId: scheduling.cycleId ?? null,
+  });
+}
+
+/** Size of the `[width][height][stride]` header on binary render responses. */
+const BINARY_RENDER_HEADER_LEN = 12;
+
+/**
+ * Render a page to raw RGBA pixels via Tauri's binary response channel.
+ *
+ * Avoids the base64 JSON path of renderPage: the response arrives as an
+ * ArrayBuffer with a small little-endian header, so large pages are not
+ * pushed through a multi-megabyte string.
+ *
+ * @param docId - Document ID from loadPdf
+ * @param pageIndex - 0-based page index
+ * @param scale - Render scale (1.0 = 72 DPI, 2.0 = 144 DPI)
+ * @param options - Render options (annotations, forms, background, color...)
+ * @param scheduling - Priority and render cycle for the backend scheduler
+ */
+export async function renderPageBinary(
+  docId: string,
+  pageIndex: number,
+  scale: number,
+  options: RenderOptions = {},
+  scheduling: RenderScheduling = {}
+): Promise<RgbaRender> {
+  const buffer = await invoke<ArrayBuffer>('render_page_binary', {
+    docId,
+    pageIndex,
+    scale,
+    options,
+    priority: scheduling.priority ?? null,
+    cycleId: scheduling.cycleId ?? null,
+  });
+  return decodeBinaryRender(buffer);
+}
+
+/** A page rendered as separate content and annotation layers. */
+export interface PageLayers {
+  content: RgbaRender;
+  annotations: RgbaRender;
+}
+
+/**
+ * Render a page's content and annotations as two bitmaps of the same
+ * size, so annotations can be hidden or faded by compositing (e.g. canvas
+ * globalAlpha) and, after an edit, re-fetched alone with
+ * renderPageBinary(..., { layer: 'annotations' }).
+ */
+export async function renderPageLayers(
+  docId: string,
+  pageIndex: number,
+  scale: number,
+  options: RenderOptions = {},
+  scheduling: RenderScheduling = {}
+): Promise<PageLayers> {
+  const [content, annotations] = await Promise.all([
+    renderPageBinary(docId, pageIndex, scale, { ...options, layer: 'content' }, scheduling),
+    renderPageBinary(docId, pageIndex, scale, { ...options, layer: 'annotations' }, scheduling),
+  ]);
+  return { content, annotations };
+}
+
+/** Stage of a progressive render */
+export type ProgressiveStatus = 'rendering' | 'paused' | 'complete' | 'cancelled' | 'failed';
+
+/** Progress of a progressive render, sent with every published frame and at the end */
+export interface ProgressiveUpdate {
+  render_id: number;
+  doc_id: string;
+  page_index: number;
+  /** Size of the current frame; 0 when none was published */
+  width: number;
+  height: number;
+  status: ProgressiveStatus;
+  /** Reason of a 'failed' render */
+  error: string | null;
+}
+
+/**
+ * Start rendering a heavy page progressively and return its render id.
+ * Partial frames are announced through onProgressiveRender and fetched with
+ * getProgressiveFrame; the 'complete' frame equals renderPageBinary's result
+ * with the same arguments.
+ */
+export async function startProgressiveRender(
+  docId: string,
+  pageIndex: number,
+  scale: number,
+  options: RenderOptions = {}
+): Promise<number> {
+  return invoke<number>('start_progressive_render', { docId, pageIndex, scale, options });
+}
+
+/**
+ * Fetch the latest frame of a progressive render. Fetching the final frame
+ * releases the render.
+ */
+export async function getProgressiveFrame(renderId: number): Promise<RgbaRender> {
+  const buffer = await invoke<ArrayBuffer>('get_progressive_frame', { renderId });
+  return decodeBinaryRender(buffer);
+}
+
+export async function pauseProgressiveRender(renderId: number): Promise<void> {
+  return invoke('pause_progressive_render', { renderId });
+}
+
+export async function resumeProgressiveRender(renderId: number): Promise<void> {
+  return invoke('resume_progressive_render', { renderId });
+}
+
+/** Cancel a progressive render, or release a completed one. */
+export async function cancelProgressiveRender(renderId: number): Promise<void> {
+  return invoke('cancel_progressive_render', { renderId });
+}
+
+/** Subscribe to progressive render updates. */
+export async function onProgressiveRender(
+  handler: (update: ProgressiveUpdate) => void
+): Promise<UnlistenFn> {
+  return listen<ProgressiveUpdate>('progressive-render', (event) => handler(event.payload));
+}
+
+/**
+ * Decode a binary render response: `[width u32][height u32][stride u32]`
+ * (little-endian) followed by `stride * height` bytes of RGBA pixels.
+ */
+export function decodeBinaryRender(buffer: ArrayBuffer): RgbaRender {
+  const header = new DataView(buffer, 0, BINARY_RENDER_HEADER_LEN);
+  const width = header.getUint32(0, true);
+  const height = header.getUint32(4, true);
+  const stride = header.getUint32(8, true);
+  const rowBytes = width * 4;
+
+  if (stride === rowBytes) {
+    return {
+      pixels: new Uint8ClampedArray(buffer, BINARY_RENDER_HEADER_LEN, rowBytes * height),
+      width,
+      height,
+    };
+  }
+
+  // Padded rows: repack into a tightly packed buffer for ImageData.
+  const pixels = new Uint8ClampedArray(new ArrayBuffer(rowBytes * height));
+  for (let row = 0; row < height; row++) {
+    const start = BINARY_RENDER_HEADER_LEN + row * stride;
+    pixels.set(new Uint8ClampedArray(buffer, start, rowBytes), row * rowBytes);
+  }
+  return { pixels, width, height };
+}
+
+/**
+ * Build a kiosk:// URL for a rendered page image, usable directly as an
+ * <img> src or with fetch(). `rgba` responses use the same layout as
+ * renderPageBinary and can be decoded with decodeBinaryRender.
+ *
+ * convertFileSrc supplies the platform-specific origin
+ * (kiosk://localhost/ or http://kiosk.localhost/ on Windows).
+ */
+export function pageImageUrl(
+  docId: string,
+  pageIndex: number,
+  scale: number,
+  format: PageImageFormat = 'png',
+  options: RenderOptions = {}
+): string {
+  const base = convertFileSrc('', 'kiosk');
+  const hex = (rgb: number[]) => rgb.map((c) => c.toString(16).padStart(2, '0')).join('');
+  const flag = (value: boolean) => (value ? '1' : '0');
+  const query = new URLSearchParams({ scale: String(scale), format });
+
+  const color = options.color ?? { mode: 'normal' };
+  query.set('color', color.mode);
+  if (color.mode === 'inverted' && color.preserve_images) {
+    query.set('preserve_images', '1');
+  } else if (color.mode === 'custom') {
+    query.set('paper', hex(color.paper));
+    query.set('ink', hex(color.ink));
+  }
+
+  const flags: Array<[string, boolean | undefined]> = [
+    ['annotations', options.annotations],
+    ['forms', options.forms],
+    ['aa_text', options.antialias_text],
+    ['aa_paths', options.antialias_paths],
+    ['aa_images', options.antialias_images],
+    ['transparent', options.transparent],
+    ['grayscale', options.grayscale],
+  ];
+  for (const [key, value] of flags) {
+    if (value !== undefined) query.set(key, flag(value));
+  }
+  if (options.background) query.set('background', hex(options.background));
+  if (options.rotation) query.set('rotation', String(options.rotation));
+  if (options.layer) query.set('layer', options.layer);
+
+  return `${base}render/${encodeURIComponent(docId)}/${pageIndex}?${query}`;
+}
+
+/**
+ * Render only a rectangular region (tile) of a page to raw RGBA pixels.
+ * Used for deep zoom so only the visible tiles are rasterized.
+ *
+ * @param docId - Document ID from loadPdf
+ * @param pageIndex - 0-based page index
+ * @param region - Region in PDF points (top-left origin of the rotated view)
+ * @param scale - Render scale (1.0 = 72 DPI, 2.0 = 144 DPI)
+ * @param options - Render options; form fields are never drawn in tiles
+ * @returns RegionRenderResult with base64-encoded RGBA pixels and tile origin
+ */
+export async function renderPageRegion(
+  docId: string,
+  pageIndex: number,
+  region: TextRect,
+  scale: number,
+  options: RenderOptions = {},
+  scheduling: RenderScheduling = {}
+): Promise<RegionRenderResult> {
+  return invoke<RegionRenderResult>('render_page_region', {
+    docId,
+    pageIndex,
+    x: region.x,
+    y: region.y,
+    width: region.width,
+    height: region.height,
+    scale,
+    options,
+    priority: scheduling.priority ?? null,
+    cycleId: scheduling.cycleId ?? null,
+  });
+}
+
+/**
+ * Render compact encoded thumbnails for a batch of pages.
+ * Results are cached on disk by the backend, keyed by document content,
+ * so reopening a document returns them without rendering.
+ *
+ * @param docId - Document ID from loadPdf
+ * @param pages - 0-based page indices
+ * @param maxDim - Maximum width/height of each thumbnail in pixels
+ * @param format - 'jpeg' (default), 'webp' or 'png'
+ * @param options - Render options; embedded thumbnails are only used with
+ *   the defaults
+ */
+export async function renderThumbnails(
+  docId: string,
+  pages: number[],
+  maxDim: number,
+  format: Exclude<PageImageFormat, 'rgba' | 'tiff'> = 'jpeg',
+  options: RenderOptions = {}
+): Promise<ThumbnailResult[]> {
+  return invoke<ThumbnailResult[]>('render_thumbnails', { docId, pages, maxDim, format, options });
+}
+
+/** Decode a thumbnail's image data into an ImageBitmap. */
+export async function decodeThumbnail(thumbnail: ThumbnailResult): Promise<ImageBitmap> {
+  const bytes = Uint8Array.from(atob(thumbnail.data), (c) => c.charCodeAt(0));
+  const mime = thumbnail.format === 'jpeg' ? 'image/jpeg' : `image/${thumbnail.format}`;
+  return createImageBitmap(new Blob([bytes], { type: mime }));
+}
+
+/**
+ * Options for exportPagesAsImages. Only directory is required.
+ *
+ * pattern names each file without extension: {name} is the document's
+ * file name and {page} the 1-based page number, zero-padded so files sort
+ * in page order (default "{name}-{page}").
+ */
+export interface ImageExportOptions {
+  /** Page range such as "1-3,5,8-" (1-based); all pages when omitted */
+  pages?: string;
+  /** Resolution in dots per inch (default 150, at most 1200) */
+  dpi?: number;
+  format?: Exclude<PageImageFormat, 'rgba'>;
+  /** JPEG quality 1-100 (default 85); other formats are lossless */
+  quality?: number;
+  /** Destination folder, created if missing */
+  directory: string;
+  pattern?: string;
+  /** Burn annotations into the images (default true) */
+  annotations?: boolean;
+  /** Apply the document's accessibility filter, as in the viewer (default true) */
+  apply_view_filters?: boolean;
+  /** Replace existing files; otherwise their pages fail (default false) */
+  overwrite?: boolean;
+}
+
+/** Progress of an export, sent after every page */
+export interface ExportProgress {
+  doc_id: string;
+  page_index: number;
+  completed: number;
+  total: number;
+  /** Path of the written file, or null when the page failed */
+  path: string | null;
+}
+
+/** Outcome of exportPagesAsImages */
+export interface ExportResult {
+  /** Written files, in page order */
+  files: string[];
+  /** 0-based indices of pages that failed */
+  failed: number[];
+}
+
+/**
+ * Render pages to image files, as shown in the viewer (view rotation and
+ * accessibility filter included). Listen with onExportProgress for
+ * per-page progress.
+ */
+export async function exportPagesAsImages(
+  docId: string,
+  options: ImageExportOptions
+): Promise<ExportResult> {
+  return invoke<ExportResult>('export_pages_as_images', { docId, options });
+}
+
+/** Subscribe to export progress events. */
+export async function onExportProgress(
+  handler: (progress: ExportProgress) => void
+): Promise<UnlistenFn> {
+  return listen<ExportProgress>('export-progress', (event) => handler(event.payload));
+}
+
+/**
+ * Get render cache statistics for one document, or aggregated over all
+ * open documents when docId is omitted.
+ */
+export async function getRenderCacheStats(docId?: string): Promise<RenderCacheStats> {
+  return invoke<RenderCacheStats>('get_render_cache_stats', { docId: docId ?? null });
+}
+
+/**
+ * Set the global render cache budget in bytes. Shrinking the budget evicts
+ * least recently used bitmaps immediately.
+ */
+export async function setRenderCacheBudget(budgetBytes: number): Promise<void> {
+  return invoke('set_render_cache_budget', { budgetBytes });
+}
+
+/**
+ * Set the largest bitmap, in pixels, a single render may allocate (default
+ * 64 MP). Larger pages are downscaled to fit; larger tiles and exports fail.
+ */
+export async function setMaxRenderPixels(maxPixels: number): Promise<void> {
+  return invoke('set_max_render_pixels', { maxPixels });
+}
+
+/**
+ * Enable out-of-process render workers (count > 0) or disable them (0).
+ * Workers render pages of one document in parallel and isolate pdfium
+ * crashes; password-protected documents always render in process.
+ */
+export async function setRenderWorkers(count: number): Promise<void> {
+  return invoke('set_render_workers', { count });
+}
+
+/** Scroll direction reported with a viewport hint */
+export type ScrollDirection = 'forward' | 'backward' | 'none';
+
+/**
+ * Report the current viewport so the backend can prefetch neighbouring
+ * pages into its render cache. Each call replaces the previous hint.
+ *
+ * @param docId - Document ID from loadPdf
+ * @param visiblePages - 0-based indices of the visible pages
+ * @param direction - Current scroll direction
+ * @param scale - Render scale the viewer requests for these pages
+ * @param options - Render options the viewer requests for these pages
+ * @param prefetchPages - Pages to prefetch on each side (backend default 2)
+ */
+export async function setViewportHint(
+  docId: string,
+  visiblePages: number[],
+  direction: ScrollDirection,
+  scale: number,
+  options: RenderOptions = {},
+  prefetchPages?: number
+): Promise<void> {
+  return invoke('set_viewport_hint', {
+    docId,
+    visiblePages,
+    direction,
+    scale,
+    options,
+    prefetchPages: prefetchPages ?? null,
+  });
 }
 
 /**
@@ -193,22 +819,135 @@ export async function getPageText(docId: string, pageIndex: number): Promise<str
 }
 
 /**
- * Search for text across all pages.
+ * How a search query is read:
+ * - 'text': the query is a literal string (default)
+ * - 'regex': a regular expression, e.g. \d+\.\d+\(a\)
+ * - 'query': terms that must all occur on a page, "quoted phrases" as single
+ *   terms and OR between alternatives, e.g. "force majeure" OR act of god
+ */
+export type SearchMode = 'text' | 'regex' | 'query' | 'fuzzy';
+
+/** Differences between query and page text that a search ignores */
+export interface SearchNormalization {
+  /** Compatibility forms (full-width letters, ligatures, superscripts) */
+  compatibility?: boolean;
+  /** Accents and other combining marks */
+  ignore_diacritics?: boolean;
+  /** Typographic ligatures such as "ﬁ" only */
+  expand_ligatures?: boolean;
+  /** Line breaks match a space; words hyphenated at a line end match whole */
+  join_lines?: boolean;
+}
+
+/** Query mode and page filter for searchText */
+export interface SearchFilter {
+  mode?: SearchMode;
+  /** 1-based page range such as "1-3,5,8-"; all pages when omitted */
+  pages?: string;
+  normalization?: SearchNormalization;
+  /** Only matches that start and end at word boundaries */
+  wholeWord?: boolean;
+  /** Edits a fuzzy match may differ by; a quarter of the query length by default, always under half of it */
+  maxEdits?: number;
+}
+
+/**
+ * Search for text across all pages. Fuzzy results come best score first.
  */
 export async function searchText(
   docId: string,
   query: string,
   caseSensitive: boolean = false,
-  maxResults: number = 50
+  maxResults: number = 50,
+  filter: SearchFilter = {}
 ): Promise<SearchResult[]> {
   return invoke<SearchResult[]>('search_text', {
     docId,
     query,
     caseSensitive,
     maxResults,
+    mode: filter.mode ?? null,
+    pages: filter.pages ?? null,
+    normalization: filter.normalization ?? null,
+    wholeWord: filter.wholeWord ?? null,
+    maxEdits: filter.maxEdits ?? null,
+  });
+}
+
+/** Stage of a search session */
+export type SearchStatus = 'searching' | 'complete' | 'cancelled';
+
+/** Sent for each page with matches, periodically as pages are searched, and at the end */
+export interface SearchUpdate {
+  search_id: number;
+  doc_id: string;
+  status: SearchStatus;
+  /** Results found since the previous update */
+  results: SearchResult[];
+  pages_searched: number;
+  page_count: number;
+  total_results: number;
+  /** The result limit was reached before the last page */
+  truncated: boolean;
+}
+
+/** A slice of a search session's results */
+export interface SearchResultsPage {
+  offset: number;
+  results: SearchResult[];
+  total_results: number;
+  pages_searched: number;
+  page_count: number;
+  status: SearchStatus;
+  truncated: boolean;
+}
+
+/**
+ * Start a streaming search and return its id. Results arrive through
+ * onSearchProgress and stay available to getSearchResults until the search
+ * is cancelled or the document closed. maxResults defaults to 10000.
+ */
+export async function startSearch(
+  docId: string,
+  query: string,
+  caseSensitive: boolean = false,
+  maxResults?: number,
+  filter: SearchFilter = {}
+): Promise<number> {
+  return invoke<number>('start_search', {
+    docId,
+    query,
+    caseSensitive,
+    maxResults: maxResults ?? null,
+    mode: filter.mode ?? null,
+    pages: filter.pages ?? null,
+    normalization: filter.normalization ?? null,
+    wholeWord: filter.wholeWord ?? null,
+    maxEdits: filter.maxEdits ?? null,
   });
 }
 
+/** Page through a search's results, also while it is running. */
+export async function getSearchResults(
+  searchId: number,
+  offset: number = 0,
+  limit: number = 50
+): Promise<SearchResultsPage> {
+  return invoke<SearchResultsPage>('get_search_results', { searchId, offset, limit });
+}
+
+/** Cancel a search, or release a finished one. */
+export async function cancelSearch(searchId: number): Promise<void> {
+  return invoke('cancel_search', { searchId });
+}
+
+/** Subscribe to search session updates. */
+export async function onSearchProgress(
+  handler: (update: SearchUpdate) => void
+): Promise<UnlistenFn> {
+  return listen<SearchUpdate>('search-progress', (event) => handler(event.payload));
+}
+
 // ============================================================================
 // Annotation Types
 // ============================================================================
@@ -8,11 +8,17 @@ import {
   loadPdf,
   loadPdfBytes,
   closePdf,
-  renderPage,
+  renderPageBinary,
+  cancelRenders,
+  isRenderCancelled,
+  setViewportHint,
+  setAccessibilityFilter as apiSetAccessibilityFilter,
+  setViewRotation,
+  renderThumbnails,
+  decodeThumbnail,
   getCharRects,
   getAllPageInfos,
   searchText,
-  decodeBase64Rgba,
   createRgbaImageData,
   fileToBytes,
   type LoadResult,
@@ -20,13 +26,18 @@ import {
   type PageInfo,
   type CharRect,
   type SearchResult,
-  type RenderResult,
+  type RgbaRender,
+  type ScrollDirection,
+  type RenderOptions,
+  type AccessibilityFilter,
+  type ViewRotation,
   type AnnotationData,
   type AnnotationColor,
 } from './pdf-api';
 
 import {
   initAnnotations,
+  updateAnnotationPageInfos,
   resetAnnotations,
   setAnnotationTool,
   getAnnotationTool,
@@ -63,9 +74,15 @@ const PINCH_SENSITIVITY = 0.01; // Sensitivity for trackpad pinch gesture
  */
 const DEBUG_RENDER_DIAGNOSTICS = true;
 
-/** Maximum number of concurrent thumbnail IPC render calls. */
+/** Maximum number of concurrent thumbnail batch IPC calls. */
 const THUMBNAIL_CONCURRENCY = 2;
 
+/** Pages per render_thumbnails batch. */
+const THUMBNAIL_BATCH_SIZE = 12;
+
+/** Longer side of backend thumbnails in pixels (covers 2x DPR at scale 0.2). */
+const THUMBNAIL_MAX_DIM = 256;
+
 /** Phase 2: time (ms) after last interaction before upgrading to high-res. */
 const SETTLE_DELAY = 200;
 
@@ -97,6 +114,10 @@ interface ViewerState {
   lastError: string | null;
   // Display mode
   displayMode: DisplayMode;
+  // Accessibility filter of the open document (applied by the backend)
+  accessibilityFilter: AccessibilityFilter;
+  // View rotation of all pages (per-page rotations live in PageInfo)
+  viewRotation: ViewRotation;
   // Layout state
   sidebarVisible: boolean;
   // Phase 1: render cycle tracking to discard stale IPC results
@@ -124,6 +145,8 @@ const state: ViewerState = {
   gestureStartScale: DEFAULT_ZOOM,
   lastError: null,
   displayMode: (localStorage.getItem('kiosk-display-mode') as DisplayMode) || 'light',
+  accessibilityFilter: { filter: 'none' },
+  viewRotation: 0,
   sidebarVisible: true,
   renderCycleId: 0,
   renderState: 'IDLE' as RenderState,
@@ -154,6 +177,11 @@ let settleTimerId: number | undefined;
 let upgradeQueue: number[] = [];
 let upgradeInFlight = false;
 
+/** Last observed scrollTop, used to derive the scroll direction. */
+let lastScrollTop = 0;
+/** Direction of the most recent scroll, reported with viewport hints. */
+let scrollDirection: ScrollDirection = 'none';
+
 // ============================================================================
 // Phase 4.5 — Live GPU Transform Zoom State
 // ============================================================================
@@ -192,6 +220,10 @@ function markInteraction(reason: 'scroll' | 'zoom'): void {
   // Cancel any in-progress upgrade immediately
   cancelUpgrade();
 
+  if (reason === 'zoom') {
+    scrollDirection = 'none';
+  }
+
   const target: RenderState = reason === 'scroll' ? 'SCROLLING' : 'ZOOMING';
   if (state.renderState !== target) {
     transitionRenderState(target);
@@ -222,6 +254,7 @@ function scheduleSettle(): void {
     // Brief SETTLING → IDLE
     transitionRenderState('IDLE');
     maybeStartUpgrade();
+    reportViewportHint();
   }, SETTLE_DELAY);
 }
 
@@ -293,6 +326,31 @@ function maybeStartUpgrade(): void {
   drainUpgradeQueue();
 }
 
+/**
+ * Tell the backend which pages are visible at which high-res scale so it
+ * can prefetch the neighbouring pages into its render cache while idle.
+ * The backend yields prefetch work to our own render calls.
+ */
+function reportViewportHint(): void {
+  if (!state.docId) return;
+
+  const dpr = window.devicePixelRatio || 1;
+  const effectiveDpr = isDeepZoom() ? Math.min(dpr, 1.5) : dpr;
+  const targetScale = state.scale * effectiveDpr;
+  const visibleRange = getVisiblePageRange();
+
+  const visiblePages: number[] = [];
+  for (let i = visibleRange.start; i <= visibleRange.end; i++) {
+    if (i >= 0 && i < state.pages.length) visiblePages.push(i);
+  }
+
+  // Phase 5: in deep zoom each page is expensive; don't prefetch neighbours
+  const prefetchPages = isDeepZoom() ? 0 : RENDER_BUFFER;
+  diag(`viewport hint pages=[${visiblePages.join(',')}] dir=${scrollDirection} scale=${targetScale.toFixed(2)}`);
+  setViewportHint(state.docId, visiblePages, scrollDirection, targetScale, pageRenderOptions(), prefetchPages)
+    .catch((err) => diag(`viewport hint failed: ${err}`));
+}
+
 /**
  * Process the upgrade queue one page at a time.
  * Respects renderCycleId from Phase 1 to discard stale results.
@@ -321,7 +379,10 @@ async function drainUpgradeQueue(): Promise<void> {
     const renderScale = state.scale * effectiveDpr;
 
     diagP2(`HIGH-RES upgrade START page=${pageIndex} scale=${renderScale.toFixed(2)} cycleId=${cycleId}`);
-    const result: RenderResult = await renderPage(state.docId!, pageIndex, renderScale);
+    const result: RgbaRender = await renderPageBinary(state.docId!, pageIndex, renderScale, pageRenderOptions(), {
+      priority: 'normal',
+      cycleId,
+    });
 
     // Check if still valid after IPC round-trip
     if (cycleId !== state.renderCycleId || state.renderState !== 'UPGRADING') {
@@ -337,9 +398,8 @@ async function drainUpgradeQueue(): Promise<void> {
       return;
     }
 
-    // Phase 4: decode RGBA → ImageData → createImageBitmap → canvas
-    const rgba = decodeBase64Rgba(result.pixels);
-    const imageData = createRgbaImageData(rgba, result.width, result.height);
+    // Binary IPC: RGBA view → ImageData → createImageBitmap → canvas
+    const imageData = createRgbaImageData(result.pixels, result.width, result.height);
     const bitmap = await createImageBitmap(imageData);
 
     // Re-check staleness after async createImageBitmap
@@ -366,7 +426,11 @@ async function drainUpgradeQueue(): Promise<void> {
 
     diagP2(`HIGH-RES upgrade applied page=${pageIndex} ${result.width}x${result.height}`);
   } catch (err) {
-    console.error(`Failed to upgrade page ${pageIndex}:`, err);
+    if (isRenderCancelled(err)) {
+      diagP2(`HIGH-RES upgrade CANCELLED by backend page=${pageIndex}`);
+    } else {
+      console.error(`Failed to upgrade page ${pageIndex}:`, err);
+    }
   } finally {
     upgradeInFlight = false;
     drainUpgradeQueue();
@@ -895,6 +959,9 @@ function hidePasswordModal(): void {
 
 async function initializeDocument(result: LoadResult): Promise<void> {
   state.docId = result.id;
+  // Filters are per document; a newly loaded one starts unfiltered
+  state.accessibilityFilter = { filter: 'none' };
+  state.viewRotation = 0;
   state.pages = await getAllPageInfos(result.id);
   state.currentPage = 0;
 
@@ -1088,7 +1155,10 @@ async function renderPageToContainer(pageIndex: number, cycleId: number, lowRes
     }
 
     diag(`render START page=${pageIndex} scale=${renderScale.toFixed(2)} cycleId=${cycleId}`);
-    const result: RenderResult = await renderPage(state.docId, pageIndex, renderScale);
+    const result: RgbaRender = await renderPageBinary(state.docId, pageIndex, renderScale, pageRenderOptions(), {
+      priority: 'high',
+      cycleId,
+    });
 
     // ── Phase 1: stale-render guard ──────────────────────────────────
     if (cycleId !== state.renderCycleId) {
@@ -1103,9 +1173,8 @@ async function renderPageToContainer(pageIndex: number, cycleId: number, lowRes
       return;
     }
 
-    // Phase 4: decode base64 → RGBA → ImageData → createImageBitmap → canvas
-    const rgba = decodeBase64Rgba(result.pixels);
-    const imageData = createRgbaImageData(rgba, result.width, result.height);
+    // Binary IPC: RGBA view → ImageData → createImageBitmap → canvas
+    const imageData = createRgbaImageData(result.pixels, result.width, result.height);
     const bitmap = await createImageBitmap(imageData);
 
     // Re-check staleness after async createImageBitmap
@@ -1140,7 +1209,11 @@ async function renderPageToContainer(pageIndex: number, cycleId: number, lowRes
       buildTextOverlay(pageIndex, rects);
     }
   } catch (err) {
-    console.error(`Failed to render page ${pageIndex}:`, err);
+    if (isRenderCancelled(err)) {
+      diag(`render CANCELLED by backend page=${pageIndex} cycleId=${cycleId}`);
+    } else {
+      console.error(`Failed to render page ${pageIndex}:`, err);
+    }
   }
 }
 
@@ -1167,6 +1240,11 @@ function clearRenderedPages(): void {
   // the previous cycle will be discarded on arrival.
   state.renderCycleId++;
   diag(`clearRenderedPages: new cycleId=${state.renderCycleId}`);
+  // Drop renders of the old cycle still queued in the backend
+  if (state.docId) {
+    cancelRenders(state.docId, state.renderCycleId)
+      .catch((err) => diag(`cancelRenders failed: ${err}`));
+  }
 
   // Phase 4: no blob URLs to revoke — just clear the set and canvas elements
   for (const pageIndex of state.renderedPages) {
@@ -1221,21 +1299,22 @@ function buildTextOverlay(pageIndex: number, rects: CharRect[]): void {
 /**
  * Phase 1: thumbnail render queue with bounded concurrency.
  * Prevents the IPC stampede diagnosed in M-5 / C-3.
+ * Pages are requested in batches of THUMBNAIL_BATCH_SIZE.
  */
-let thumbnailQueue: Array<{ pageIndex: number; canvas: HTMLCanvasElement; scale: number }> = [];
+let thumbnailQueue: Array<{ pageIndex: number; canvas: HTMLCanvasElement }> = [];
 let thumbnailsInFlight = 0;
 let thumbnailDocId: string | null = null;
 
 /**
- * Drain the thumbnail queue, launching up to THUMBNAIL_CONCURRENCY renders
- * at a time. Each completion triggers the next item in the queue.
+ * Drain the thumbnail queue, launching up to THUMBNAIL_CONCURRENCY batches
+ * at a time. Each completion triggers the next batch in the queue.
  */
 function drainThumbnailQueue(): void {
   while (thumbnailsInFlight < THUMBNAIL_CONCURRENCY && thumbnailQueue.length > 0) {
-    const item = thumbnailQueue.shift()!;
+    const batch = thumbnailQueue.splice(0, THUMBNAIL_BATCH_SIZE);
     thumbnailsInFlight++;
-    diag(`thumbnail START page=${item.pageIndex} queueLen=${thumbnailQueue.length} inFlight=${thumbnailsInFlight}`);
-    renderThumbnailThrottled(item.pageIndex, item.canvas, item.scale)
+    diag(`thumbnail batch START pages=${batch[0].pageIndex}..${batch[batch.length - 1].pageIndex} queueLen=${thumbnailQueue.length} inFlight=${thumbnailsInFlight}`);
+    renderThumbnailBatch(batch)
       .finally(() => {
         thumbnailsInFlight--;
         drainThumbnailQueue();
@@ -1281,7 +1360,7 @@ async function createThumbnails(): Promise<void> {
     thumbnailsContainer.appendChild(thumbEl);
 
     // Phase 1: enqueue instead of firing immediately
-    thumbnailQueue.push({ pageIndex: i, canvas, scale: thumbnailScale });
+    thumbnailQueue.push({ pageIndex: i, canvas });
   }
 
   diag(`thumbnails: enqueued ${thumbnailQueue.length} items, concurrency=${THUMBNAIL_CONCURRENCY}`);
@@ -1293,47 +1372,51 @@ async function createThumbnails(): Promise<void> {
 }
 
 /**
- * Render a single thumbnail via IPC. If the document has changed since the
- * queue was created, the render is skipped.
+ * Render a batch of thumbnails via one IPC call. If the document has
+ * changed since the queue was created, the batch is skipped.
  *
- * Phase 4: paints to canvas instead of setting img.src with blob URL.
+ * The backend returns compact JPEGs (from its disk cache when possible),
+ * decoded here with createImageBitmap and painted to each canvas.
  */
-async function renderThumbnailThrottled(pageIndex: number, canvas: HTMLCanvasElement, scale: number): Promise<void> {
+async function renderThumbnailBatch(
+  batch: Array<{ pageIndex: number; canvas: HTMLCanvasElement }>
+): Promise<void> {
   // Skip if document changed while queued
   if (!state.docId || state.docId !== thumbnailDocId) {
-    diag(`thumbnail SKIPPED (doc changed) page=${pageIndex}`);
+    diag(`thumbnail batch SKIPPED (doc changed) size=${batch.length}`);
     return;
   }
 
   try {
-    const result: RenderResult = await renderPage(state.docId, pageIndex, scale);
+    const pages = batch.map((item) => item.pageIndex);
+    const thumbnails = await renderThumbnails(state.docId, pages, THUMBNAIL_MAX_DIM);
 
     // Verify document still matches after IPC round-trip
     if (state.docId !== thumbnailDocId) {
-      diag(`thumbnail DISCARDED (doc changed after IPC) page=${pageIndex}`);
-      return;
-    }
-
-    // Verify canvas element is still in the DOM
-    if (!canvas.isConnected) {
-      diag(`thumbnail DISCARDED (canvas disconnected) page=${pageIndex}`);
+      diag(`thumbnail batch DISCARDED (doc changed after IPC) size=${batch.length}`);
       return;
     }
 
-    // Phase 4: decode RGBA → ImageData → createImageBitmap → canvas
-    const rgba = decodeBase64Rgba(result.pixels);
-    const imageData = createRgbaImageData(rgba, result.width, result.height);
-    const bitmap = await createImageBitmap(imageData);
+    const canvases = new Map(batch.map((item) => [item.pageIndex, item.canvas]));
+    for (const thumbnail of thumbnails) {
+      const canvas = canvases.get(thumbnail.page_index);
+      // Verify canvas element is still in the DOM
+      if (!canvas || !canvas.isConnected) {
+        diag(`thumbnail DISCARDED (canvas disconnected) page=${thumbnail.page_index}`);
+        continue;
+      }
 
-    canvas.width = result.width;
-    canvas.height = result.height;
-    const ctx = canvas.getContext('2d');
-    if (ctx) {
-      ctx.drawImage(bitmap, 0, 0);
+      const bitmap = await decodeThumbnail(thumbnail);
+      canvas.width = thumbnail.width;
+      canvas.height = thumbnail.height;
+      const ctx = canvas.getContext('2d');
+      if (ctx) {
+        ctx.drawImage(bitmap, 0, 0);
+      }
+      bitmap.close();
     }
-    bitmap.close();
   } catch (err) {
-    console.error(`Failed to render thumbnail ${pageIndex}:`, err);
+    console.error(`Failed to render thumbnails ${batch[0].pageIndex}..${batch[batch.length - 1].pageIndex}:`, err);
   }
 }
 
@@ -1394,6 +1477,12 @@ function handleScroll(): void {
 
   markInteraction('scroll');
 
+  const scrollTop = viewerContainer.scrollTop;
+  if (scrollTop !== lastScrollTop) {
+    scrollDirection = scrollTop > lastScrollTop ? 'forward' : 'backward';
+    lastScrollTop = scrollTop;
+  }
+
   const range = getVisiblePageRange();
   if (range.start !== state.currentPage) {
     state.currentPage = range.start;
@@ -1609,6 +1698,9 @@ function handleKeydown(e: KeyboardEvent): void {
   } else if (isMeta && e.key === 'f') {
     e.preventDefault();
     searchInput?.focus();
+  } else if (isMeta && (e.key === 'r' || e.key === 'l')) {
+    e.preventDefault();
+    rotateView(e.key === 'r');
   } else if (e.key === 'ArrowLeft' || e.key === 'PageUp') {
     prevPage();
   } else if (e.key === 'ArrowRight' || e.key === 'PageDown') {
@@ -1901,15 +1993,39 @@ function createInversionOverlay(): void {
  * Set the display mode and apply appropriate styles.
  */
 export function setDisplayMode(mode: DisplayMode): void {
+  const wasInverted = isNativelyInverted();
   state.displayMode = mode;
   localStorage.setItem('kiosk-display-mode', mode);
   applyDisplayMode(mode);
   console.log('[Kiosk] Display mode set to:', mode);
+
+  // Page colors are baked into the rendered pixels, so re-render on change
+  if (state.docId && isNativelyInverted() !== wasInverted) {
+    clearRenderedPages();
+    renderVisiblePages();
+  }
+}
+
+/**
+ * Whether page renders are inverted by the backend. Night mode inverts page
+ * content natively (hue preserved, photos left as authored) instead of with
+ * a CSS filter.
+ */
+function isNativelyInverted(): boolean {
+  return state.displayMode === 'night';
+}
+
+/**
+ * Render options for the viewer's page renders and prefetch hints.
+ */
+function pageRenderOptions(): RenderOptions {
+  return isNativelyInverted() ? { color: { mode: 'inverted', preserve_images: true } } : {};
 }
 
 /**
  * Apply display mode styles without re-rendering PDF.
- * Note: The CSS handles the inversion via body.mode-night #pages-container selector.
+ * Note: Page pixels are inverted by the backend (see pageRenderOptions); the
+ * CSS only inverts thumbnails in night mode.
  */
 function applyDisplayMode(mode: DisplayMode): void {
   const body = document.body;
@@ -1939,6 +2055,60 @@ export function getDisplayMode(): DisplayMode {
   return state.displayMode;
 }
 
+/**
+ * Select the accessibility filter (high contrast, grayscale, color vision)
+ * for the open document and re-render pages and thumbnails with it.
+ */
+export async function setAccessibilityFilter(filter: AccessibilityFilter): Promise<void> {
+  if (!state.docId) return;
+  await apiSetAccessibilityFilter(state.docId, filter);
+  state.accessibilityFilter = filter;
+  console.log('[Kiosk] Accessibility filter set to:', filter);
+
+  clearRenderedPages();
+  renderVisiblePages();
+  createThumbnails();
+}
+
+/**
+ * Get the accessibility filter of the open document.
+ */
+export function getAccessibilityFilter(): AccessibilityFilter {
+  return state.accessibilityFilter;
+}
+
+/**
+ * Rotate the view a quarter turn, of one page or (by default) of every
+ * page. The file is not modified.
+ */
+export async function rotateView(clockwise = true, pageIndex?: number): Promise<void> {
+  if (!state.docId) return;
+  const current = pageIndex === undefined
+    ? state.viewRotation
+    : state.pages[pageIndex]?.view_rotation ?? 0;
+  const rotation = ((current + (clockwise ? 90 : 270)) % 360) as ViewRotation;
+  await setViewRotation(state.docId, rotation, pageIndex);
+  if (pageIndex === undefined) state.viewRotation = rotation;
+
+  // Page sizes, char rects and search rects all change with the rotation
+  const currentPage = state.currentPage;
+  clearRenderedPages();
+  state.pages = await getAllPageInfos(state.docId);
+  updateAnnotationPageInfos(state.pages);
+  createPageContainers();
+  goToPage(currentPage);
+  renderVisiblePages();
+  createThumbnails();
+  if (state.searchQuery) handleSearch();
+}
+
+/**
+ * Get the view rotation applied to every page.
+ */
+export function getViewRotation(): ViewRotation {
+  return state.viewRotation;
+}
+
 /**
  * Cycle through display modes: light -> dark -> night -> light
  */